@@ -0,0 +1,285 @@
+//! Blurred copy of the scene behind the UI, used to give translucent panels a frosted glass look.
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    create_render_pipeline, egui, RenderPass, ScreenDescriptor, SizedBuffer, TextureParams,
+};
+
+/// Number of taps on each side of the blur kernel. Needs to match `blur.frag`.
+const BLUR_TAPS: f32 = 8.0;
+
+/// Format of the blurred textures.
+const BACKDROP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Settings of the backdrop blur.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackdropBlurOptions {
+    /// Factor by which the scene is downscaled before blurring. Larger values are faster and blurrier.
+    pub downsample: u32,
+    /// Blur radius in pixels of the downscaled texture.
+    pub radius: f32,
+}
+
+impl Default for BackdropBlurOptions {
+    fn default() -> Self {
+        Self {
+            downsample: 2,
+            radius: 8.0,
+        }
+    }
+}
+
+/// GPU resources of the backdrop blur.
+pub(crate) struct Backdrop {
+    pub(crate) id: u64,
+    size: (u32, u32),
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    horizontal_uniform: SizedBuffer,
+    vertical_uniform: SizedBuffer,
+    horizontal_view: Option<wgpu::TextureView>,
+}
+
+impl Backdrop {
+    /// Creates the blur resources. The blurred texture is registered with `id` by the first update.
+    pub(crate) fn new(device: &wgpu::Device, id: u64) -> Self {
+        let vs_module = device.create_shader_module(&include_shader!("blur.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("blur.frag"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("egui_blur_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                        ty: wgpu::BufferBindingType::Uniform,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("egui_blur_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_render_pipeline(
+            device,
+            "egui_blur_pipeline",
+            &pipeline_layout,
+            &vs_module,
+            &fs_module,
+            &[],
+            wgpu::ColorTargetState {
+                format: BACKDROP_FORMAT,
+                color_blend: wgpu::BlendState::REPLACE,
+                alpha_blend: wgpu::BlendState::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            },
+            None,
+            1,
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("egui_blur_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let create_uniform = |label| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(&[0.0f32; 4]),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            });
+            SizedBuffer {
+                buffer,
+                size: 4 * 4,
+            }
+        };
+
+        Self {
+            id,
+            size: (0, 0),
+            pipeline,
+            bind_group_layout,
+            sampler,
+            horizontal_uniform: create_uniform("egui_blur_horizontal_uniform_buffer"),
+            vertical_uniform: create_uniform("egui_blur_vertical_uniform_buffer"),
+            horizontal_view: None,
+        }
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        uniform: &SizedBuffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("egui_blur_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &uniform.buffer,
+                        offset: 0,
+                        size: None,
+                    },
+                },
+            ],
+        })
+    }
+
+    fn blur_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            label: Some("egui blur render pass"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_blur_texture(device: &wgpu::Device, size: (u32, u32), label: &str) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: BACKDROP_FORMAT,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::RENDER_ATTACHMENT,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+impl RenderPass {
+    /// Blurs `scene` into the backdrop texture and returns its id, which stays the same between calls.
+    ///
+    /// Meshes drawn with the backdrop texture sample it at their position on the screen instead of
+    /// their UVs, so any shape painted with it shows the blurred scene behind it. `scene` needs to be
+    /// sampleable, filterable and cover the whole output. Should be called after the scene is rendered
+    /// and before `execute()`.
+    pub fn update_backdrop(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::TextureView,
+        screen_descriptor: &ScreenDescriptor,
+        options: &BackdropBlurOptions,
+    ) -> egui::TextureId {
+        if self.backdrop.is_none() {
+            let id = self.allocate_user_texture_id();
+            self.backdrop = Some(Backdrop::new(device, id));
+        }
+
+        let downsample = options.downsample.max(1);
+        let size = (
+            (screen_descriptor.physical_width / downsample).max(1),
+            (screen_descriptor.physical_height / downsample).max(1),
+        );
+
+        let backdrop = self.backdrop.as_ref().unwrap();
+        let id = backdrop.id;
+        let user_texture_missing = self.user_texture(id).is_none();
+        if backdrop.size != size || user_texture_missing {
+            let horizontal_view = create_blur_texture(device, size, "egui_backdrop_horizontal");
+            let view = create_blur_texture(device, size, "egui_backdrop");
+            self.set_user_texture(
+                device,
+                id,
+                view,
+                None,
+                TextureParams {
+                    screen_space_uv: true,
+                    ..Default::default()
+                },
+            );
+            let backdrop = self.backdrop.as_mut().unwrap();
+            backdrop.size = size;
+            backdrop.horizontal_view = Some(horizontal_view);
+        }
+
+        let backdrop = self.backdrop.as_ref().unwrap();
+        let step = options.radius / BLUR_TAPS;
+        queue.write_buffer(
+            &backdrop.horizontal_uniform.buffer,
+            0,
+            bytemuck::cast_slice(&[step / size.0 as f32, 0.0, 0.0, 0.0]),
+        );
+        queue.write_buffer(
+            &backdrop.vertical_uniform.buffer,
+            0,
+            bytemuck::cast_slice(&[0.0, step / size.1 as f32, 0.0, 0.0]),
+        );
+
+        let horizontal_view = backdrop.horizontal_view.as_ref().unwrap();
+        let view = &self.user_texture(id).unwrap().view;
+
+        encoder.push_debug_group("egui_backdrop_blur");
+        let bind_group = backdrop.create_bind_group(device, scene, &backdrop.horizontal_uniform);
+        backdrop.blur_pass(encoder, horizontal_view, &bind_group);
+        let bind_group =
+            backdrop.create_bind_group(device, horizontal_view, &backdrop.vertical_uniform);
+        backdrop.blur_pass(encoder, view, &bind_group);
+        encoder.pop_debug_group();
+
+        egui::TextureId::User(id)
+    }
+}
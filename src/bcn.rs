@@ -0,0 +1,190 @@
+//! CPU compression of user textures into BC1 and BC3, to fit more large images into video memory.
+
+use crate::{egui, BackendError, PendingTexture, RenderPass};
+
+/// Compresses `rgba` pixels of a `width` x `height` image, row by row, into BC1 if it is opaque and BC3
+/// otherwise. Returns `None` if the size isn't a multiple of the 4x4 blocks.
+fn compress(width: usize, height: usize, rgba: &[u8]) -> Option<(Vec<u8>, wgpu::TextureFormat)> {
+    if width == 0 || height == 0 || width % 4 != 0 || height % 4 != 0 {
+        return None;
+    }
+    let opaque = rgba.chunks_exact(4).all(|pixel| pixel[3] == 255);
+    let block_size = if opaque { 8 } else { 16 };
+    let mut data = Vec::with_capacity(width * height / 16 * block_size);
+
+    let mut block = [[0u8; 4]; 16];
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            for (i, pixel) in block.iter_mut().enumerate() {
+                let offset = ((block_y + i / 4) * width + block_x + i % 4) * 4;
+                pixel.copy_from_slice(&rgba[offset..offset + 4]);
+            }
+            if !opaque {
+                data.extend_from_slice(&alpha_block(&block));
+            }
+            data.extend_from_slice(&color_block(&block));
+        }
+    }
+
+    let format = if opaque {
+        wgpu::TextureFormat::Bc1RgbaUnormSrgb
+    } else {
+        wgpu::TextureFormat::Bc3RgbaUnormSrgb
+    };
+    Some((data, format))
+}
+
+fn to_rgb565(color: [u8; 3]) -> u16 {
+    (color[0] as u16 >> 3) << 11 | (color[1] as u16 >> 2) << 5 | color[2] as u16 >> 3
+}
+
+fn from_rgb565(color: u16) -> [i32; 3] {
+    let r = (color >> 11) & 0x1f;
+    let g = (color >> 5) & 0x3f;
+    let b = color & 0x1f;
+    [
+        (r << 3 | r >> 2) as i32,
+        (g << 2 | g >> 4) as i32,
+        (b << 3 | b >> 2) as i32,
+    ]
+}
+
+/// Encodes the colors of a block with the endpoints of their bounding box, in the four color mode.
+fn color_block(block: &[[u8; 4]; 16]) -> [u8; 8] {
+    let mut min = [255u8; 3];
+    let mut max = [0u8; 3];
+    for pixel in block {
+        for channel in 0..3 {
+            min[channel] = min[channel].min(pixel[channel]);
+            max[channel] = max[channel].max(pixel[channel]);
+        }
+    }
+
+    let mut color0 = to_rgb565(max);
+    let mut color1 = to_rgb565(min);
+    if color0 < color1 {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let mut indices = 0u32;
+    if color0 != color1 {
+        let c0 = from_rgb565(color0);
+        let c1 = from_rgb565(color1);
+        let mut palette = [c0, c1, [0; 3], [0; 3]];
+        for channel in 0..3 {
+            palette[2][channel] = (2 * c0[channel] + c1[channel]) / 3;
+            palette[3][channel] = (c0[channel] + 2 * c1[channel]) / 3;
+        }
+        for (i, pixel) in block.iter().enumerate() {
+            let distance = |color: &[i32; 3]| -> i32 {
+                (0..3)
+                    .map(|channel| (color[channel] - pixel[channel] as i32).pow(2))
+                    .sum()
+            };
+            let index = (0..4)
+                .min_by_key(|&index| distance(&palette[index]))
+                .unwrap();
+            indices |= (index as u32) << (2 * i);
+        }
+    }
+
+    let mut data = [0u8; 8];
+    data[0..2].copy_from_slice(&color0.to_le_bytes());
+    data[2..4].copy_from_slice(&color1.to_le_bytes());
+    data[4..8].copy_from_slice(&indices.to_le_bytes());
+    data
+}
+
+/// Encodes the alphas of a block as a BC4 block with eight interpolated values.
+fn alpha_block(block: &[[u8; 4]; 16]) -> [u8; 8] {
+    let alpha0 = block.iter().map(|pixel| pixel[3]).max().unwrap();
+    let alpha1 = block.iter().map(|pixel| pixel[3]).min().unwrap();
+
+    let mut indices = 0u64;
+    if alpha0 != alpha1 {
+        let mut palette = [alpha0 as i32, alpha1 as i32, 0, 0, 0, 0, 0, 0];
+        for (i, value) in palette.iter_mut().enumerate().skip(2) {
+            *value = ((8 - i as i32) * alpha0 as i32 + (i as i32 - 1) * alpha1 as i32) / 7;
+        }
+        for (i, pixel) in block.iter().enumerate() {
+            let index = (0..8)
+                .min_by_key(|&index| (palette[index] - pixel[3] as i32).abs())
+                .unwrap();
+            indices |= (index as u64) << (3 * i);
+        }
+    }
+
+    let mut data = [0u8; 8];
+    data[0] = alpha0;
+    data[1] = alpha1;
+    data[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    data
+}
+
+impl RenderPass {
+    /// Compresses the sRGBA user textures on upload into BC1, or BC3 if they aren't opaque, which uses
+    /// a quarter to an eighth of the memory at a loss of quality. Textures whose width or height isn't a
+    /// multiple of 4 stay uncompressed. Returns `BackendError::MissingFeatures` if the device was created
+    /// without `wgpu::Features::TEXTURE_COMPRESSION_BC`.
+    pub fn set_texture_compression(&mut self, enabled: bool) -> Result<(), BackendError> {
+        if enabled && !self.capabilities.texture_compression_bc {
+            return Err(BackendError::MissingFeatures(
+                wgpu::Features::TEXTURE_COMPRESSION_BC,
+            ));
+        }
+        self.texture_compression = enabled;
+        Ok(())
+    }
+
+    /// Compresses a pending sRGBA texture if texture compression is enabled.
+    pub(crate) fn compress_pending_texture(&self, mut pending: PendingTexture) -> PendingTexture {
+        if !self.texture_compression || pending.format != wgpu::TextureFormat::Rgba8UnormSrgb {
+            return pending;
+        }
+        let texture = &pending.texture;
+        if let Some((pixels, format)) = compress(texture.width, texture.height, &texture.pixels) {
+            pending.texture = egui::Texture {
+                version: texture.version,
+                width: texture.width,
+                height: texture.height,
+                pixels,
+            };
+            pending.format = format;
+        }
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compress;
+
+    #[test]
+    fn compress_needs_whole_blocks() {
+        assert!(compress(0, 4, &[]).is_none());
+        assert!(compress(6, 4, &[255; 6 * 4 * 4]).is_none());
+        assert!(compress(4, 2, &[255; 4 * 2 * 4]).is_none());
+    }
+
+    #[test]
+    fn compress_picks_bc1_for_opaque_images() {
+        let red: Vec<u8> = [255, 0, 0, 255].repeat(8 * 4);
+        let (data, format) = compress(8, 4, &red).unwrap();
+        assert_eq!(format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+        // Both endpoints are red in RGB565 and all indices pick the first one.
+        assert_eq!(data, [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0].repeat(2));
+    }
+
+    #[test]
+    fn compress_picks_bc3_for_translucent_images() {
+        let mut pixels: Vec<u8> = [0, 0, 255, 255].repeat(4 * 4);
+        pixels[3] = 0;
+        let (data, format) = compress(4, 4, &pixels).unwrap();
+        assert_eq!(format, wgpu::TextureFormat::Bc3RgbaUnormSrgb);
+        assert_eq!(data.len(), 16);
+        // The alpha endpoints are the maximum and minimum, the transparent pixel uses the second one.
+        assert_eq!(data[0..2], [255, 0]);
+        assert_eq!(data[2] & 0b111, 1);
+        assert_eq!(data[8..12], [0x1f, 0x00, 0x1f, 0x00]);
+    }
+}
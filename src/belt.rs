@@ -0,0 +1,91 @@
+//! Buffer uploads through a `wgpu::util::StagingBelt`, recorded into the command encoder of the caller.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::readback::noop_waker;
+use crate::{egui, BackendError, BufferWriter, RenderPass, ScreenDescriptor};
+
+/// Size of the staging buffers the belt allocates.
+const STAGING_BELT_CHUNK_SIZE: wgpu::BufferAddress = 1024 * 1024;
+
+type Recall = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The staging belt of a render pass and the recalls of its staging buffers that didn't finish yet.
+pub(crate) struct StagingUploads {
+    belt: wgpu::util::StagingBelt,
+    recalls: Vec<Recall>,
+    /// The belt was finished and its encoder needs to be submitted before the belt is recalled.
+    finished: bool,
+}
+
+impl StagingUploads {
+    fn new() -> Self {
+        Self {
+            belt: wgpu::util::StagingBelt::new(STAGING_BELT_CHUNK_SIZE),
+            recalls: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Takes back the staging buffers of the previous upload, whose encoder has been submitted by now, and
+    /// of earlier uploads the GPU finished with since.
+    fn recall(&mut self, device: &wgpu::Device) {
+        if self.finished {
+            self.recalls.push(Box::pin(self.belt.recall()));
+            self.finished = false;
+        }
+        // The staging buffers are mapped again by the device, the recalls only hand them back to the belt.
+        device.poll(wgpu::Maintain::Poll);
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        self.recalls = std::mem::take(&mut self.recalls)
+            .into_iter()
+            .filter_map(|mut recall| match recall.as_mut().poll(&mut context) {
+                Poll::Ready(()) => None,
+                Poll::Pending => Some(recall),
+            })
+            .collect();
+    }
+}
+
+impl RenderPass {
+    /// Like [`RenderPass::update_buffers_with_encoder`], but writes the data into the staging buffers of a
+    /// `wgpu::util::StagingBelt` owned by the render pass instead of creating a staging buffer per upload.
+    /// This avoids the per-frame `queue.write_buffer()` calls of large UIs, e.g. plots with many vertices.
+    ///
+    /// The belt is finished at the end of the call and recalled by the next call, so `encoder` has to be
+    /// submitted before this is called again.
+    pub fn update_buffers_with_staging_belt<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        let mut uploads = self
+            .staging_uploads
+            .get_mut()
+            .expect("staging belt poisoned")
+            .take()
+            .unwrap_or_else(StagingUploads::new);
+        uploads.recall(device);
+        let result = self.write_buffers(
+            device,
+            &mut BufferWriter::Belt {
+                belt: &mut uploads.belt,
+                encoder,
+            },
+            paint_jobs,
+            screen_descriptor,
+        );
+        uploads.belt.finish();
+        uploads.finished = true;
+        *self
+            .staging_uploads
+            .get_mut()
+            .expect("staging belt poisoned") = Some(uploads);
+        result
+    }
+}
@@ -0,0 +1,97 @@
+//! Custom draw calls between the meshes of egui, e.g. for a 3D viewport inside of a panel.
+
+use crate::{egui, RenderPass, ScreenDescriptor};
+
+/// Draws into the egui render pass between the meshes of egui. Added with [`RenderPass::add_paint_callback`].
+///
+/// The pass borrows everything set on it for its whole lifetime, so the pipelines, bind groups and buffers
+/// the callback draws with are owned by the callback, e.g. behind an `Arc`.
+pub trait PaintCallback: Send + Sync {
+    /// Records draw calls into `pass`, whose scissor rect is set to the clip rect of the callback.
+    /// The render pass restores the state of the egui pipeline afterwards.
+    fn paint<'rp>(&'rp self, info: &PaintCallbackInfo, pass: &mut wgpu::RenderPass<'rp>);
+}
+
+/// Where a [`PaintCallback`] draws.
+#[derive(Clone, Copy)]
+pub struct PaintCallbackInfo<'a> {
+    /// The rect the callback draws into, in points.
+    pub rect: egui::Rect,
+    /// The clip rect of the callback in points.
+    pub clip_rect: egui::Rect,
+    /// The scissor rect of the clip rect as `[x, y, width, height]` in physical pixels of the render target.
+    pub scissor_rect: [u32; 4],
+    /// The screen the UI is drawn for.
+    pub screen_descriptor: &'a ScreenDescriptor,
+}
+
+impl PaintCallbackInfo<'_> {
+    /// Returns `rect` as `[x, y, width, height]` in physical pixels of the window, e.g. for the viewport of
+    /// the draw calls. It isn't clamped to the render target and needs to be rotated for a screen descriptor
+    /// with a rotation.
+    pub fn viewport(&self) -> [f32; 4] {
+        let (region_x, region_y, _, _) = self.screen_descriptor.ui_region();
+        let scale_factor = self.screen_descriptor.scale_factor;
+        [
+            region_x as f32 + self.rect.min.x * scale_factor,
+            region_y as f32 + self.rect.min.y * scale_factor,
+            self.rect.width() * scale_factor,
+            self.rect.height() * scale_factor,
+        ]
+    }
+}
+
+/// A paint callback and where it is drawn.
+pub(crate) struct PositionedCallback {
+    /// Index of the paint job the callback is drawn before.
+    pub(crate) position: usize,
+    rect: egui::Rect,
+    clip_rect: egui::Rect,
+    callback: Box<dyn PaintCallback>,
+}
+
+impl RenderPass {
+    /// Adds `callback` to the next `update_buffers()`, which draws into `rect` clipped to `clip_rect` before
+    /// the paint job at `position`, or after all of them if `position` is past the end. Custom geometry at the
+    /// same position is drawn before the callback. The callback is drawn for a single frame by the `execute`
+    /// functions, but not by draw commands, picking or stencil clipping.
+    pub fn add_paint_callback(
+        &mut self,
+        position: usize,
+        rect: egui::Rect,
+        clip_rect: egui::Rect,
+        callback: Box<dyn PaintCallback>,
+    ) {
+        self.paint_callbacks.push(PositionedCallback {
+            position,
+            rect,
+            clip_rect,
+            callback,
+        });
+    }
+
+    /// Runs a paint callback and restores the state of the egui pipeline afterwards.
+    pub(crate) fn run_paint_callback<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        callback: &'rp PositionedCallback,
+        screen_descriptor: &ScreenDescriptor,
+    ) {
+        let (x, y, width, height) = match screen_descriptor.scissor_rect(&callback.clip_rect, 0.0) {
+            Some(rect) => rect,
+            None => return,
+        };
+        pass.set_scissor_rect(x, y, width, height);
+        let info = PaintCallbackInfo {
+            rect: callback.rect,
+            clip_rect: callback.clip_rect,
+            scissor_rect: [x, y, width, height],
+            screen_descriptor,
+        };
+        callback.callback.paint(&info, pass);
+
+        self.set_egui_state(pass, screen_descriptor);
+        let (x, y, width, height) = screen_descriptor.target_region();
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+    }
+}
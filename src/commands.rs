@@ -0,0 +1,275 @@
+//! Texture commands sent from other threads, e.g. asset streaming, and applied by the render pass.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    check_pixel_data, egui, next_generation, user_texture_slot, BackendError, PendingTexture,
+    RenderPass, TextureParams,
+};
+
+/// User texture ids and the commands waiting for the next frame, shared by the render pass, its
+/// [`TextureCommandSender`]s and [`crate::TextureHandle`]s.
+#[derive(Default)]
+pub(crate) struct TextureQueue {
+    next_id: u64,
+    /// Ids for the slots of freed user textures, with the generation of their next use.
+    free_ids: Vec<u64>,
+    commands: Vec<TextureCommand>,
+}
+
+pub(crate) type SharedTextureQueue = Arc<Mutex<TextureQueue>>;
+
+pub(crate) enum TextureCommand {
+    Set {
+        id: u64,
+        size: (usize, usize),
+        srgba_pixels: Vec<egui::Color32>,
+    },
+    Free(egui::TextureId),
+}
+
+impl TextureQueue {
+    /// Returns a new user texture id, reusing the slot of a freed texture with the next generation if possible.
+    pub(crate) fn allocate_id(&mut self) -> u64 {
+        self.free_ids.pop().unwrap_or_else(|| {
+            let id = self.next_id;
+            self.next_id += 1;
+            id
+        })
+    }
+
+    /// Marks the slot of `id` as used, so it isn't handed out again. Returns `false` if the slot is free
+    /// for another generation, i.e. `id` is stale.
+    pub(crate) fn reserve_id(&mut self, id: u64) -> bool {
+        let slot = user_texture_slot(id);
+        if self
+            .free_ids
+            .iter()
+            .any(|&free| user_texture_slot(free) == slot && free != id)
+        {
+            return false;
+        }
+        self.free_ids.retain(|&free| free != id);
+        self.next_id = self.next_id.max(slot as u64 + 1);
+        true
+    }
+
+    /// Makes the slot of `id` available for the next generation.
+    pub(crate) fn release_id(&mut self, id: u64) {
+        self.free_ids.push(next_generation(id));
+    }
+
+    pub(crate) fn push(&mut self, command: TextureCommand) {
+        self.commands.push(command);
+    }
+}
+
+/// Registers, updates and frees user textures without access to the render pass.
+///
+/// The sender can be cloned and sent to other threads. Its commands are applied in order by the next
+/// call to [`RenderPass::update_user_textures`], before the pending textures are uploaded.
+#[derive(Clone)]
+pub struct TextureCommandSender {
+    queue: SharedTextureQueue,
+}
+
+impl TextureCommandSender {
+    /// Registers a texture with `size` premultiplied sRGBA pixels and returns its id right away. Returns
+    /// `BackendError::InvalidPixelData` if `srgba_pixels` doesn't hold `size.0 * size.1` pixels.
+    pub fn register(
+        &self,
+        size: (usize, usize),
+        srgba_pixels: Vec<egui::Color32>,
+    ) -> Result<egui::TextureId, BackendError> {
+        check_pixel_data(size.0 * size.1 * 4, srgba_pixels.len() * 4)?;
+        let mut queue = self.lock();
+        let id = queue.allocate_id();
+        queue.push(TextureCommand::Set {
+            id,
+            size,
+            srgba_pixels,
+        });
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Replaces the pixels of the user texture `id`. It keeps its sampler and parameters. If `id` is stale, i.e.
+    /// the texture was freed and its slot reused, the command is dropped and the next `update_user_textures()`
+    /// returns `BackendError::StaleUserTexture`. Returns `BackendError::InvalidPixelData` if `srgba_pixels`
+    /// doesn't hold `size.0 * size.1` pixels.
+    pub fn update(
+        &self,
+        id: egui::TextureId,
+        size: (usize, usize),
+        srgba_pixels: Vec<egui::Color32>,
+    ) -> Result<(), BackendError> {
+        check_pixel_data(size.0 * size.1 * 4, srgba_pixels.len() * 4)?;
+        if let egui::TextureId::User(id) = id {
+            self.lock().push(TextureCommand::Set {
+                id,
+                size,
+                srgba_pixels,
+            });
+        }
+        Ok(())
+    }
+
+    /// Frees the user texture `id`.
+    pub fn free(&self, id: egui::TextureId) {
+        self.lock().push(TextureCommand::Free(id));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TextureQueue> {
+        self.queue.lock().expect("texture queue poisoned")
+    }
+}
+
+impl RenderPass {
+    /// Returns a sender to register, update and free user textures from other threads.
+    pub fn texture_command_sender(&self) -> TextureCommandSender {
+        TextureCommandSender {
+            queue: self.texture_queue.clone(),
+        }
+    }
+
+    pub(crate) fn lock_texture_queue(&self) -> std::sync::MutexGuard<'_, TextureQueue> {
+        self.texture_queue.lock().expect("texture queue poisoned")
+    }
+
+    /// Applies the commands of the texture command senders and dropped texture handles. Commands for stale ids
+    /// are dropped, the texture now using the slot stays. The other commands are applied anyway and the first
+    /// error is returned.
+    pub(crate) fn apply_texture_commands(&mut self) -> Result<(), BackendError> {
+        let commands = std::mem::take(&mut self.lock_texture_queue().commands);
+        let mut result = Ok(());
+        for command in commands {
+            match command {
+                TextureCommand::Set {
+                    id,
+                    size,
+                    srgba_pixels,
+                } => {
+                    result = result.and(self.set_srgba_texture(id, size, &srgba_pixels));
+                }
+                TextureCommand::Free(id) => epi::TextureAllocator::free(self, id),
+            }
+        }
+        result
+    }
+
+    /// Creates or replaces the user texture `id` with `size` premultiplied sRGBA pixels. It is uploaded by the
+    /// next `update_user_textures()`, a replaced texture is drawn until then. Replaced textures keep their
+    /// sampler and parameters. Returns `BackendError::StaleUserTexture` if the slot of `id` was freed and reused
+    /// and `BackendError::InvalidPixelData` if `srgba_pixels` doesn't hold `size.0 * size.1` pixels.
+    pub(crate) fn set_srgba_texture(
+        &mut self,
+        id: u64,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<(), BackendError> {
+        check_pixel_data(size.0 * size.1 * 4, srgba_pixels.len() * 4)?;
+        let mut pixels = Vec::with_capacity(srgba_pixels.len() * 4);
+        for color in srgba_pixels {
+            pixels.extend_from_slice(&color.to_array());
+        }
+        self.set_rgba_texture(id, size, pixels)
+    }
+
+    /// Like `set_srgba_texture()`, with the pixels as bytes.
+    pub(crate) fn set_rgba_texture(
+        &mut self,
+        id: u64,
+        size: (usize, usize),
+        pixels: Vec<u8>,
+    ) -> Result<(), BackendError> {
+        self.reserve_user_texture_id(id)?;
+        let (sampler, params) = match self.user_texture(id) {
+            Some(user_texture) => (user_texture.sampler, user_texture.params),
+            _ => self
+                .pending_user_textures
+                .iter()
+                .find(|p| p.id == id)
+                .map(|p| (p.sampler, p.params))
+                .or_else(|| {
+                    self.evicted_textures
+                        .iter()
+                        .find(|e| e.id == id)
+                        .map(|e| (e.sampler, e.params))
+                })
+                .unwrap_or((None, TextureParams::default())),
+        };
+        self.pending_user_textures.retain(|p| p.id != id);
+        self.evicted_textures.retain(|e| e.id != id);
+
+        self.pending_user_textures.push_back(PendingTexture {
+            id,
+            texture: egui::Texture {
+                version: 0,
+                width: size.0,
+                height: size.1,
+                pixels,
+            },
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            sampler,
+            params,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TextureCommandSender, TextureQueue};
+    use crate::{egui, next_generation, user_texture_slot, BackendError};
+
+    #[test]
+    fn freed_ids_are_reused_with_the_next_generation() {
+        let mut queue = TextureQueue::default();
+        assert_eq!(queue.allocate_id(), 0);
+        assert_eq!(queue.allocate_id(), 1);
+
+        queue.release_id(0);
+        let reused = queue.allocate_id();
+        assert_eq!(reused, next_generation(0));
+        assert_eq!(user_texture_slot(reused), 0);
+        assert_eq!(queue.allocate_id(), 2);
+    }
+
+    #[test]
+    fn reserve_id_rejects_stale_ids() {
+        let mut queue = TextureQueue::default();
+        let id = queue.allocate_id();
+        queue.release_id(id);
+        assert!(!queue.reserve_id(id));
+        // The freed slot is still handed out with the next generation.
+        assert_eq!(queue.allocate_id(), next_generation(id));
+    }
+
+    #[test]
+    fn reserve_id_claims_free_and_unused_slots() {
+        let mut queue = TextureQueue::default();
+        let id = queue.allocate_id();
+        queue.release_id(id);
+        assert!(queue.reserve_id(next_generation(id)));
+        assert!(queue.reserve_id(5));
+        assert_eq!(queue.allocate_id(), 6);
+    }
+
+    #[test]
+    fn senders_reject_pixels_not_matching_the_size() {
+        let sender = TextureCommandSender {
+            queue: Default::default(),
+        };
+        let pixels = vec![egui::Color32::WHITE; 3];
+        let invalid = Err(BackendError::InvalidPixelData {
+            expected: 16,
+            actual: 12,
+        });
+        assert_eq!(sender.register((2, 2), pixels.clone()), invalid);
+        assert_eq!(
+            sender.update(egui::TextureId::User(0), (2, 2), pixels.clone()),
+            invalid.map(|_| ())
+        );
+        assert!(sender.queue.lock().unwrap().commands.is_empty());
+        assert!(sender.register((3, 1), pixels).is_ok());
+    }
+}
@@ -0,0 +1,427 @@
+//! Resources shared by the render passes of several windows, e.g. an application with one surface per OS window.
+
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    check_pixel_data, create_colormap_lut_view, create_render_pipeline,
+    create_texture_bind_group_layout, egui, egui_color_target_state, egui_depth_stencil_state,
+    egui_push_constant_ranges, egui_vertex_buffer_layouts, next_generation, user_texture_slot,
+    BackendCapabilities, BackendError, PipelineKey, RenderPassConfig, SampleKindLayout,
+    TextureBlendMode, TextureParams, TextureSampleKind, TextureUniformBuffer, UniformBuffer,
+    SHARED_TEXTURE_FLAG, UNIFORM_STAGES,
+};
+
+/// The shader modules, layouts, samplers and pipelines of a [`crate::RenderPass`] configuration, which don't
+/// depend on the window it draws into.
+///
+/// Create the render pass of every window with [`crate::RenderPass::with_context`] from the same context to
+/// create these only once. The pipelines are cached by output format, so a render pass created again with the
+/// context of the previous one, e.g. after the surface format changed, reuses the pipelines compiled for that
+/// format before. Cloning a context is cheap and refers to the same resources.
+///
+/// The egui texture stays with each render pass, since every egui context has a font atlas of its own. User
+/// textures registered with [`RenderContext::register_shared_texture`] are uploaded once and drawn by all
+/// render passes of the context. Each render pass takes a snapshot of them in `update_user_textures()`, which
+/// keeps a freed texture alive until the last render pass drawing it took its next snapshot.
+#[derive(Clone)]
+pub struct RenderContext {
+    pub(crate) shared: Arc<SharedResources>,
+}
+
+pub(crate) struct SharedResources {
+    pub(crate) config: RenderPassConfig,
+    pub(crate) capabilities: BackendCapabilities,
+    /// The egui pipeline reads the uniforms from push constants instead of the uniform buffer.
+    pub(crate) push_constants: bool,
+    pub(crate) vs_module: wgpu::ShaderModule,
+    pub(crate) fs_module: wgpu::ShaderModule,
+    pub(crate) uniform_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) pipeline_layout: wgpu::PipelineLayout,
+    pub(crate) sampler: wgpu::Sampler,
+    /// Bound as the colormap of textures without a custom colormap.
+    pub(crate) default_colormap_lut: wgpu::TextureView,
+    /// Layouts of the sample kinds other than `TextureSampleKind::Float`, created when first used.
+    sample_kind_layouts: Mutex<Vec<Arc<SampleKindLayout>>>,
+    /// The egui pipelines created so far, by output format and pipeline key.
+    render_pipelines: Mutex<Vec<(wgpu::TextureFormat, PipelineKey, Arc<wgpu::RenderPipeline>)>>,
+    shared_textures: Mutex<SharedTextureTable>,
+}
+
+/// The user textures of a context by slot, with the ids of freed slots for their next generation.
+#[derive(Default)]
+struct SharedTextureTable {
+    textures: Vec<Option<Arc<SharedTexture>>>,
+    free_ids: Vec<u64>,
+}
+
+/// A user texture drawn by all render passes of a [`RenderContext`].
+pub(crate) struct SharedTexture {
+    /// Full id including the generation and `SHARED_TEXTURE_FLAG`.
+    pub(crate) id: u64,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) bind_group: wgpu::BindGroup,
+    _texture: wgpu::Texture,
+    _uniform_buffer: wgpu::Buffer,
+}
+
+impl RenderContext {
+    /// Creates the shader modules, layouts and samplers of `config` on `device`.
+    pub fn new(device: &wgpu::Device, config: &RenderPassConfig) -> Self {
+        let capabilities = BackendCapabilities::detect(device);
+        // Custom shaders read the uniforms from the uniform buffer.
+        let push_constants = capabilities.push_constants
+            && config.vertex_shader.is_none()
+            && config.fragment_shader.is_none();
+
+        let vs_module = match (
+            &config.vertex_shader,
+            config.aux_vertex_stream,
+            push_constants,
+        ) {
+            (Some(spirv), _, _) => device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some("egui_custom_vertex_shader"),
+                source: wgpu::ShaderSource::SpirV(spirv.clone()),
+                flags: wgpu::ShaderFlags::VALIDATION,
+            }),
+            (None, false, false) => device.create_shader_module(&include_shader!("egui.vert")),
+            (None, true, false) => device.create_shader_module(&include_shader!("egui_aux.vert")),
+            #[cfg(feature = "spirv")]
+            (None, false, true) => device.create_shader_module(&include_shader!("egui_push.vert")),
+            #[cfg(feature = "spirv")]
+            (None, true, true) => {
+                device.create_shader_module(&include_shader!("egui_aux_push.vert"))
+            }
+            #[cfg(not(feature = "spirv"))]
+            (None, _, true) => unreachable!("push constants need the spirv feature"),
+        };
+        let fs_module = match (&config.fragment_shader, push_constants) {
+            (Some(spirv), _) => device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some("egui_custom_fragment_shader"),
+                source: wgpu::ShaderSource::SpirV(spirv.clone()),
+                flags: wgpu::ShaderFlags::VALIDATION,
+            }),
+            (None, false) => device.create_shader_module(&include_shader!("egui.frag")),
+            #[cfg(feature = "spirv")]
+            (None, true) => device.create_shader_module(&include_shader!("egui_push.frag")),
+            #[cfg(not(feature = "spirv"))]
+            (None, true) => unreachable!("push constants need the spirv feature"),
+        };
+
+        let sampler = config
+            .sampler
+            .create_sampler(device, "egui_texture_sampler");
+
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("egui_uniform_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: UNIFORM_STAGES,
+                    ty: wgpu::BindingType::Buffer {
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<UniformBuffer>() as wgpu::BufferAddress,
+                        ),
+                        ty: wgpu::BufferBindingType::Uniform,
+                    },
+                    count: None,
+                }],
+            });
+
+        let default_colormap_lut = create_colormap_lut_view(device, 1, "egui_default_colormap_lut");
+
+        let texture_bind_group_layout = create_texture_bind_group_layout(
+            device,
+            "egui_texture_bind_group_layout",
+            TextureSampleKind::Float,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("egui_pipeline_layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            push_constant_ranges: egui_push_constant_ranges(push_constants),
+        });
+
+        Self {
+            shared: Arc::new(SharedResources {
+                config: config.clone(),
+                capabilities,
+                push_constants,
+                vs_module,
+                fs_module,
+                uniform_bind_group_layout,
+                texture_bind_group_layout,
+                pipeline_layout,
+                sampler,
+                default_colormap_lut,
+                sample_kind_layouts: Mutex::new(Vec::new()),
+                render_pipelines: Mutex::new(Vec::new()),
+                shared_textures: Mutex::new(SharedTextureTable::default()),
+            }),
+        }
+    }
+
+    /// Returns the configuration of the render passes created from the context.
+    pub fn config(&self) -> &RenderPassConfig {
+        &self.shared.config
+    }
+
+    /// Uploads a user texture with `size` premultiplied sRGBA pixels, which every render pass created from the
+    /// context can draw after its next `update_user_textures()`. It uses the sampler of the configuration and
+    /// the default texture parameters, the setters of a render pass don't apply to it.
+    ///
+    /// Returns `BackendError::InvalidPixelData` if `srgba_pixels` doesn't hold `size.0 * size.1` pixels.
+    pub fn register_shared_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<egui::TextureId, BackendError> {
+        check_pixel_data(size.0 * size.1 * 4, srgba_pixels.len() * 4)?;
+        let mut table = self.lock_shared_textures();
+        let id = table.free_ids.pop().unwrap_or(table.textures.len() as u64) | SHARED_TEXTURE_FLAG;
+        let label = format!("egui_shared_texture{}", user_texture_slot(id));
+
+        let mut pixels = Vec::with_capacity(srgba_pixels.len() * 4);
+        for color in srgba_pixels {
+            pixels.extend_from_slice(&color.to_array());
+        }
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(format!("{}_texture", label).as_str()),
+                size: wgpu::Extent3d {
+                    width: size.0 as u32,
+                    height: size.1 as u32,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            },
+            &pixels,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // The default parameters sample a float texture, which has a colormap binding.
+        let params = TextureParams::default();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(format!("{}_uniform_buffer", label).as_str()),
+            contents: bytemuck::cast_slice(&[TextureUniformBuffer::new(
+                &self.shared.config.sampler,
+                &params,
+                1,
+            )]),
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+        let entries = [
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&self.shared.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: None,
+                },
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&self.shared.default_colormap_lut),
+            },
+        ];
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(format!("{}_texture_bind_group", label).as_str()),
+            layout: &self.shared.texture_bind_group_layout,
+            entries: &entries,
+        });
+
+        let slot = user_texture_slot(id);
+        if slot == table.textures.len() {
+            table.textures.push(None);
+        }
+        table.textures[slot] = Some(Arc::new(SharedTexture {
+            id,
+            view,
+            bind_group,
+            _texture: texture,
+            _uniform_buffer: uniform_buffer,
+        }));
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Frees a texture registered with [`RenderContext::register_shared_texture`]. Render passes keep drawing it
+    /// until their next `update_user_textures()`, its `wgpu::Texture` is dropped once all of them let go of it.
+    pub fn free_shared_texture(&self, id: egui::TextureId) {
+        let id = match id {
+            egui::TextureId::User(id) if id & SHARED_TEXTURE_FLAG != 0 => id,
+            _ => return,
+        };
+        let mut table = self.lock_shared_textures();
+        let slot = user_texture_slot(id);
+        if matches!(table.textures.get(slot), Some(Some(texture)) if texture.id == id) {
+            table.textures[slot] = None;
+            table.free_ids.push(next_generation(id));
+        }
+    }
+
+    /// Returns the shared textures by slot, for a render pass to draw until its next snapshot.
+    pub(crate) fn shared_textures(&self) -> Vec<Option<Arc<SharedTexture>>> {
+        self.lock_shared_textures().textures.clone()
+    }
+
+    fn lock_shared_textures(&self) -> std::sync::MutexGuard<'_, SharedTextureTable> {
+        self.shared
+            .shared_textures
+            .lock()
+            .expect("shared textures poisoned")
+    }
+
+    /// Returns the layout of `sample_kind`, created on first use. `TextureSampleKind::Float` uses the default
+    /// layout instead.
+    pub(crate) fn sample_kind_layout(
+        &self,
+        device: &wgpu::Device,
+        sample_kind: TextureSampleKind,
+    ) -> Arc<SampleKindLayout> {
+        let mut layouts = self
+            .shared
+            .sample_kind_layouts
+            .lock()
+            .expect("sample kind layouts poisoned");
+        if let Some(layout) = layouts
+            .iter()
+            .find(|layout| layout.sample_kind == sample_kind)
+        {
+            return layout.clone();
+        }
+        let name = format!("{:?}", sample_kind).to_lowercase();
+
+        let bind_group_layout = create_texture_bind_group_layout(
+            device,
+            &format!("egui_{}_texture_bind_group_layout", name),
+            sample_kind,
+        );
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(format!("egui_{}_pipeline_layout", name).as_str()),
+            bind_group_layouts: &[&self.shared.uniform_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: egui_push_constant_ranges(self.shared.push_constants),
+        });
+        // The variants read the uniforms from the uniform buffer, which is always up to date.
+        let fs_module = match sample_kind {
+            TextureSampleKind::Float => unreachable!(),
+            TextureSampleKind::UnfilterableFloat => {
+                device.create_shader_module(&include_shader!("egui.frag"))
+            }
+            #[cfg(feature = "spirv")]
+            TextureSampleKind::Depth => {
+                device.create_shader_module(&include_shader!("egui_depth.frag"))
+            }
+            #[cfg(feature = "spirv")]
+            TextureSampleKind::Uint => {
+                device.create_shader_module(&include_shader!("egui_uint.frag"))
+            }
+            #[cfg(feature = "spirv")]
+            TextureSampleKind::Sint => {
+                device.create_shader_module(&include_shader!("egui_sint.frag"))
+            }
+            #[cfg(not(feature = "spirv"))]
+            TextureSampleKind::Depth | TextureSampleKind::Uint | TextureSampleKind::Sint => {
+                unreachable!("depth and integer textures need the spirv feature")
+            }
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(format!("egui_{}_sampler", name).as_str()),
+            ..Default::default()
+        });
+
+        let layout = Arc::new(SampleKindLayout {
+            sample_kind,
+            bind_group_layout,
+            pipeline_layout,
+            fs_module,
+            sampler,
+        });
+        layouts.push(layout.clone());
+        layout
+    }
+
+    /// Returns the egui pipeline of `key` drawing into `output_format`, created on first use.
+    pub(crate) fn pipeline(
+        &self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        key: PipelineKey,
+    ) -> Arc<wgpu::RenderPipeline> {
+        let mut render_pipelines = self
+            .shared
+            .render_pipelines
+            .lock()
+            .expect("render pipelines poisoned");
+        if let Some((_, _, pipeline)) = render_pipelines
+            .iter()
+            .find(|(format, variant, _)| *format == output_format && *variant == key)
+        {
+            return pipeline.clone();
+        }
+        let label = if key == PipelineKey::default() {
+            "egui_pipeline".to_owned()
+        } else {
+            format!("egui_{:?}_{:?}_pipeline", key.blend_mode, key.sample_kind).to_lowercase()
+        };
+        let depth_stencil = egui_depth_stencil_state(&self.shared.config);
+        let pipeline =
+            Arc::new(self.create_keyed_pipeline(device, output_format, key, &label, depth_stencil));
+        render_pipelines.push((output_format, key, pipeline.clone()));
+        pipeline
+    }
+
+    /// Creates an egui pipeline of `key` drawing into `output_format`, without caching it.
+    pub(crate) fn create_keyed_pipeline(
+        &self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        key: PipelineKey,
+        label: &str,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let shared = &self.shared;
+        let layout = if key.sample_kind == TextureSampleKind::Float {
+            None
+        } else {
+            Some(self.sample_kind_layout(device, key.sample_kind))
+        };
+        let (pipeline_layout, fs_module) = match &layout {
+            Some(layout) => (&layout.pipeline_layout, &layout.fs_module),
+            None => (&shared.pipeline_layout, &shared.fs_module),
+        };
+
+        let mut target = egui_color_target_state(output_format, &shared.config);
+        if key.blend_mode != TextureBlendMode::Normal {
+            target.color_blend = key.blend_mode.color_blend();
+        }
+        create_render_pipeline(
+            device,
+            label,
+            pipeline_layout,
+            &shared.vs_module,
+            fs_module,
+            egui_vertex_buffer_layouts(shared.config.aux_vertex_stream),
+            target,
+            depth_stencil,
+            shared.config.sample_count(),
+        )
+    }
+}
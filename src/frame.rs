@@ -0,0 +1,53 @@
+//! Splitting a frame into a mutable preparation and an immutable recording, for render graphs.
+
+use crate::{egui, BackendError, RenderPass, ScreenDescriptor};
+
+/// The paint jobs and screen of a frame prepared by [`RenderPass::prepare`], drawn by [`RenderPass::render`].
+pub struct FrameResources {
+    paint_jobs: Vec<egui::paint::ClippedMesh>,
+    screen_descriptor: ScreenDescriptor,
+}
+
+impl FrameResources {
+    /// Returns the paint jobs of the frame.
+    pub fn paint_jobs(&self) -> &[egui::paint::ClippedMesh] {
+        &self.paint_jobs
+    }
+
+    /// Returns the screen the frame is drawn for.
+    pub fn screen_descriptor(&self) -> &ScreenDescriptor {
+        &self.screen_descriptor
+    }
+}
+
+impl RenderPass {
+    /// Uploads the user textures and the buffers of a frame. Everything that needs mutable access happens here,
+    /// so the frame can be recorded by [`RenderPass::render`] through a shared reference, e.g. inside of a
+    /// render graph node. The egui texture still needs to be updated with `update_texture()` before.
+    ///
+    /// Returns the error of `update_buffers()`, e.g. for a malformed mesh.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        paint_jobs: Vec<egui::paint::ClippedMesh>,
+        screen_descriptor: ScreenDescriptor,
+    ) -> Result<FrameResources, BackendError> {
+        self.update_user_textures(device, queue)?;
+        self.update_buffers(device, queue, &paint_jobs, &screen_descriptor)?;
+        Ok(FrameResources {
+            paint_jobs,
+            screen_descriptor,
+        })
+    }
+
+    /// Records the draw calls of a frame prepared by [`RenderPass::prepare`] into a render pass of the caller,
+    /// with the same requirements as [`RenderPass::execute_with_renderpass`].
+    pub fn render<'rp>(
+        &'rp self,
+        frame: &FrameResources,
+        pass: &mut wgpu::RenderPass<'rp>,
+    ) -> Result<(), BackendError> {
+        self.execute_with_renderpass(pass, &frame.paint_jobs, &frame.screen_descriptor)
+    }
+}
@@ -0,0 +1,125 @@
+//! Native runner for `epi::App`s, which owns the window, device and render pass. Needs the `framework` feature.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use winit::dpi::LogicalSize;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
+use winit::window::WindowBuilder;
+
+use crate::{epi, simple, EguiIntegration};
+
+/// Wakes up the event loop to repaint the app.
+struct RepaintEvent;
+
+/// Sends [`RepaintEvent`]s from any thread.
+struct RepaintSignal(Mutex<EventLoopProxy<RepaintEvent>>);
+
+impl epi::RepaintSignal for RepaintSignal {
+    fn request_repaint(&self) {
+        // Fails only if the event loop has already exited.
+        let _ = self
+            .0
+            .lock()
+            .expect("repaint signal poisoned")
+            .send_event(RepaintEvent);
+    }
+}
+
+/// Opens a window for `app` and runs it until it quits or the window is closed. Never returns.
+///
+/// Creates the event loop, window, device and render pass like [`crate::simple::run`], with the name, size
+/// policy and clear color of the app. The app allocates textures through the render pass and can request
+/// repaints from other threads. The window is only repainted on input, repaint requests and animations.
+/// Storage isn't supported, so `load()` and `save()` aren't called, and `Frame::set_pixels_per_point()`
+/// is ignored.
+pub fn run_native(mut app: impl epi::App + 'static) -> ! {
+    let event_loop = EventLoop::with_user_event();
+    let window = WindowBuilder::new()
+        .with_title(app.name())
+        .with_resizable(app.is_resizable())
+        .build(&event_loop)
+        .expect("failed to create the window");
+
+    let (device, queue, mut window_surface) =
+        simple::create_device(&window, "egui_framework_device");
+    let mut integration = EguiIntegration::new(&device, window_surface.format(), &window)
+        .expect("the swap chain format is not supported");
+    let repaint_signal = Arc::new(RepaintSignal(Mutex::new(event_loop.create_proxy())));
+    let mut cpu_usage = None;
+    app.setup(&integration.context());
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+        integration.handle_event(&event);
+        window_surface.handle_event(&event);
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                app.on_exit();
+                *control_flow = ControlFlow::Exit;
+            }
+            Event::WindowEvent { .. } | Event::UserEvent(RepaintEvent) => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let surface_frame = match window_surface.get_current_frame(&device) {
+                    Some(frame) => frame,
+                    None => return,
+                };
+                let frame_start = Instant::now();
+
+                integration.begin_frame();
+                let context = integration.context();
+                let mut app_output = epi::backend::AppOutput::default();
+                let mut frame = epi::backend::FrameBuilder {
+                    info: epi::IntegrationInfo {
+                        web_info: None,
+                        cpu_usage,
+                        seconds_since_midnight: None,
+                        native_pixels_per_point: Some(window.scale_factor() as f32),
+                    },
+                    tex_allocator: Some(integration.render_pass()),
+                    output: &mut app_output,
+                    repaint_signal: repaint_signal.clone(),
+                }
+                .build();
+                app.update(&context, &mut frame);
+
+                let clear_color = app.clear_color();
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("egui_framework_encoder"),
+                });
+                let output = integration
+                    .render(
+                        &device,
+                        &queue,
+                        &mut encoder,
+                        &surface_frame.output.view,
+                        Some(wgpu::Color {
+                            r: clear_color.r() as f64,
+                            g: clear_color.g() as f64,
+                            b: clear_color.b() as f64,
+                            a: clear_color.a() as f64,
+                        }),
+                    )
+                    .expect("failed to render the UI");
+                queue.submit(Some(encoder.finish()));
+                integration.handle_output(&window, &output);
+                cpu_usage = Some(frame_start.elapsed().as_secs_f32());
+
+                if let Some(size) = app_output.window_size {
+                    window.set_inner_size(LogicalSize::new(size.x, size.y));
+                }
+                if app_output.quit {
+                    app.on_exit();
+                    *control_flow = ControlFlow::Exit;
+                } else if output.needs_repaint {
+                    window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+    })
+}
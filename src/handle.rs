@@ -0,0 +1,47 @@
+//! Texture ids that free their texture when dropped.
+
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::{
+    commands::{TextureCommand, TextureQueue},
+    egui, RenderPass,
+};
+
+/// Owns a user texture and frees it when dropped.
+///
+/// The texture is freed by the next call to [`RenderPass::update_user_textures`]. Handles outliving their
+/// render pass don't free anything.
+#[derive(Debug)]
+pub struct TextureHandle {
+    id: egui::TextureId,
+    queue: Weak<Mutex<TextureQueue>>,
+}
+
+impl TextureHandle {
+    /// The id to draw the texture with. It is only valid as long as the handle is alive.
+    pub fn id(&self) -> egui::TextureId {
+        self.id
+    }
+}
+
+impl Drop for TextureHandle {
+    fn drop(&mut self) {
+        // Panicking while unwinding aborts, so a poisoned queue leaks the texture instead.
+        if let Some(queue) = self.queue.upgrade() {
+            if let Ok(mut queue) = queue.lock() {
+                queue.push(TextureCommand::Free(self.id));
+            }
+        }
+    }
+}
+
+impl RenderPass {
+    /// Wraps the user texture `id` in a handle that frees it when dropped. The texture must not be freed
+    /// through `epi::TextureAllocator::free()` as well.
+    pub fn texture_handle(&self, id: egui::TextureId) -> TextureHandle {
+        TextureHandle {
+            id,
+            queue: Arc::downgrade(&self.texture_queue),
+        }
+    }
+}
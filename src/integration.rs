@@ -0,0 +1,304 @@
+//! Glue between winit, egui and the render pass. Needs the `winit` feature.
+
+use std::time::Instant;
+
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+use winit::window::Window;
+
+use crate::{egui, BackendError, RenderPass, ScreenDescriptor, SurfaceRotation};
+
+/// Points scrolled per line of a mouse wheel.
+const POINTS_PER_SCROLL_LINE: f32 = 24.0;
+
+/// Translates the events of a winit window into egui input and renders the UI with a [`RenderPass`].
+///
+/// Call [`EguiIntegration::handle_event`] for every event, [`EguiIntegration::begin_frame`] before building
+/// the UI and [`EguiIntegration::render`] afterwards. The clipboard is not supported, copied text is returned
+/// in the `egui::Output` of `render()`.
+pub struct EguiIntegration {
+    context: egui::CtxRef,
+    raw_input: egui::RawInput,
+    render_pass: RenderPass,
+    start_time: Instant,
+    physical_size: (u32, u32),
+    scale_factor: f64,
+    pointer_position: egui::Pos2,
+    modifiers: egui::Modifiers,
+}
+
+impl EguiIntegration {
+    /// Creates the integration for `window`, which is rendered into targets of `output_format`.
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        window: &Window,
+    ) -> Result<Self, BackendError> {
+        let size = window.inner_size();
+        let mut integration = Self {
+            context: egui::CtxRef::default(),
+            raw_input: egui::RawInput::default(),
+            render_pass: RenderPass::new(device, output_format, 1)?,
+            start_time: Instant::now(),
+            physical_size: (size.width, size.height),
+            scale_factor: window.scale_factor(),
+            pointer_position: egui::Pos2::default(),
+            modifiers: egui::Modifiers::default(),
+        };
+        integration.update_screen_rect();
+        Ok(integration)
+    }
+
+    /// Returns the egui context.
+    pub fn context(&self) -> egui::CtxRef {
+        self.context.clone()
+    }
+
+    /// Returns the render pass, e.g. to register textures.
+    pub fn render_pass(&mut self) -> &mut RenderPass {
+        &mut self.render_pass
+    }
+
+    /// Returns the screen descriptor of the window.
+    pub fn screen_descriptor(&self) -> ScreenDescriptor {
+        ScreenDescriptor {
+            physical_width: self.physical_size.0,
+            physical_height: self.physical_size.1,
+            scale_factor: self.scale_factor as f32,
+            viewport: None,
+            rotation: SurfaceRotation::Identity,
+        }
+    }
+
+    /// Returns `true` if egui wants the pointer or keyboard input, so the app should ignore it.
+    pub fn wants_input(&self) -> bool {
+        self.context.wants_pointer_input() || self.context.wants_keyboard_input()
+    }
+
+    fn update_screen_rect(&mut self) {
+        let size = egui::vec2(self.physical_size.0 as f32, self.physical_size.1 as f32)
+            / self.scale_factor as f32;
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(Default::default(), size));
+        self.raw_input.pixels_per_point = Some(self.scale_factor as f32);
+    }
+
+    /// Translates a winit event into egui input. Events of other windows should be filtered out before.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        let event = match event {
+            Event::WindowEvent { event, .. } => event,
+            _ => return,
+        };
+        let scale_factor = self.scale_factor as f32;
+        match event {
+            WindowEvent::Resized(size) => {
+                self.physical_size = (size.width, size.height);
+                self.update_screen_rect();
+            }
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                self.scale_factor = *scale_factor;
+                self.physical_size = (new_inner_size.width, new_inner_size.height);
+                self.update_screen_rect();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_position = egui::pos2(
+                    position.x as f32 / scale_factor,
+                    position.y as f32 / scale_factor,
+                );
+                self.raw_input
+                    .events
+                    .push(egui::Event::PointerMoved(self.pointer_position));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.raw_input.events.push(egui::Event::PointerGone);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    MouseButton::Other(_) => return,
+                };
+                self.raw_input.events.push(egui::Event::PointerButton {
+                    pos: self.pointer_position,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.raw_input.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        egui::vec2(*x, *y) * POINTS_PER_SCROLL_LINE
+                    }
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        egui::vec2(delta.x as f32, delta.y as f32) / scale_factor
+                    }
+                };
+            }
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = translate_modifiers(*state);
+                self.raw_input.modifiers = self.modifiers;
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(keycode),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                if pressed && self.modifiers.command {
+                    match keycode {
+                        VirtualKeyCode::C => self.raw_input.events.push(egui::Event::Copy),
+                        VirtualKeyCode::X => self.raw_input.events.push(egui::Event::Cut),
+                        _ => {}
+                    }
+                }
+                if let Some(key) = translate_key(*keycode) {
+                    self.raw_input.events.push(egui::Event::Key {
+                        key,
+                        pressed,
+                        modifiers: self.modifiers,
+                    });
+                }
+            }
+            // Private use characters are sent for special keys on macOS.
+            WindowEvent::ReceivedCharacter(c)
+                if !c.is_control() && !('\u{e000}'..='\u{f8ff}').contains(c) =>
+            {
+                self.raw_input.events.push(egui::Event::Text(c.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts a new frame with the input gathered since the last one.
+    pub fn begin_frame(&mut self) {
+        self.raw_input.time = Some(self.start_time.elapsed().as_secs_f64());
+        self.context.begin_frame(self.raw_input.take());
+    }
+
+    /// Ends the frame, uploads the UI and renders it into `color_attachment`, cleared with `clear_color` if set.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<egui::Output, BackendError> {
+        let (output, shapes) = self.context.end_frame();
+        let paint_jobs = self.context.tessellate(shapes);
+        let screen_descriptor = self.screen_descriptor();
+
+        self.render_pass
+            .update_texture(device, queue, &self.context.texture());
+        self.render_pass.update_user_textures(device, queue)?;
+        self.render_pass
+            .update_buffers(device, queue, &paint_jobs, &screen_descriptor)?;
+        self.render_pass.execute(
+            encoder,
+            color_attachment,
+            None,
+            &paint_jobs,
+            &screen_descriptor,
+            clear_color,
+        )?;
+        Ok(output)
+    }
+
+    /// Applies the parts of `output` that concern the window, currently the cursor icon.
+    pub fn handle_output(&self, window: &Window, output: &egui::Output) {
+        use winit::window::CursorIcon;
+        window.set_cursor_icon(match output.cursor_icon {
+            egui::CursorIcon::Default => CursorIcon::Default,
+            egui::CursorIcon::PointingHand => CursorIcon::Hand,
+            egui::CursorIcon::ResizeHorizontal => CursorIcon::EwResize,
+            egui::CursorIcon::ResizeNeSw => CursorIcon::NeswResize,
+            egui::CursorIcon::ResizeNwSe => CursorIcon::NwseResize,
+            egui::CursorIcon::ResizeVertical => CursorIcon::NsResize,
+            egui::CursorIcon::Text => CursorIcon::Text,
+            egui::CursorIcon::Grab => CursorIcon::Grab,
+            egui::CursorIcon::Grabbing => CursorIcon::Grabbing,
+        });
+    }
+}
+
+fn translate_modifiers(state: ModifiersState) -> egui::Modifiers {
+    egui::Modifiers {
+        alt: state.alt(),
+        ctrl: state.ctrl(),
+        shift: state.shift(),
+        mac_cmd: cfg!(target_os = "macos") && state.logo(),
+        command: if cfg!(target_os = "macos") {
+            state.logo()
+        } else {
+            state.ctrl()
+        },
+    }
+}
+
+fn translate_key(keycode: VirtualKeyCode) -> Option<egui::Key> {
+    use egui::Key;
+    Some(match keycode {
+        VirtualKeyCode::Down => Key::ArrowDown,
+        VirtualKeyCode::Left => Key::ArrowLeft,
+        VirtualKeyCode::Right => Key::ArrowRight,
+        VirtualKeyCode::Up => Key::ArrowUp,
+        VirtualKeyCode::Escape => Key::Escape,
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Back => Key::Backspace,
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::Insert => Key::Insert,
+        VirtualKeyCode::Delete => Key::Delete,
+        VirtualKeyCode::Home => Key::Home,
+        VirtualKeyCode::End => Key::End,
+        VirtualKeyCode::PageUp => Key::PageUp,
+        VirtualKeyCode::PageDown => Key::PageDown,
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Key::Num0,
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Key::Num1,
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Key::Num2,
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Key::Num3,
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Key::Num4,
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Key::Num5,
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Key::Num6,
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Key::Num7,
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Key::Num8,
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Key::Num9,
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::B => Key::B,
+        VirtualKeyCode::C => Key::C,
+        VirtualKeyCode::D => Key::D,
+        VirtualKeyCode::E => Key::E,
+        VirtualKeyCode::F => Key::F,
+        VirtualKeyCode::G => Key::G,
+        VirtualKeyCode::H => Key::H,
+        VirtualKeyCode::I => Key::I,
+        VirtualKeyCode::J => Key::J,
+        VirtualKeyCode::K => Key::K,
+        VirtualKeyCode::L => Key::L,
+        VirtualKeyCode::M => Key::M,
+        VirtualKeyCode::N => Key::N,
+        VirtualKeyCode::O => Key::O,
+        VirtualKeyCode::P => Key::P,
+        VirtualKeyCode::Q => Key::Q,
+        VirtualKeyCode::R => Key::R,
+        VirtualKeyCode::S => Key::S,
+        VirtualKeyCode::T => Key::T,
+        VirtualKeyCode::U => Key::U,
+        VirtualKeyCode::V => Key::V,
+        VirtualKeyCode::W => Key::W,
+        VirtualKeyCode::X => Key::X,
+        VirtualKeyCode::Y => Key::Y,
+        VirtualKeyCode::Z => Key::Z,
+        _ => return None,
+    })
+}
@@ -4,389 +4,4267 @@
 //! A basic usage example can be found [here](https://github.com/hasenbanck/egui_example).
 #![warn(missing_docs)]
 
+/// Includes a shader of `src/shader` by its name, as SPIR-V with the `spirv` feature and as WGSL otherwise.
+/// The push constant and depth and integer texture variants only exist as SPIR-V.
+#[cfg(feature = "spirv")]
+macro_rules! include_shader {
+    ($name:literal) => {
+        wgpu::include_spirv!(concat!("shader/", $name, ".spirv"))
+    };
+}
+
+/// Includes a shader of `src/shader` by its name, as SPIR-V with the `spirv` feature and as WGSL otherwise.
+/// The push constant and depth and integer texture variants only exist as SPIR-V.
+#[cfg(not(feature = "spirv"))]
+macro_rules! include_shader {
+    ($name:literal) => {
+        wgpu::ShaderModuleDescriptor {
+            label: Some($name),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(concat!(
+                "shader/", $name, ".wgsl"
+            )))),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        }
+    };
+}
+
+mod backdrop;
+#[cfg(feature = "bcn")]
+mod bcn;
+mod belt;
+mod callback;
+mod commands;
+mod context;
+mod frame;
+#[cfg(feature = "framework")]
+pub mod framework;
+mod handle;
+#[cfg(feature = "winit")]
+mod integration;
+mod offscreen;
+mod overdraw;
+mod painter;
+mod readback;
+#[cfg(feature = "simple")]
+pub mod simple;
+mod stats;
+mod stencil;
+#[cfg(feature = "stress")]
+pub mod stress;
+mod tiled;
+mod timing;
+#[cfg(feature = "winit")]
+mod window;
+
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU8;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
 use bytemuck::{Pod, Zeroable};
 pub use epi;
 pub use epi::egui;
 pub use wgpu;
-use wgpu::{include_spirv, util::DeviceExt};
 
-/// Enum for selecting the right buffer type.
-#[derive(Debug)]
-enum BufferType {
-    Uniform,
-    Index,
-    Vertex,
-}
+use backdrop::Backdrop;
+pub use backdrop::BackdropBlurOptions;
+use belt::StagingUploads;
+use callback::PositionedCallback;
+pub use callback::{PaintCallback, PaintCallbackInfo};
+use commands::SharedTextureQueue;
+pub use commands::TextureCommandSender;
+pub use context::RenderContext;
+use context::SharedTexture;
+pub use frame::FrameResources;
+pub use handle::TextureHandle;
+#[cfg(feature = "winit")]
+pub use integration::EguiIntegration;
+pub use painter::{EguiPainter, Painter, SurfacePainter};
+pub use readback::{read_pixels, read_pixels_async, srgba8_from_pixels};
+pub use stats::RenderStats;
+use stats::StatsCounters;
+use stencil::StencilClip;
+pub use stencil::STENCIL_CLIP_FORMAT;
+pub use tiled::{
+    ImageTile, StreamedImage, TileKey, TileProvider, TiledImage, DEFAULT_MAX_TILE_SIZE,
+};
+use timing::GpuTimer;
+use wgpu::util::DeviceExt;
+#[cfg(feature = "winit")]
+pub use window::WindowSurface;
 
 /// Information about the screen used for rendering.
 pub struct ScreenDescriptor {
-    /// Width of the window in physical pixel.
+    /// Width of the render target in physical pixel.
     pub physical_width: u32,
-    /// Height of the window in physical pixel.
+    /// Height of the render target in physical pixel.
     pub physical_height: u32,
     /// HiDPI scale factor.
     pub scale_factor: f32,
+    /// Region of the window the UI is rendered into. `None` uses the whole window.
+    pub viewport: Option<ScreenViewport>,
+    /// Rotation of the render target relative to the window as the user sees it.
+    pub rotation: SurfaceRotation,
+}
+
+/// Clockwise rotation applied to the UI so the compositor doesn't need to rotate the surface, e.g. for the
+/// pre-transform reported by Android surfaces. With a quarter turn, the render target is as wide as the window
+/// is tall, and viewports and window positions stay in the orientation of the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceRotation {
+    /// No rotation.
+    Identity,
+    /// Rotated by 90 degrees.
+    Rotate90,
+    /// Rotated by 180 degrees.
+    Rotate180,
+    /// Rotated by 270 degrees.
+    Rotate270,
+}
+
+impl Default for SurfaceRotation {
+    fn default() -> Self {
+        SurfaceRotation::Identity
+    }
+}
+
+/// A region of the window the UI is rendered into, e.g. to show a UI with a fixed aspect ratio.
+/// The screen size passed to egui needs to be the size of the region in points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenViewport {
+    /// Left edge of the region in physical pixels.
+    pub x: u32,
+    /// Top edge of the region in physical pixels.
+    pub y: u32,
+    /// Width of the region in physical pixels.
+    pub width: u32,
+    /// Height of the region in physical pixels.
+    pub height: u32,
+    /// Color of the bars drawn over the window outside of the region. `None` leaves it untouched.
+    pub letterbox_color: Option<egui::Color32>,
+}
+
+impl ScreenViewport {
+    /// Returns the largest region with `aspect_ratio`, its width divided by its height, centered in a window of
+    /// `physical_width` times `physical_height` pixels.
+    pub fn centered(
+        physical_width: u32,
+        physical_height: u32,
+        aspect_ratio: f32,
+        letterbox_color: Option<egui::Color32>,
+    ) -> Self {
+        let width = (physical_height as f32 * aspect_ratio).round() as u32;
+        let (width, height) = if width <= physical_width {
+            (width, physical_height)
+        } else {
+            let height = (physical_width as f32 / aspect_ratio).round() as u32;
+            (physical_width, height.min(physical_height))
+        };
+        Self {
+            x: (physical_width - width) / 2,
+            y: (physical_height - height) / 2,
+            width,
+            height,
+            letterbox_color,
+        }
+    }
 }
 
 impl ScreenDescriptor {
+    /// Returns the size of the window in physical pixels, which is the size of the target before the rotation.
+    fn window_size(&self) -> (u32, u32) {
+        match self.rotation {
+            SurfaceRotation::Identity | SurfaceRotation::Rotate180 => {
+                (self.physical_width, self.physical_height)
+            }
+            SurfaceRotation::Rotate90 | SurfaceRotation::Rotate270 => {
+                (self.physical_height, self.physical_width)
+            }
+        }
+    }
+
+    /// Transforms a rect `(x, y, width, height)` in physical pixels of the window into the render target.
+    fn rotate_rect(&self, rect: (u32, u32, u32, u32)) -> (u32, u32, u32, u32) {
+        let (x, y, width, height) = rect;
+        let (window_width, window_height) = self.window_size();
+        match self.rotation {
+            SurfaceRotation::Identity => rect,
+            SurfaceRotation::Rotate90 => (window_height - y - height, x, height, width),
+            SurfaceRotation::Rotate180 => (
+                window_width - x - width,
+                window_height - y - height,
+                width,
+                height,
+            ),
+            SurfaceRotation::Rotate270 => (y, window_width - x - width, height, width),
+        }
+    }
+
+    /// Returns the region the UI is rendered into in physical pixels of the render target.
+    fn target_region(&self) -> (u32, u32, u32, u32) {
+        self.rotate_rect(self.ui_region())
+    }
+
     fn logical_size(&self) -> (u32, u32) {
-        let logical_width = self.physical_width as f32 / self.scale_factor;
-        let logical_height = self.physical_height as f32 / self.scale_factor;
+        let (_, _, width, height) = self.ui_region();
+        let logical_width = width as f32 / self.scale_factor;
+        let logical_height = height as f32 / self.scale_factor;
         (logical_width as u32, logical_height as u32)
     }
+
+    /// Returns the region `(x, y, width, height)` the UI is rendered into in physical pixels of the window,
+    /// clamped to the window.
+    fn ui_region(&self) -> (u32, u32, u32, u32) {
+        let (window_width, window_height) = self.window_size();
+        match &self.viewport {
+            Some(viewport) => {
+                let x = viewport.x.min(window_width);
+                let y = viewport.y.min(window_height);
+                let width = viewport.width.min(window_width - x);
+                let height = viewport.height.min(window_height - y);
+                (x, y, width, height)
+            }
+            None => (0, 0, window_width, window_height),
+        }
+    }
+
+    /// Transforms a position in physical pixels of the window, e.g. of a mouse event, into points of the UI.
+    pub fn window_to_ui(&self, position: egui::Pos2) -> egui::Pos2 {
+        let (x, y, _, _) = self.ui_region();
+        egui::pos2(
+            (position.x - x as f32) / self.scale_factor,
+            (position.y - y as f32) / self.scale_factor,
+        )
+    }
+
+    /// Transforms a clip rect in points into a scissor rect `(x, y, width, height)` in physical pixels of the
+    /// render target, grown by `expansion` physical pixels on each side. Returns `None` if the scissor rect
+    /// has no area.
+    fn scissor_rect(&self, clip_rect: &egui::Rect, expansion: f32) -> Option<(u32, u32, u32, u32)> {
+        let (region_x, region_y, physical_width, physical_height) = self.ui_region();
+        let scale_factor = self.scale_factor;
+
+        // Transform clip rect to physical pixels.
+        let clip_min_x = scale_factor * clip_rect.min.x - expansion;
+        let clip_min_y = scale_factor * clip_rect.min.y - expansion;
+        let clip_max_x = scale_factor * clip_rect.max.x + expansion;
+        let clip_max_y = scale_factor * clip_rect.max.y + expansion;
+
+        // Make sure clip rect can fit within an `u32`.
+        let clip_min_x = egui::clamp(clip_min_x, 0.0..=physical_width as f32);
+        let clip_min_y = egui::clamp(clip_min_y, 0.0..=physical_height as f32);
+        let clip_max_x = egui::clamp(clip_max_x, clip_min_x..=physical_width as f32);
+        let clip_max_y = egui::clamp(clip_max_y, clip_min_y..=physical_height as f32);
+
+        let clip_min_x = clip_min_x.round() as u32;
+        let clip_min_y = clip_min_y.round() as u32;
+        let clip_max_x = clip_max_x.round() as u32;
+        let clip_max_y = clip_max_y.round() as u32;
+
+        let width = (clip_max_x - clip_min_x).max(1);
+        let height = (clip_max_y - clip_min_y).max(1);
+
+        // clip scissor rectangle to target size
+        let x = clip_min_x.min(physical_width);
+        let y = clip_min_y.min(physical_height);
+        let width = width.min(physical_width - x);
+        let height = height.min(physical_height - y);
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(self.rotate_rect((x + region_x, y + region_y, width, height)))
+    }
+
+    /// Returns the bars `(x, y, width, height)` of the window outside of the UI region in physical pixels
+    /// of the render target.
+    fn letterbox_bars(&self) -> Vec<(u32, u32, u32, u32)> {
+        let (x, y, width, height) = self.ui_region();
+        let (window_width, window_height) = self.window_size();
+        let bars = [
+            (0, 0, window_width, y),
+            (0, y + height, window_width, window_height - y - height),
+            (0, y, x, height),
+            (x + width, y, window_width - x - width, height),
+        ];
+        bars.iter()
+            .copied()
+            .filter(|&(_, _, width, height)| width > 0 && height > 0)
+            .map(|bar| self.rotate_rect(bar))
+            .collect()
+    }
 }
 
-/// Uniform buffer used when rendering.
+/// A target for [`RenderPass::execute_multiple`].
+pub struct RenderTarget<'a> {
+    /// The view to render into.
+    pub color_attachment: &'a wgpu::TextureView,
+    /// Information about the target used for rendering.
+    pub screen_descriptor: &'a ScreenDescriptor,
+}
+
+/// A region of the render target that is cleared before the UI is drawn, for [`RenderPass::execute_with_clear_rect`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClearRect {
+    /// The color the region is cleared with.
+    pub color: wgpu::Color,
+    /// Left edge in physical pixels of the window.
+    pub x: u32,
+    /// Top edge in physical pixels of the window.
+    pub y: u32,
+    /// Width in physical pixels.
+    pub width: u32,
+    /// Height in physical pixels.
+    pub height: u32,
+}
+
+/// A draw call of the paint jobs after clipping and buffer uploads, returned by [`RenderPass::draw_commands`].
+#[derive(Clone, Copy, Debug)]
+pub struct DrawCommand<'a> {
+    /// Index of the mesh in the paint jobs.
+    pub mesh_index: usize,
+    /// The mesh in points.
+    pub mesh: &'a egui::paint::Mesh,
+    /// Scissor rect as `[x, y, width, height]` in physical pixels of the render target.
+    pub scissor_rect: [u32; 4],
+    /// Texture the mesh is drawn with.
+    pub texture_id: egui::TextureId,
+    /// View of the texture. `None` if the texture is not uploaded yet or was evicted.
+    pub texture_view: Option<&'a wgpu::TextureView>,
+    /// Part of the shared vertex buffer holding the vertices of the mesh, in the layout of `egui::paint::Vertex`.
+    pub vertex_buffer: wgpu::BufferSlice<'a>,
+    /// Part of the shared index buffer holding the `Uint32` indices of the mesh.
+    pub index_buffer: wgpu::BufferSlice<'a>,
+    /// Number of indices to draw.
+    pub index_count: u32,
+}
+
+/// The render pass descriptor of `execute()`, which [`RenderPass::execute_with_pass_descriptor`] lets the
+/// caller adjust before the pass begins.
+///
+/// The egui pipelines draw into a single color target of the output format, with a depth-stencil
+/// attachment only if `RenderPassConfig::depth_stencil_format` is set, so the attachments need to stay
+/// compatible with that. The label, the load and store
+/// operations, the color attachment and its resolve target can be changed freely.
+#[derive(Clone, Debug)]
+pub struct PassDescriptor<'a> {
+    /// Debug label of the render pass.
+    pub label: Option<&'a str>,
+    /// Color attachments of the pass, the output attachment of `execute()` by default.
+    pub color_attachments: Vec<wgpu::RenderPassColorAttachmentDescriptor<'a>>,
+    /// Depth-stencil attachment of the pass, `None` by default.
+    pub depth_stencil_attachment: Option<wgpu::RenderPassDepthStencilAttachmentDescriptor<'a>>,
+}
+
+/// Information about a mesh that is about to be drawn, passed to the [`DrawHook`].
 #[derive(Clone, Copy, Debug)]
+pub struct DrawInfo<'a> {
+    /// Index of the mesh in the paint jobs.
+    pub mesh_index: usize,
+    /// Clip rect of the mesh in points.
+    pub clip_rect: &'a egui::Rect,
+    /// Texture the mesh is drawn with.
+    pub texture_id: egui::TextureId,
+}
+
+/// Scissor rect and viewport of a draw call, which the [`DrawHook`] can adjust.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawState {
+    /// Scissor rect as `[x, y, width, height]` in physical pixels. Gets clamped to the target size.
+    pub scissor_rect: [u32; 4],
+    /// Viewport as `[x, y, width, height]` in physical pixels. Defaults to the whole target.
+    pub viewport: [f32; 4],
+}
+
+/// Callback invoked before every mesh is drawn. It can adjust the draw state and
+/// returns `false` to skip the mesh.
+pub type DrawHook = dyn Fn(&DrawInfo, &mut DrawState) -> bool + Send + Sync;
+
+/// Callback that provides the size and srgba premultiplied pixels of an evicted user texture when it is
+/// drawn again. Returns `None` if the pixels are not available yet, in which case it is asked again next frame.
+pub type TextureProvider =
+    dyn FnMut(egui::TextureId) -> Option<((usize, usize), Vec<egui::Color32>)> + Send + Sync;
+
+/// Uniform buffer used when rendering.
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
 struct UniformBuffer {
     screen_size: [f32; 2],
+    gamma: f32,
+    dither_strength: f32,
+    srgb_encode: u32,
+    gamma_blending: u32,
+    physical_size: [f32; 2],
+    rotation: u32,
+    color_transform: u32,
+    _padding: [u32; 2],
+    /// Columns of the matrix applied to the linear output color, padded to `vec4`.
+    color_matrix: [[f32; 4]; 3],
+    /// Exponents applied to the channels after the matrix, padded to `vec4`.
+    tone_curve: [f32; 4],
+}
+
+impl UniformBuffer {
+    fn new(
+        screen_descriptor: &ScreenDescriptor,
+        constants: &ShaderConstants,
+        blend_space: BlendSpace,
+    ) -> Self {
+        let (logical_width, logical_height) = screen_descriptor.logical_size();
+        let transform = constants.color_space.transform();
+        let column = |i: usize| {
+            let matrix = transform.map_or(IDENTITY_MATRIX, |transform| transform.matrix);
+            [matrix[0][i], matrix[1][i], matrix[2][i], 0.0]
+        };
+        let [red, green, blue] = transform.map_or([1.0; 3], |transform| transform.tone_curve);
+        Self {
+            screen_size: [logical_width as f32, logical_height as f32],
+            gamma: constants.gamma,
+            dither_strength: constants.dither_strength,
+            srgb_encode: constants.srgb_encode as u32,
+            gamma_blending: (blend_space == BlendSpace::Gamma) as u32,
+            physical_size: [
+                screen_descriptor.physical_width as f32,
+                screen_descriptor.physical_height as f32,
+            ],
+            rotation: screen_descriptor.rotation as u32,
+            color_transform: transform.is_some() as u32,
+            _padding: [0; 2],
+            color_matrix: [column(0), column(1), column(2)],
+            tone_curve: [red, green, blue, 1.0],
+        }
+    }
 }
 
 unsafe impl Pod for UniformBuffer {}
 
 unsafe impl Zeroable for UniformBuffer {}
 
-/// Wraps the buffers and includes additional information.
-#[derive(Debug)]
-struct SizedBuffer {
-    buffer: wgpu::Buffer,
-    size: usize,
+/// Uniform buffer with the per texture settings.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct TextureUniformBuffer {
+    lod_bias: f32,
+    screen_space_uv: u32,
+    colormap: u32,
+    value_min: f32,
+    value_max: f32,
+    exposure: f32,
+    tone_mapping: u32,
+    uv_transform: u32,
+    coverage: u32,
+    /// Number of colors of the custom colormap, since the WGSL shaders can't query the size of its texture.
+    colormap_size: u32,
 }
 
-/// RenderPass to render a egui based GUI.
-pub struct RenderPass {
-    render_pipeline: wgpu::RenderPipeline,
-    index_buffers: Vec<SizedBuffer>,
-    vertex_buffers: Vec<SizedBuffer>,
-    uniform_buffer: SizedBuffer,
-    uniform_bind_group: wgpu::BindGroup,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
-    texture_bind_group: Option<wgpu::BindGroup>,
-    texture_version: Option<u64>,
-    next_user_texture_id: u64,
-    pending_user_textures: Vec<(u64, egui::Texture)>,
-    user_textures: Vec<Option<wgpu::BindGroup>>,
+impl TextureUniformBuffer {
+    fn new(sampler: &SamplerOptions, params: &TextureParams, colormap_size: u32) -> Self {
+        Self {
+            lod_bias: sampler.lod_bias,
+            screen_space_uv: params.screen_space_uv as u32,
+            colormap: match params.colormap {
+                Colormap::None => 0,
+                Colormap::Grayscale => 1,
+                Colormap::Viridis => 2,
+                Colormap::Magma => 3,
+                Colormap::Custom(_) => 4,
+            },
+            value_min: params.value_range.0,
+            value_max: params.value_range.1,
+            exposure: params.exposure.exp2(),
+            tone_mapping: params.tone_mapping as u32,
+            uv_transform: params.uv_transform.flip_x as u32
+                | (params.uv_transform.flip_y as u32) << 1
+                | (params.uv_transform.rotate_90 as u32) << 2,
+            coverage: params.coverage as u32,
+            colormap_size,
+        }
+    }
 }
 
-impl RenderPass {
-    /// Creates a new render pass to render a egui UI. `output_format` needs to be either `wgpu::TextureFormat::Rgba8UnormSrgb` or `wgpu::TextureFormat::Bgra8UnormSrgb`. Panics if it's not a Srgb format.
-    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
-        if !(output_format == wgpu::TextureFormat::Rgba8UnormSrgb
-            || output_format == wgpu::TextureFormat::Bgra8UnormSrgb)
-        {
-            panic!("Incompatible output_format. Needs to be either Rgba8UnormSrgb or Bgra8UnormSrgb: {:?}", output_format);
+unsafe impl Pod for TextureUniformBuffer {}
+
+unsafe impl Zeroable for TextureUniformBuffer {}
+
+/// Per texture settings read by the fragment shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TextureParams {
+    /// Samples the texture at the position of the fragment on the screen instead of the vertex UVs.
+    screen_space_uv: bool,
+    /// How meshes using the texture are composited with the output.
+    blend_mode: TextureBlendMode,
+    /// How the fragment shader reads the texture.
+    sample_kind: TextureSampleKind,
+    /// Colors single channel data.
+    colormap: Colormap,
+    /// Data values mapped to `0.0` and `1.0` before applying the colormap.
+    value_range: (f32, f32),
+    /// Exposure in stops the color is scaled with when sampling.
+    exposure: f32,
+    /// Maps HDR colors into the displayable range after the exposure.
+    tone_mapping: ToneMapping,
+    /// Orientation of the texture.
+    uv_transform: UvTransform,
+    /// The red channel holds the coverage of premultiplied white, like the single channel egui texture.
+    coverage: bool,
+}
+
+impl Default for TextureParams {
+    fn default() -> Self {
+        Self {
+            screen_space_uv: false,
+            blend_mode: TextureBlendMode::default(),
+            sample_kind: TextureSampleKind::default(),
+            colormap: Colormap::default(),
+            value_range: (0.0, 1.0),
+            exposure: 0.0,
+            tone_mapping: ToneMapping::default(),
+            uv_transform: UvTransform::default(),
+            coverage: false,
         }
+    }
+}
 
-        let vs_module = device.create_shader_module(&include_spirv!("shader/egui.vert.spirv"));
-        let fs_module = device.create_shader_module(&include_spirv!("shader/egui.frag.spirv"));
+/// Orientation of a user texture, e.g. for upside down video frames or images from OpenGL.
+/// The texture is first rotated, then flipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UvTransform {
+    /// Mirrors the texture horizontally.
+    pub flip_x: bool,
+    /// Mirrors the texture vertically.
+    pub flip_y: bool,
+    /// Rotates the texture by 90 degrees clockwise.
+    pub rotate_90: bool,
+}
 
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("egui_uniform_buffer"),
-            contents: bytemuck::cast_slice(&[UniformBuffer {
-                screen_size: [0.0, 0.0],
-            }]),
-            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-        });
-        let uniform_buffer = SizedBuffer {
-            buffer: uniform_buffer,
-            size: std::mem::size_of::<UniformBuffer>(),
-        };
+/// Operator mapping the colors of HDR textures into the displayable range in the shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Clips values above `1.0`, which keeps colors in range exact.
+    Clamp = 0,
+    /// Reinhard's operator `c / (1 + c)`, which compresses highlights smoothly but darkens the midtones.
+    Reinhard = 1,
+    /// Narkowicz' fit of the ACES filmic curve, with more contrast than Reinhard.
+    Aces = 2,
+}
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("egui_texture_sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping::Clamp
+    }
+}
 
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("egui_uniform_bind_group_layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStage::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                            ty: wgpu::BufferBindingType::Uniform,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler {
-                            filtering: true,
-                            comparison: false,
-                        },
-                        count: None,
-                    },
-                ],
-            });
+/// Maps the values of single channel data textures to colors in the shader. Reads the red channel,
+/// so it works with any format, e.g. `R8Unorm` or `R16Float` with values in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    /// Shows the texture as it is.
+    None,
+    /// Black to white.
+    Grayscale,
+    /// The perceptually uniform viridis colormap of matplotlib.
+    Viridis,
+    /// The perceptually uniform magma colormap of matplotlib.
+    Magma,
+    /// A lookup table created with [`RenderPass::create_colormap_lut`]. Only applies to color textures.
+    Custom(ColormapLut),
+}
 
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("egui_uniform_bind_group"),
-            layout: &uniform_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: &uniform_buffer.buffer,
-                        offset: 0,
-                        size: None,
-                    },
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+impl Default for Colormap {
+    fn default() -> Self {
+        Colormap::None
+    }
+}
 
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("egui_texture_bind_group_layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                }],
-            });
+/// Handle of a colormap lookup table. Only valid for the render pass that created it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColormapLut(usize);
 
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("egui_pipeline_layout"),
-            bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+/// A colormap lookup table.
+struct ColormapLutData {
+    colors: Vec<egui::Color32>,
+    view: wgpu::TextureView,
+}
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("egui_pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                entry_point: "main",
-                module: &vs_module,
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: 5 * 4,
-                    step_mode: wgpu::InputStepMode::Vertex,
-                    // 0: vec2 position
-                    // 1: vec2 texture coordinates
-                    // 2: uint color
-                    attributes: &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Uint],
-                }],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                cull_mode: wgpu::CullMode::default(),
-                front_face: wgpu::FrontFace::default(),
-                polygon_mode: wgpu::PolygonMode::default(),
-                strip_index_format: Some(wgpu::IndexFormat::Uint32),
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                alpha_to_coverage_enabled: false,
-                count: 1,
-                mask: !0,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &fs_module,
-                entry_point: "main",
-                targets: &[wgpu::ColorTargetState {
-                    format: output_format,
-                    color_blend: wgpu::BlendState {
-                        src_factor: wgpu::BlendFactor::One,
-                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                    alpha_blend: wgpu::BlendState {
-                        src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
-                        dst_factor: wgpu::BlendFactor::One,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-            }),
-        });
+/// How the fragment shader reads a texture. Every kind has its own bind group layout and pipeline variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TextureSampleKind {
+    /// Filtered color.
+    Float,
+    /// Color of formats that can't be filtered, like `R32Float`, sampled with the nearest texel.
+    UnfilterableFloat,
+    /// Depth shown as gray scale.
+    Depth,
+    /// Unsigned integers shown as distinct colors.
+    Uint,
+    /// Signed integers shown as distinct colors.
+    Sint,
+}
 
-        Self {
-            render_pipeline,
-            vertex_buffers: Vec::with_capacity(64),
-            index_buffers: Vec::with_capacity(64),
-            uniform_buffer,
-            uniform_bind_group,
-            texture_bind_group_layout,
-            texture_version: None,
-            texture_bind_group: None,
-            next_user_texture_id: 0,
-            pending_user_textures: Vec::new(),
-            user_textures: Vec::new(),
-        }
+impl Default for TextureSampleKind {
+    fn default() -> Self {
+        TextureSampleKind::Float
     }
+}
 
-    /// Executes the egui render pass. When `clear_on_draw` is set, the output target will get cleared before writing to it.
-    pub fn execute(
-        &mut self,
-        encoder: &mut wgpu::CommandEncoder,
-        color_attachment: &wgpu::TextureView,
-        paint_jobs: &[egui::paint::ClippedMesh],
-        screen_descriptor: &ScreenDescriptor,
-        clear_color: Option<wgpu::Color>,
-    ) {
-        let load_operation = if let Some(color) = clear_color {
-            wgpu::LoadOp::Clear(color)
+impl TextureSampleKind {
+    /// Returns the kind of a view of a texture with `format`, or `None` if it can't be displayed.
+    /// Depth, stencil and integer textures need the `spirv` feature, since the WGSL frontend of wgpu 0.7 can't
+    /// sample depth textures or query the size of a texture for texel fetches.
+    fn from_format(format: wgpu::TextureFormat, aspect: wgpu::TextureAspect) -> Option<Self> {
+        let kind = if aspect == wgpu::TextureAspect::StencilOnly {
+            TextureSampleKind::Uint
         } else {
-            wgpu::LoadOp::Load
+            match format.describe().sample_type {
+                wgpu::TextureSampleType::Float { filterable: true } => TextureSampleKind::Float,
+                wgpu::TextureSampleType::Float { filterable: false } => {
+                    TextureSampleKind::UnfilterableFloat
+                }
+                wgpu::TextureSampleType::Depth => TextureSampleKind::Depth,
+                wgpu::TextureSampleType::Uint => TextureSampleKind::Uint,
+                wgpu::TextureSampleType::Sint => TextureSampleKind::Sint,
+            }
         };
+        if cfg!(feature = "spirv") || kind.has_colormap_lut() {
+            Some(kind)
+        } else {
+            None
+        }
+    }
 
-        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: color_attachment,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: load_operation,
-                    store: true,
-                },
-            }],
-            depth_stencil_attachment: None,
-            label: Some("egui main render pass"),
-        });
-        pass.push_debug_group("egui_pass");
-        pass.set_pipeline(&self.render_pipeline);
-
-        pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+    /// Returns `true` if textures of this kind can use a colormap lookup table.
+    fn has_colormap_lut(self) -> bool {
+        matches!(
+            self,
+            TextureSampleKind::Float | TextureSampleKind::UnfilterableFloat
+        )
+    }
+}
 
-        let scale_factor = screen_descriptor.scale_factor;
-        let physical_width = screen_descriptor.physical_width;
-        let physical_height = screen_descriptor.physical_height;
+/// Bind group layout and shaders of a `TextureSampleKind` other than `Float`.
+struct SampleKindLayout {
+    sample_kind: TextureSampleKind,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    fs_module: wgpu::ShaderModule,
+    /// These textures can't be filtered, so they share a nearest sampler.
+    sampler: wgpu::Sampler,
+}
 
-        for ((egui::ClippedMesh(clip_rect, mesh), vertex_buffer), index_buffer) in paint_jobs
-            .iter()
-            .zip(self.vertex_buffers.iter())
-            .zip(self.index_buffers.iter())
-        {
-            // Transform clip rect to physical pixels.
-            let clip_min_x = scale_factor * clip_rect.min.x;
-            let clip_min_y = scale_factor * clip_rect.min.y;
-            let clip_max_x = scale_factor * clip_rect.max.x;
-            let clip_max_y = scale_factor * clip_rect.max.y;
-
-            // Make sure clip rect can fit within an `u32`.
-            let clip_min_x = egui::clamp(clip_min_x, 0.0..=physical_width as f32);
-            let clip_min_y = egui::clamp(clip_min_y, 0.0..=physical_height as f32);
-            let clip_max_x = egui::clamp(clip_max_x, clip_min_x..=physical_width as f32);
-            let clip_max_y = egui::clamp(clip_max_y, clip_min_y..=physical_height as f32);
-
-            let clip_min_x = clip_min_x.round() as u32;
-            let clip_min_y = clip_min_y.round() as u32;
-            let clip_max_x = clip_max_x.round() as u32;
-            let clip_max_y = clip_max_y.round() as u32;
-
-            let width = (clip_max_x - clip_min_x).max(1);
-            let height = (clip_max_y - clip_min_y).max(1);
+/// Selects the pipeline a mesh is drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct PipelineKey {
+    blend_mode: TextureBlendMode,
+    sample_kind: TextureSampleKind,
+}
 
-            {
-                // clip scissor rectangle to target size
-                let x = clip_min_x.min(physical_width);
-                let y = clip_min_y.min(physical_height);
-                let width = width.min(physical_width - x);
-                let height = height.min(physical_height - y);
+/// How meshes using a user texture are composited with what is already in the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureBlendMode {
+    /// Regular premultiplied alpha blending.
+    Normal,
+    /// Adds the color to the output, e.g. for glow sprites.
+    Additive,
+    /// Multiplies the output with the color, e.g. for lighting and shadow maps.
+    Multiply,
+}
 
-                // skip rendering with zero-sized clip areas
-                if width == 0 || height == 0 {
-                    continue;
-                }
+impl Default for TextureBlendMode {
+    fn default() -> Self {
+        TextureBlendMode::Normal
+    }
+}
 
-                pass.set_scissor_rect(x, y, width, height);
+impl TextureBlendMode {
+    fn color_blend(self) -> wgpu::BlendState {
+        let (src_factor, dst_factor) = match self {
+            TextureBlendMode::Normal => {
+                (wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha)
             }
-            pass.set_bind_group(1, self.get_texture_bind_group(mesh.texture_id), &[]);
-
-            pass.set_index_buffer(index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
-            pass.set_vertex_buffer(0, vertex_buffer.buffer.slice(..));
-            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+            TextureBlendMode::Additive => (wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+            TextureBlendMode::Multiply => (
+                wgpu::BlendFactor::DstColor,
+                wgpu::BlendFactor::OneMinusSrcAlpha,
+            ),
+        };
+        wgpu::BlendState {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
         }
-
-        pass.pop_debug_group();
     }
+}
 
-    fn get_texture_bind_group(&self, texture_id: egui::TextureId) -> &wgpu::BindGroup {
-        match texture_id {
-            egui::TextureId::Egui => self
-                .texture_bind_group
-                .as_ref()
-                .expect("egui texture was not set before the first draw"),
-            egui::TextureId::User(id) => {
-                let id = id as usize;
-                assert!(id < self.user_textures.len());
-                &(self
-                    .user_textures
-                    .get(id)
-                    .unwrap_or_else(|| panic!("user texture {} not found", id))
-                    .as_ref()
-                    .unwrap_or_else(|| panic!("user texture {} freed", id)))
-            }
+/// Settings of the sampler used to sample a texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerOptions {
+    /// How to address texture coordinates outside of `0.0..=1.0` horizontally.
+    /// Use `Repeat` or `MirrorRepeat` together with larger UVs to tile a texture.
+    pub address_mode_u: wgpu::AddressMode,
+    /// How to address texture coordinates outside of `0.0..=1.0` vertically.
+    pub address_mode_v: wgpu::AddressMode,
+    /// How to filter the texture when it needs to be magnified.
+    pub mag_filter: wgpu::FilterMode,
+    /// How to filter the texture when it needs to be minified.
+    pub min_filter: wgpu::FilterMode,
+    /// How to filter between mip map levels.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Minimum level of detail (i.e. mip level) to use.
+    pub lod_min_clamp: f32,
+    /// Maximum level of detail (i.e. mip level) to use.
+    pub lod_max_clamp: f32,
+    /// Bias added to the level of detail calculated by the GPU. Positive values soften, negative values sharpen.
+    pub lod_bias: f32,
+    /// Enables anisotropic filtering with the given maximal anisotropy. Must be 1, 2, 4, 8 or 16.
+    /// Ignored if the device doesn't support anisotropic filtering.
+    pub anisotropy_clamp: Option<NonZeroU8>,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            lod_bias: 0.0,
+            anisotropy_clamp: None,
         }
     }
+}
 
-    /// Updates the texture used by egui for the fonts etc. Should be called before `execute()`.
-    pub fn update_texture(
-        &mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        egui_texture: &egui::Texture,
-    ) {
-        // Don't update the texture if it hasn't changed.
-        if self.texture_version == Some(egui_texture.version) {
-            return;
-        }
-        // we need to convert the texture into rgba_srgb format
-        let mut pixels: Vec<u8> = Vec::with_capacity(egui_texture.pixels.len() * 4);
-        for srgba in egui_texture.srgba_pixels() {
-            pixels.push(srgba.r());
-            pixels.push(srgba.g());
-            pixels.push(srgba.b());
-            pixels.push(srgba.a());
-        }
-        let egui_texture = egui::Texture {
-            version: egui_texture.version,
-            width: egui_texture.width,
-            height: egui_texture.height,
-            pixels,
+impl SamplerOptions {
+    fn create_sampler(&self, device: &wgpu::Device, label: &str) -> wgpu::Sampler {
+        let clamp_to_border = BackendCapabilities::detect(device).clamp_to_border;
+        let address_mode = |mode| match mode {
+            wgpu::AddressMode::ClampToBorder if !clamp_to_border => wgpu::AddressMode::ClampToEdge,
+            mode => mode,
         };
-        let bind_group = self.egui_texture_to_wgpu(device, queue, &egui_texture, "egui");
-
-        self.texture_version = Some(egui_texture.version);
-        self.texture_bind_group = Some(bind_group);
+        let border_color = if clamp_to_border {
+            Some(wgpu::SamplerBorderColor::TransparentBlack)
+        } else {
+            None
+        };
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: address_mode(self.address_mode_u),
+            address_mode_v: address_mode(self.address_mode_v),
+            border_color,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
     }
+}
 
-    /// Updates the user textures that the app allocated. Should be called before `execute()`.
-    pub fn update_user_textures(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        let pending_user_textures = std::mem::take(&mut self.pending_user_textures);
-        for (id, texture) in pending_user_textures {
-            let bind_group = self.egui_texture_to_wgpu(
-                device,
-                queue,
-                &texture,
-                format!("user_texture{}", id).as_str(),
-            );
-            self.user_textures.push(Some(bind_group));
-        }
+const IDENTITY_MATRIX: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Color space of the output surface. The UI uses sRGB colors, which the shader converts into this color space,
+/// so they keep their look on wide gamut surfaces. The transfer function stays the one of the output format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputColorSpace {
+    /// sRGB primaries, no conversion.
+    Srgb,
+    /// Display P3 primaries, e.g. for wide gamut surfaces on macOS and iOS.
+    DisplayP3,
+    /// BT.2020 primaries.
+    Bt2020,
+    /// A transform derived from a display profile, e.g. the ICC profile of a calibrated display.
+    Custom(ColorTransform),
+}
+
+impl Default for OutputColorSpace {
+    fn default() -> Self {
+        OutputColorSpace::Srgb
     }
+}
 
-    // Assumes egui_texture contains srgb data.
-    // This does not match how egui::Texture is documented as of writing, but this is how it is used for user textures.
+impl OutputColorSpace {
+    /// Returns the transform from linear sRGB into the color space, `None` for sRGB.
+    fn transform(self) -> Option<ColorTransform> {
+        let matrix = match self {
+            OutputColorSpace::Srgb => return None,
+            OutputColorSpace::DisplayP3 => [
+                [0.822_462, 0.177_538, 0.0],
+                [0.033_194, 0.966_806, 0.0],
+                [0.017_083, 0.072_397, 0.910_520],
+            ],
+            OutputColorSpace::Bt2020 => [
+                [0.627_404, 0.329_283, 0.043_313],
+                [0.069_097, 0.919_540, 0.011_362],
+                [0.016_391, 0.088_013, 0.895_595],
+            ],
+            OutputColorSpace::Custom(transform) => return Some(transform),
+        };
+        Some(ColorTransform {
+            matrix,
+            tone_curve: [1.0; 3],
+        })
+    }
+}
+
+/// A color transform applied to the linear UI output, for [`OutputColorSpace::Custom`].
+///
+/// The matrix and tone curve can be derived from the `rXYZ`/`gXYZ`/`bXYZ` and `rTRC`/`gTRC`/`bTRC` tags of
+/// an ICC display profile. Tone curves given as tables need to be fitted to an exponent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorTransform {
+    /// Row-major matrix converting linear sRGB into the linear color space of the display.
+    pub matrix: [[f32; 3]; 3],
+    /// Exponent applied to the red, green and blue channel after the matrix. `1.0` leaves a channel unchanged.
+    pub tone_curve: [f32; 3],
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        Self {
+            matrix: IDENTITY_MATRIX,
+            tone_curve: [1.0; 3],
+        }
+    }
+}
+
+/// Tunables of the egui shader. They are fixed when the render pass is created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShaderConstants {
+    /// Exponent applied to the linear output color. `1.0` leaves the color unchanged.
+    pub gamma: f32,
+    /// Strength of the dithering applied to the output, in units of one 8 bit color step. `0.0` disables dithering.
+    pub dither_strength: f32,
+    /// Encodes the output to sRGB inside the shader. Needed for `Rgba8Unorm` and `Bgra8Unorm` output formats,
+    /// which don't do the conversion in hardware.
+    pub srgb_encode: bool,
+    /// Color space of the output. Conversions out of sRGB need `BlendSpace::Linear`.
+    pub color_space: OutputColorSpace,
+}
+
+impl Default for ShaderConstants {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            dither_strength: 0.0,
+            srgb_encode: false,
+            color_space: OutputColorSpace::Srgb,
+        }
+    }
+}
+
+/// Color space in which the UI is blended with the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendSpace {
+    /// Blend in linear space. This is correct and the default.
+    Linear,
+    /// Blend in gamma space like the old OpenGL backends of egui, which makes text look heavier.
+    /// Needs a `Rgba8Unorm` or `Bgra8Unorm` output format.
+    Gamma,
+}
+
+impl Default for BlendSpace {
+    fn default() -> Self {
+        BlendSpace::Linear
+    }
+}
+
+/// Configuration of a [`RenderPass`].
+#[derive(Clone, Debug, Default)]
+pub struct RenderPassConfig {
+    /// Sampler settings used for the egui texture and all user textures without their own settings.
+    pub sampler: SamplerOptions,
+    /// Tunables of the egui shader.
+    pub constants: ShaderConstants,
+    /// Color space in which the UI is blended.
+    pub blend_space: BlendSpace,
+    /// Adds a second vertex buffer with a `vec4` per vertex at location 3, which the built-in vertex shader
+    /// forwards to the fragment shader at location 2. Upload it with [`RenderPass::update_aux_buffers`].
+    pub aux_vertex_stream: bool,
+    /// SPIR-V of a vertex shader replacing the built-in one. It has to use the same interface.
+    pub vertex_shader: Option<Cow<'static, [u32]>>,
+    /// SPIR-V of a fragment shader replacing the built-in one. It has to use the same interface
+    /// and can read the auxiliary vertex stream at location 2.
+    pub fragment_shader: Option<Cow<'static, [u32]>>,
+    /// Checks every mesh in `update_buffers()`. Malformed meshes, e.g. with an index pointing past the vertices,
+    /// are skipped and returned as `BackendError::MalformedMesh`. Out of range indices can crash the driver
+    /// instead of failing in Rust.
+    pub validate_meshes: bool,
+    /// Channels of the output the UI is written to. Defaults to all channels. Leaving out
+    /// `wgpu::ColorWrite::ALPHA` keeps the alpha of the output, e.g. when compositing the UI over video.
+    pub write_mask: wgpu::ColorWrite,
+    /// Blends the alpha channel with the premultiplied `over` operator, so a target cleared to transparent
+    /// ends up with the coverage of the UI in its alpha channel. Use this when the output is composited by
+    /// another program, e.g. as a streaming overlay or in a transparent window. The clear color is
+    /// premultiplied with its alpha as well.
+    pub export_alpha: bool,
+    /// Replaces the blending of the color channels, which defaults to the premultiplied `over` operator, e.g.
+    /// to accumulate the UI in an intermediate buffer. User textures with another [`TextureBlendMode`] than
+    /// `Normal` keep their own blending.
+    pub color_blend: Option<wgpu::BlendState>,
+    /// Replaces the blending of the alpha channel, which otherwise depends on `export_alpha`.
+    pub alpha_blend: Option<wgpu::BlendState>,
+    /// Grows the scissor rect of every clip rect by this many physical pixels on each side, so the
+    /// anti-aliasing fringe egui feathers around shapes at the edge of a clip rect isn't cut off.
+    pub scissor_expansion: f32,
+    /// Skips uploading and drawing meshes that are completely covered by an opaque rectangle of a later mesh,
+    /// e.g. the content behind an opaque modal panel, to save fill rate on low-end GPUs. Only rectangles
+    /// without rounding filled with a solid color are found. Meshes a draw hook vetoes still count as covering.
+    pub skip_covered_meshes: bool,
+    /// Number of samples per pixel of the render target, e.g. 4 to draw into the multisampled target of a 3D
+    /// scene. `0` and `1` both disable multisampling.
+    pub sample_count: u32,
+    /// Format of a depth-stencil attachment the render pass is compatible with, e.g. when an engine
+    /// needs the same attachments in every pass. The UI neither tests nor writes it. Pass the attachment
+    /// to [`RenderPass::execute_with_depth_stencil`].
+    pub depth_stencil_format: Option<wgpu::TextureFormat>,
+    /// Stores the egui texture as `R8Unorm` with just the coverage of the fonts, instead of expanding it to
+    /// sRGBA on the CPU, which uploads and keeps a quarter of the data. The built-in shaders read the red
+    /// channel as premultiplied white. Custom fragment shaders have to do the same if `u_coverage` is set,
+    /// and the texture view of [`DrawCommand`]s of the egui texture has the `R8Unorm` format.
+    pub single_channel_font_texture: bool,
+}
+
+/// Output formats supported when the hardware does the sRGB encoding.
+const SRGB_OUTPUT_FORMATS: &[wgpu::TextureFormat] = &[
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+];
+
+/// Output formats supported when the shader does the sRGB encoding.
+const UNORM_OUTPUT_FORMATS: &[wgpu::TextureFormat] = &[
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Bgra8Unorm,
+];
+
+impl RenderPassConfig {
+    /// Returns a configuration for the surface of a transparent window, whose output carries the premultiplied
+    /// alpha the window system composites with. Clear it with `wgpu::Color::TRANSPARENT` to see the desktop
+    /// behind the UI, or with a translucent color, which is premultiplied before the clear.
+    pub fn transparent_surface() -> Self {
+        Self {
+            export_alpha: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the number of samples of the render target, at least one.
+    fn sample_count(&self) -> u32 {
+        self.sample_count.max(1)
+    }
+
+    /// Returns `true` if the shader outputs sRGB encoded colors, which needs a non sRGB output format.
+    fn encodes_srgb_in_shader(&self) -> bool {
+        self.constants.srgb_encode || self.blend_space == BlendSpace::Gamma
+    }
+
+    /// Returns the output formats a render pass with this configuration can render into.
+    pub fn supported_output_formats(&self) -> &'static [wgpu::TextureFormat] {
+        if self.encodes_srgb_in_shader() {
+            UNORM_OUTPUT_FORMATS
+        } else {
+            SRGB_OUTPUT_FORMATS
+        }
+    }
+
+    /// Returns `true` if a render pass with this configuration can render into `format`.
+    pub fn is_format_supported(&self, format: wgpu::TextureFormat) -> bool {
+        self.supported_output_formats().contains(&format)
+    }
+}
+
+/// Optional device features the render pass makes use of when they are enabled.
+/// The render pass falls back to a slower or simpler path if a feature is missing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    /// The uniforms are passed as push constants instead of through a uniform buffer.
+    /// Needs `wgpu::Features::PUSH_CONSTANTS`, room for the uniforms in `max_push_constant_size` and the `spirv`
+    /// feature, since the WGSL frontend of wgpu 0.7 has no push constants. Custom shaders always use the uniform
+    /// buffer.
+    pub push_constants: bool,
+    /// Samplers can use `wgpu::AddressMode::ClampToBorder` with a transparent border.
+    /// Needs `wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER`, otherwise `ClampToEdge` is used instead.
+    pub clamp_to_border: bool,
+    /// Textures can use the BC formats, e.g. for `RenderPass::set_texture_compression()` with the `bcn` feature.
+    /// Needs `wgpu::Features::TEXTURE_COMPRESSION_BC`.
+    pub texture_compression_bc: bool,
+    /// The GPU time of the egui render pass can be measured with [`RenderPass::set_gpu_timing`].
+    /// Needs `wgpu::Features::TIMESTAMP_QUERY`.
+    pub timestamp_query: bool,
+}
+
+impl BackendCapabilities {
+    /// Detects the capabilities from the features and limits `device` was created with.
+    pub fn detect(device: &wgpu::Device) -> Self {
+        let features = device.features();
+        Self {
+            push_constants: cfg!(feature = "spirv")
+                && features.contains(wgpu::Features::PUSH_CONSTANTS)
+                && device.limits().max_push_constant_size as usize
+                    >= std::mem::size_of::<UniformBuffer>(),
+            clamp_to_border: features.contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER),
+            texture_compression_bc: features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC),
+            timestamp_query: features.contains(wgpu::Features::TIMESTAMP_QUERY),
+        }
+    }
+}
+
+/// Shader stages reading the uniforms.
+const UNIFORM_STAGES: wgpu::ShaderStage = wgpu::ShaderStage::from_bits_truncate(
+    wgpu::ShaderStage::VERTEX.bits() | wgpu::ShaderStage::FRAGMENT.bits(),
+);
+
+/// Description of the state of a [`RenderPass`] to attach to bug reports. Serializable with the `serde` feature.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DebugSnapshot {
+    /// Version of this crate.
+    pub crate_version: &'static str,
+    /// Format of the render target.
+    pub output_format: String,
+    /// Number of samples per pixel of the render target.
+    pub sample_count: u32,
+    /// The uniforms are passed as push constants.
+    pub push_constants: bool,
+    /// See [`BackendCapabilities::clamp_to_border`].
+    pub clamp_to_border: bool,
+    /// Size of the uniforms in bytes.
+    pub uniform_size: usize,
+    /// Alignment of dynamic uniform buffer offsets in bytes.
+    pub uniform_offset_alignment: usize,
+    /// The render pass uses an auxiliary vertex stream.
+    pub aux_vertex_stream: bool,
+    /// The render pass uses a custom vertex shader.
+    pub custom_vertex_shader: bool,
+    /// The render pass uses a custom fragment shader.
+    pub custom_fragment_shader: bool,
+    /// Size of the shared vertex buffer in bytes.
+    pub vertex_buffer_size: usize,
+    /// Size of the shared index buffer in bytes.
+    pub index_buffer_size: usize,
+    /// Size of the shared auxiliary vertex buffer in bytes.
+    pub aux_buffer_size: usize,
+    /// Version of the uploaded egui texture.
+    pub egui_texture_version: Option<u64>,
+    /// Maximum number of texture bytes uploaded per frame.
+    pub upload_budget: Option<usize>,
+    /// Bytes of the user textures the render pass uploaded and owns.
+    pub texture_memory_usage: usize,
+    /// The user texture table.
+    pub user_textures: Vec<TextureSnapshot>,
+}
+
+/// Description of a user texture in a [`DebugSnapshot`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextureSnapshot {
+    /// Id of the texture.
+    pub id: u64,
+    /// Width and height in pixels. `None` for textures registered from a `wgpu::Texture`.
+    pub size: Option<(usize, usize)>,
+    /// The texture is still waiting for its upload.
+    pub pending: bool,
+    /// The texture was registered from a `wgpu::Texture`.
+    pub registered: bool,
+    /// The texture has its own sampler settings.
+    pub custom_sampler: bool,
+    /// How the texture is composited.
+    pub blend_mode: String,
+}
+
+/// Reasons why a `wgpu::Texture` can't be registered with a render pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureRegistrationError {
+    /// The texture was not created with `wgpu::TextureUsage::SAMPLED`.
+    NotSampled,
+    /// The texture is not a 2D texture.
+    UnsupportedDimension(wgpu::TextureDimension),
+    /// The texture is multisampled.
+    Multisampled,
+    /// The format can't be displayed.
+    UnsupportedFormat(wgpu::TextureFormat),
+}
+
+impl std::fmt::Display for TextureRegistrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureRegistrationError::NotSampled => {
+                write!(f, "texture was not created with TextureUsage::SAMPLED")
+            }
+            TextureRegistrationError::UnsupportedDimension(dimension) => {
+                write!(f, "texture dimension {:?} is not supported", dimension)
+            }
+            TextureRegistrationError::Multisampled => {
+                write!(f, "multisampled textures are not supported")
+            }
+            TextureRegistrationError::UnsupportedFormat(format) => {
+                write!(f, "texture format {:?} can't be displayed", format)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureRegistrationError {}
+
+/// Errors of the render pass an application can recover from, e.g. by recreating the render pass with
+/// another format or by not drawing a frame that refers to a freed texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendError {
+    /// The render pass can't render into the output format with its configuration.
+    UnsupportedOutputFormat(wgpu::TextureFormat),
+    /// A mesh was drawn before the egui texture was set with `update_texture()`.
+    EguiTextureNotSet,
+    /// A mesh uses a user texture id that was never allocated.
+    UserTextureNotFound(u64),
+    /// A mesh uses a user texture that was freed.
+    UserTextureFreed(u64),
+    /// A mesh uses a user texture id whose slot was freed and reused by another texture.
+    StaleUserTexture(u64),
+    /// The aux vertex stream is enabled but `update_aux_buffers()` wasn't called for all meshes.
+    AuxBuffersNotUpdated,
+    /// A depth-stencil attachment was passed to a render pass created without a depth-stencil format.
+    NoDepthStencilFormat,
+    /// A render pass created with a depth-stencil format was executed without a depth-stencil attachment.
+    NoDepthStencilAttachment,
+    /// Mesh `index` of the frame has a number of indices that isn't a multiple of 3, or an index that points
+    /// past its `vertices`. Only checked with `RenderPassConfig::validate_meshes`.
+    MalformedMesh {
+        /// Index of the mesh, counting the custom geometry.
+        index: usize,
+        /// Number of indices of the mesh.
+        indices: usize,
+        /// Number of vertices of the mesh.
+        vertices: usize,
+    },
+    /// An option was enabled that needs features the device was created without.
+    MissingFeatures(wgpu::Features),
+    /// A mesh uses a blend mode and sample kind whose pipeline wasn't created when its texture was set.
+    PipelineNotCreated,
+    /// `TextureId::Egui` was passed to a method that only works on user textures.
+    NotUserTexture,
+    /// The pixel data doesn't match the size of the texture.
+    InvalidPixelData {
+        /// Number of bytes the size needs.
+        expected: usize,
+        /// Number of bytes passed.
+        actual: usize,
+    },
+    /// The ids passed to `execute_picking()` don't have one entry per paint job, or contain the reserved
+    /// `u32::MAX`.
+    InvalidPickingIds,
+    /// The pixels of a user texture registered from a `wgpu::Texture` or with a `RenderContext` were updated
+    /// through a render pass, which doesn't own it.
+    UserTextureNotOwned(u64),
+    /// Texture data can't be uploaded in the requested format.
+    UnsupportedTextureFormat(wgpu::TextureFormat),
+    /// A colormap lookup table was created without colors.
+    EmptyColormap,
+    /// `update_aux_buffers()` was called on a render pass created without `RenderPassConfig::aux_vertex_stream`.
+    MissingAuxVertexStream,
+    /// The aux data of a paint job doesn't have one entry per vertex.
+    InvalidAuxData {
+        /// Index of the paint job.
+        mesh: usize,
+        /// Number of vertices of the mesh.
+        expected: usize,
+        /// Number of aux entries passed.
+        actual: usize,
+    },
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::UnsupportedOutputFormat(format) => {
+                write!(f, "output format {:?} is not supported", format)
+            }
+            BackendError::EguiTextureNotSet => {
+                write!(f, "egui texture was not set before the first draw")
+            }
+            BackendError::UserTextureNotFound(id) => write!(f, "user texture {} not found", id),
+            BackendError::UserTextureFreed(id) => write!(f, "user texture {} freed", id),
+            BackendError::StaleUserTexture(id) => write!(
+                f,
+                "user texture {} is stale, its slot was freed and reused",
+                id
+            ),
+            BackendError::AuxBuffersNotUpdated => {
+                write!(f, "aux buffers were not updated before the draw")
+            }
+            BackendError::NoDepthStencilFormat => {
+                write!(
+                    f,
+                    "the render pass was created without a depth-stencil format"
+                )
+            }
+            BackendError::NoDepthStencilAttachment => {
+                write!(
+                    f,
+                    "the render pass was created with a depth-stencil format but has no depth-stencil attachment"
+                )
+            }
+            BackendError::MalformedMesh {
+                index,
+                indices,
+                vertices,
+            } => write!(
+                f,
+                "mesh {} with {} indices and {} vertices is malformed",
+                index, indices, vertices
+            ),
+            BackendError::MissingFeatures(features) => {
+                write!(f, "the device was created without {:?}", features)
+            }
+            BackendError::NotUserTexture => write!(f, "the egui texture is not a user texture"),
+            BackendError::InvalidPixelData { expected, actual } => write!(
+                f,
+                "pixel data has {} bytes, but the size needs {}",
+                actual, expected
+            ),
+            BackendError::PipelineNotCreated => {
+                write!(f, "pipeline variant was not created before the draw")
+            }
+            BackendError::InvalidPickingIds => write!(
+                f,
+                "the picking ids need one id below u32::MAX per paint job"
+            ),
+            BackendError::UserTextureNotOwned(id) => write!(
+                f,
+                "user texture {} was registered from a wgpu::Texture and can't be updated",
+                id
+            ),
+            BackendError::UnsupportedTextureFormat(format) => {
+                write!(f, "texture data can't be uploaded as {:?}", format)
+            }
+            BackendError::EmptyColormap => write!(f, "a colormap needs at least one color"),
+            BackendError::MissingAuxVertexStream => write!(
+                f,
+                "the render pass was created without an aux vertex stream"
+            ),
+            BackendError::InvalidAuxData {
+                mesh,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "aux data of mesh {} has {} entries, but the mesh has {} vertices",
+                mesh, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// Wraps the buffers and includes additional information.
+#[derive(Debug)]
+struct SizedBuffer {
+    buffer: wgpu::Buffer,
+    size: usize,
+}
+
+impl SizedBuffer {
+    /// Initial size of the buffers shared by the meshes of a frame.
+    const INITIAL_SHARED_SIZE: usize = 64 * 1024;
+
+    fn new_shared(device: &wgpu::Device, usage: wgpu::BufferUsage, label: &str) -> Self {
+        Self::with_size(device, usage, label, Self::INITIAL_SHARED_SIZE)
+    }
+
+    fn with_size(
+        device: &wgpu::Device,
+        usage: wgpu::BufferUsage,
+        label: &str,
+        size: usize,
+    ) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as wgpu::BufferAddress,
+            usage: usage | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self { buffer, size }
+    }
+
+    /// Writes `data` to the start of a shared buffer. A buffer that is too small is replaced by one with
+    /// the next power of two size, so a growing UI doesn't reallocate it every frame.
+    fn write_shared(
+        &mut self,
+        device: &wgpu::Device,
+        writer: &mut BufferWriter,
+        usage: wgpu::BufferUsage,
+        label: &str,
+        data: &[u8],
+    ) {
+        if data.len() > self.size {
+            *self = Self::with_size(device, usage, label, data.len().next_power_of_two());
+        }
+        if !data.is_empty() {
+            writer.write(device, &self.buffer, data);
+        }
+    }
+}
+
+/// Byte ranges of a mesh in the shared vertex and index buffers.
+#[derive(Clone, Debug)]
+struct MeshRange {
+    vertices: Range<wgpu::BufferAddress>,
+    indices: Range<wgpu::BufferAddress>,
+}
+
+impl MeshRange {
+    /// Returns `true` if the mesh has nothing to draw.
+    fn is_empty(&self) -> bool {
+        self.vertices.start == self.vertices.end || self.indices.start == self.indices.end
+    }
+}
+
+/// Chooses how data is uploaded to the GPU. Writing through the queue is usually fastest on integrated GPUs,
+/// while copying large data from staging buffers can be faster on discrete GPUs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadStrategy {
+    /// Vertex, index and uniform data of at least this many bytes is copied from staging buffers by
+    /// `update_buffers()`. `None` writes all of it through the queue.
+    pub buffer_staging_threshold: Option<usize>,
+    /// Textures of at least this many bytes are copied from staging buffers. `None` writes all of them
+    /// through the queue.
+    pub texture_staging_threshold: Option<usize>,
+}
+
+impl UploadStrategy {
+    /// Threshold used by [`UploadStrategy::for_adapter`] for GPUs with their own memory.
+    const DISCRETE_STAGING_THRESHOLD: usize = 256 * 1024;
+
+    /// Picks the thresholds for the type of the adapter: staging buffers for large uploads on discrete and
+    /// virtual GPUs, the queue for everything else.
+    pub fn for_adapter(info: &wgpu::AdapterInfo) -> Self {
+        match info.device_type {
+            wgpu::DeviceType::DiscreteGpu | wgpu::DeviceType::VirtualGpu => Self {
+                buffer_staging_threshold: Some(Self::DISCRETE_STAGING_THRESHOLD),
+                texture_staging_threshold: Some(Self::DISCRETE_STAGING_THRESHOLD),
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Writes data into existing buffers, either directly through the queue or by recording copies
+/// from staging buffers into an encoder.
+enum BufferWriter<'a> {
+    Queue(&'a wgpu::Queue),
+    Encoder(&'a mut wgpu::CommandEncoder),
+    /// Copies the data from the staging buffers of a belt, recorded into an encoder.
+    Belt {
+        belt: &'a mut wgpu::util::StagingBelt,
+        encoder: &'a mut wgpu::CommandEncoder,
+    },
+    /// Writes data smaller than `threshold` through the queue and copies the rest from staging buffers,
+    /// recorded into an encoder that is submitted by `submit()`.
+    Threshold {
+        queue: &'a wgpu::Queue,
+        threshold: usize,
+        encoder: Option<wgpu::CommandEncoder>,
+    },
+}
+
+impl BufferWriter<'_> {
+    /// Submits the copies of a `Threshold` writer. Does nothing for the other writers.
+    fn submit(self) {
+        if let BufferWriter::Threshold {
+            queue,
+            encoder: Some(encoder),
+            ..
+        } = self
+        {
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    fn write(&mut self, device: &wgpu::Device, buffer: &wgpu::Buffer, data: &[u8]) {
+        self.write_at(device, buffer, 0, data);
+    }
+
+    fn write_at(
+        &mut self,
+        device: &wgpu::Device,
+        buffer: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        match self {
+            BufferWriter::Queue(queue) => queue.write_buffer(buffer, offset, data),
+            BufferWriter::Encoder(encoder) => {
+                copy_from_staging_buffer(device, encoder, buffer, offset, data)
+            }
+            BufferWriter::Belt { belt, encoder } => {
+                if let Some(size) = wgpu::BufferSize::new(data.len() as wgpu::BufferAddress) {
+                    belt.write_buffer(encoder, buffer, offset, size, device)
+                        .copy_from_slice(data);
+                }
+            }
+            BufferWriter::Threshold {
+                queue,
+                threshold,
+                encoder,
+            } => {
+                if data.len() < *threshold {
+                    queue.write_buffer(buffer, offset, data);
+                } else {
+                    let encoder = encoder.get_or_insert_with(|| {
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("egui_upload_encoder"),
+                        })
+                    });
+                    copy_from_staging_buffer(device, encoder, buffer, offset, data);
+                }
+            }
+        }
+    }
+}
+
+fn copy_from_staging_buffer(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    buffer: &wgpu::Buffer,
+    offset: wgpu::BufferAddress,
+    data: &[u8],
+) {
+    if data.is_empty() {
+        return;
+    }
+    let staging_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("egui_staging_buffer"),
+        contents: data,
+        usage: wgpu::BufferUsage::COPY_SRC,
+    });
+    encoder.copy_buffer_to_buffer(
+        &staging_buffer,
+        0,
+        buffer,
+        offset,
+        data.len() as wgpu::BufferAddress,
+    );
+}
+
+/// Part of another texture, drawn by remapping the UVs of its meshes.
+#[derive(Clone, Copy, Debug)]
+struct TextureRegion {
+    parent: egui::TextureId,
+    uv_rect: egui::Rect,
+}
+
+impl TextureRegion {
+    fn map_uv(&self, uv: egui::Pos2) -> egui::Pos2 {
+        egui::pos2(
+            self.uv_rect.min.x + uv.x * self.uv_rect.width(),
+            self.uv_rect.min.y + uv.y * self.uv_rect.height(),
+        )
+    }
+}
+
+/// Vertex and index buffer of a single mesh drawn by the backend itself.
+struct MeshBuffers {
+    vertex_buffer: SizedBuffer,
+    index_buffer: SizedBuffer,
+    index_count: u32,
+}
+
+impl MeshBuffers {
+    /// Uploads `mesh`, reusing the buffers in `buffers` if they are large enough.
+    fn update(
+        device: &wgpu::Device,
+        writer: &mut BufferWriter,
+        buffers: &mut Option<MeshBuffers>,
+        mesh: &egui::paint::Mesh,
+        label: &str,
+    ) {
+        let vertex_data = as_byte_slice(&mesh.vertices);
+        let index_data: &[u8] = bytemuck::cast_slice(&mesh.indices);
+        let index_count = mesh.indices.len() as u32;
+
+        if let Some(buffers) = buffers {
+            if buffers.vertex_buffer.size >= vertex_data.len()
+                && buffers.index_buffer.size >= index_data.len()
+            {
+                writer.write(device, &buffers.vertex_buffer.buffer, vertex_data);
+                writer.write(device, &buffers.index_buffer.buffer, index_data);
+                buffers.index_count = index_count;
+                return;
+            }
+        }
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(format!("{}_vertex_buffer", label).as_str()),
+            contents: vertex_data,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(format!("{}_index_buffer", label).as_str()),
+            contents: index_data,
+            usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST,
+        });
+        *buffers = Some(MeshBuffers {
+            vertex_buffer: SizedBuffer {
+                buffer: vertex_buffer,
+                size: vertex_data.len(),
+            },
+            index_buffer: SizedBuffer {
+                buffer: index_buffer,
+                size: index_data.len(),
+            },
+            index_count,
+        });
+    }
+
+    fn draw<'rp>(&'rp self, pass: &mut wgpu::RenderPass<'rp>) {
+        pass.set_index_buffer(
+            self.index_buffer.buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(..));
+        pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
+
+/// A cursor sprite drawn by the backend on top of the UI, for applications that hide the OS cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoftwareCursor {
+    /// Texture of the cursor.
+    pub texture_id: egui::TextureId,
+    /// Size of the cursor in points.
+    pub size: egui::Vec2,
+    /// Offset of the cursor's click point from its top left corner in points.
+    pub hotspot: egui::Vec2,
+}
+
+/// A gradient drawn over the whole window before the UI, for launcher-style backgrounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackgroundGradient {
+    /// Blends from `top` at the top edge of the window to `bottom` at its bottom edge.
+    Vertical {
+        /// Color at the top edge.
+        top: egui::Color32,
+        /// Color at the bottom edge.
+        bottom: egui::Color32,
+    },
+    /// Blends from `center` in the center of the window to `edge` at its corners.
+    Radial {
+        /// Color in the center.
+        center: egui::Color32,
+        /// Color at the corners.
+        edge: egui::Color32,
+    },
+}
+
+impl BackgroundGradient {
+    /// Number of segments of the ellipse approximating radial gradients.
+    const RADIAL_SEGMENTS: u32 = 64;
+
+    /// Returns a mesh of the gradient covering a window of `size` points.
+    fn mesh(&self, size: egui::Vec2) -> egui::paint::Mesh {
+        let mut mesh = egui::paint::Mesh::with_texture(egui::TextureId::Egui);
+        match *self {
+            BackgroundGradient::Vertical { top, bottom } => {
+                mesh.colored_vertex(egui::pos2(0.0, 0.0), top);
+                mesh.colored_vertex(egui::pos2(size.x, 0.0), top);
+                mesh.colored_vertex(egui::pos2(0.0, size.y), bottom);
+                mesh.colored_vertex(egui::pos2(size.x, size.y), bottom);
+                mesh.add_triangle(0, 1, 2);
+                mesh.add_triangle(2, 1, 3);
+            }
+            BackgroundGradient::Radial { center, edge } => {
+                // The ellipse touches the corners, so its center is half the diagonal away from them.
+                let middle = egui::pos2(size.x / 2.0, size.y / 2.0);
+                let radius = size / 2.0 * std::f32::consts::SQRT_2;
+                mesh.colored_vertex(middle, center);
+                for i in 0..Self::RADIAL_SEGMENTS {
+                    let angle = i as f32 / Self::RADIAL_SEGMENTS as f32 * std::f32::consts::TAU;
+                    let offset = egui::vec2(angle.cos() * radius.x, angle.sin() * radius.y);
+                    mesh.colored_vertex(middle + offset, edge);
+                    mesh.add_triangle(0, 1 + i, 1 + (i + 1) % Self::RADIAL_SEGMENTS);
+                }
+            }
+        }
+        mesh
+    }
+}
+
+/// How a [`BackgroundImage`] is scaled to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackgroundFit {
+    /// Covers the whole window, cropping the image to the aspect ratio of the window.
+    Cover,
+    /// Shows the whole image as large as possible, leaving bars of the background on two sides.
+    Contain,
+    /// Stretches the image to the window, ignoring its aspect ratio.
+    Stretch,
+}
+
+/// A texture drawn over the whole window before the UI, e.g. for a wallpaper.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackgroundImage {
+    /// The texture to draw.
+    pub texture_id: egui::TextureId,
+    /// Width divided by height of the image.
+    pub aspect_ratio: f32,
+    /// How the image is scaled to the window.
+    pub fit: BackgroundFit,
+}
+
+impl BackgroundImage {
+    /// Returns a mesh of the image for a window of `size` points and `window_aspect_ratio`.
+    fn mesh(&self, size: egui::Vec2, window_aspect_ratio: f32) -> egui::paint::Mesh {
+        // Size of the image relative to the window, larger than 1 in one dimension for `Cover`.
+        let ratio = self.aspect_ratio / window_aspect_ratio;
+        let relative_size = match self.fit {
+            BackgroundFit::Cover if ratio > 1.0 => egui::vec2(ratio, 1.0),
+            BackgroundFit::Cover => egui::vec2(1.0, 1.0 / ratio),
+            BackgroundFit::Contain if ratio > 1.0 => egui::vec2(1.0, 1.0 / ratio),
+            BackgroundFit::Contain => egui::vec2(ratio, 1.0),
+            BackgroundFit::Stretch => egui::vec2(1.0, 1.0),
+        };
+
+        // Crops the UVs to the window instead of drawing outside of it.
+        let visible = egui::vec2(relative_size.x.min(1.0), relative_size.y.min(1.0));
+        let uv_min = egui::pos2(
+            (1.0 - visible.x / relative_size.x) / 2.0,
+            (1.0 - visible.y / relative_size.y) / 2.0,
+        );
+        let uv = egui::Rect::from_min_max(uv_min, egui::pos2(1.0 - uv_min.x, 1.0 - uv_min.y));
+        let rect = egui::Rect::from_center_size(
+            egui::pos2(size.x / 2.0, size.y / 2.0),
+            egui::vec2(visible.x * size.x, visible.y * size.y),
+        );
+
+        let mut mesh = egui::paint::Mesh::with_texture(self.texture_id);
+        mesh.add_rect_with_uv(rect, uv, egui::Color32::WHITE);
+        mesh
+    }
+}
+
+/// Pipeline used to record render bundles. Render bundles can't set scissor rects,
+/// so the clip rect is tested in the fragment shader instead.
+struct BundlePipeline {
+    pipeline: wgpu::RenderPipeline,
+    clip_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// Alignment of dynamic uniform buffer offsets.
+const UNIFORM_OFFSET_ALIGNMENT: usize = 256;
+
+/// A user texture that was uploaded or registered.
+struct UserTexture {
+    /// Full id including the generation, to detect stale ids of a reused slot.
+    id: u64,
+    /// `None` for textures registered from a `wgpu::Texture`, which the backend doesn't own.
+    texture: Option<wgpu::Texture>,
+    view: wgpu::TextureView,
+    /// CPU copy of an uploaded texture and its format, used to upload it again in `recreate()`.
+    /// `None` for textures registered from a `wgpu::Texture`.
+    source: Option<(egui::Texture, wgpu::TextureFormat)>,
+    sampler: Option<SamplerOptions>,
+    params: TextureParams,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Number of the last frame whose paint jobs used the texture.
+    last_used: u64,
+}
+
+/// A user texture whose GPU resources were freed to stay within the texture memory limit.
+struct EvictedTexture {
+    id: u64,
+    /// `None` if the texture provider provides the pixels again.
+    source: Option<(egui::Texture, wgpu::TextureFormat)>,
+    sampler: Option<SamplerOptions>,
+    params: TextureParams,
+}
+
+/// A user texture waiting for its upload.
+struct PendingTexture {
+    id: u64,
+    texture: egui::Texture,
+    format: wgpu::TextureFormat,
+    sampler: Option<SamplerOptions>,
+    params: TextureParams,
+}
+
+/// RenderPass to render a egui based GUI.
+pub struct RenderPass {
+    config: RenderPassConfig,
+    /// The shader modules, layouts and samplers, possibly shared with the render passes of other windows.
+    context: RenderContext,
+    capabilities: BackendCapabilities,
+    /// The egui pipeline reads the uniforms from push constants instead of the uniform buffer.
+    push_constants: bool,
+    render_pipeline: Arc<wgpu::RenderPipeline>,
+    /// Pipelines of the other blend modes and sample kinds, created when first used.
+    pipeline_variants: Vec<(PipelineKey, Arc<wgpu::RenderPipeline>)>,
+    sample_kind_layouts: Vec<Arc<SampleKindLayout>>,
+    colormap_luts: Vec<ColormapLutData>,
+    picking_pipeline: Option<wgpu::RenderPipeline>,
+    clear_pipeline: Option<wgpu::RenderPipeline>,
+    bundle_pipeline: Option<BundlePipeline>,
+    stencil_clip: Option<StencilClip>,
+    output_format: wgpu::TextureFormat,
+    /// Number of samples per pixel of the render target.
+    sample_count: u32,
+    /// Vertices of all meshes of the frame, packed one after another.
+    vertex_buffer: SizedBuffer,
+    /// Indices of all meshes of the frame, packed one after another.
+    index_buffer: SizedBuffer,
+    /// Auxiliary vertex data of all meshes of the frame, packed one after another.
+    aux_buffer: SizedBuffer,
+    /// Ranges of the meshes of the frame in the shared buffers, in draw order. `None` for meshes that aren't
+    /// drawn, since an empty buffer slice would reach to the end of the buffer.
+    mesh_ranges: Vec<Option<MeshRange>>,
+    /// Ranges of the meshes of the frame in `aux_buffer`, in draw order.
+    aux_ranges: Vec<Range<wgpu::BufferAddress>>,
+    aux_vertex_stream: bool,
+    /// Zeroed aux data for the quads the backend draws itself.
+    aux_zero_buffer: Option<wgpu::Buffer>,
+    /// Holds the uniforms of every screen descriptor of the frame at aligned offsets.
+    uniform_buffer: SizedBuffer,
+    uniform_slots: Vec<UniformBuffer>,
+    uniform_bind_group: wgpu::BindGroup,
+    draw_hook: Option<Box<DrawHook>>,
+    software_cursor: Option<SoftwareCursor>,
+    background_gradient: Option<BackgroundGradient>,
+    background_image: Option<BackgroundImage>,
+    cursor_position: Option<egui::Pos2>,
+    cursor_buffers: Option<MeshBuffers>,
+    letterbox_buffers: Option<MeshBuffers>,
+    gradient_buffers: Option<MeshBuffers>,
+    background_buffers: Option<MeshBuffers>,
+    backdrop: Option<Backdrop>,
+    constants: ShaderConstants,
+    blend_space: BlendSpace,
+    sampler_options: SamplerOptions,
+    texture_view: Option<wgpu::TextureView>,
+    texture_bind_group: Option<wgpu::BindGroup>,
+    texture_version: Option<u64>,
+    /// The egui texture and a copy of its pixels, to only upload the changed part of a new version.
+    egui_texture: Option<(wgpu::Texture, egui::Texture)>,
+    /// Allocates the user texture ids and holds the commands of texture command senders and handles.
+    texture_queue: SharedTextureQueue,
+    pending_user_textures: VecDeque<PendingTexture>,
+    upload_budget: Option<usize>,
+    upload_strategy: UploadStrategy,
+    /// Created by the first `update_buffers_with_staging_belt()`. Only used through `&mut self`, the mutex
+    /// keeps the render pass `Sync`.
+    staging_uploads: Mutex<Option<StagingUploads>>,
+    gpu_timer: Option<GpuTimer>,
+    stats: StatsCounters,
+    #[cfg(feature = "bcn")]
+    texture_compression: bool,
+    user_textures: Vec<Option<UserTexture>>,
+    /// Snapshot of the textures of the context taken by `update_user_textures()`, so their bind groups can be
+    /// borrowed for the frame while other windows free them.
+    shared_textures: Vec<Option<Arc<SharedTexture>>>,
+    texture_regions: HashMap<u64, TextureRegion>,
+    /// Geometry added by `add_custom_geometry()` for the next `update_buffers()`.
+    custom_geometry: Vec<(usize, egui::paint::ClippedMesh)>,
+    /// The custom geometry in the buffers of the current frame, sorted by position.
+    frame_geometry: Vec<(usize, egui::paint::ClippedMesh)>,
+    /// Paint callbacks added by `add_paint_callback()` for the next `update_buffers()`.
+    paint_callbacks: Vec<PositionedCallback>,
+    /// Paint callbacks of the current frame, sorted by position.
+    frame_callbacks: Vec<PositionedCallback>,
+    /// Meshes of the current frame hidden behind later meshes, if `skip_covered_meshes` is set.
+    covered_meshes: Vec<bool>,
+    /// Counts the calls of `update_buffers()`.
+    frame: u64,
+    texture_memory_limit: Option<usize>,
+    texture_provider: Option<Box<TextureProvider>>,
+    evicted_textures: Vec<EvictedTexture>,
+}
+
+impl RenderPass {
+    /// Creates a new render pass to render a egui UI. `output_format` needs to be either `wgpu::TextureFormat::Rgba8UnormSrgb` or `wgpu::TextureFormat::Bgra8UnormSrgb`. Returns `BackendError::UnsupportedOutputFormat` if it's not a Srgb format.
+    ///
+    /// `msaa_samples` is the number of samples per pixel of the render target, `1` without multisampling.
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        msaa_samples: u32,
+    ) -> Result<Self, BackendError> {
+        let config = RenderPassConfig {
+            sample_count: msaa_samples,
+            ..RenderPassConfig::default()
+        };
+        Self::with_config(device, output_format, &config)
+    }
+
+    /// Returns `true` if a render pass created with [`RenderPass::new`] can render into `format`.
+    /// Use [`RenderPassConfig::is_format_supported`] for other configurations.
+    pub fn is_format_supported(format: wgpu::TextureFormat) -> bool {
+        RenderPassConfig::default().is_format_supported(format)
+    }
+
+    /// Returns the output formats a render pass created with [`RenderPass::new`] can render into.
+    pub fn supported_output_formats() -> &'static [wgpu::TextureFormat] {
+        SRGB_OUTPUT_FORMATS
+    }
+
+    /// Returns a description of the render pass state to attach to bug reports.
+    pub fn debug_snapshot(&self) -> DebugSnapshot {
+        let mut user_textures: Vec<TextureSnapshot> = self
+            .user_textures
+            .iter()
+            .filter_map(|user_texture| {
+                let user_texture = user_texture.as_ref()?;
+                Some(TextureSnapshot {
+                    id: user_texture.id,
+                    size: user_texture
+                        .source
+                        .as_ref()
+                        .map(|(texture, _)| (texture.width, texture.height)),
+                    pending: false,
+                    registered: user_texture.source.is_none(),
+                    custom_sampler: user_texture.sampler.is_some(),
+                    blend_mode: format!("{:?}", user_texture.params.blend_mode),
+                })
+            })
+            .collect();
+        user_textures.extend(
+            self.pending_user_textures
+                .iter()
+                .map(|pending| TextureSnapshot {
+                    id: pending.id,
+                    size: Some((pending.texture.width, pending.texture.height)),
+                    pending: true,
+                    registered: false,
+                    custom_sampler: pending.sampler.is_some(),
+                    blend_mode: format!("{:?}", pending.params.blend_mode),
+                }),
+        );
+        user_textures.sort_by_key(|texture| texture.id);
+
+        DebugSnapshot {
+            crate_version: env!("CARGO_PKG_VERSION"),
+            output_format: format!("{:?}", self.output_format),
+            sample_count: self.sample_count,
+            push_constants: self.push_constants,
+            clamp_to_border: self.capabilities.clamp_to_border,
+            uniform_size: std::mem::size_of::<UniformBuffer>(),
+            uniform_offset_alignment: UNIFORM_OFFSET_ALIGNMENT,
+            aux_vertex_stream: self.aux_vertex_stream,
+            custom_vertex_shader: self.config.vertex_shader.is_some(),
+            custom_fragment_shader: self.config.fragment_shader.is_some(),
+            vertex_buffer_size: self.vertex_buffer.size,
+            index_buffer_size: self.index_buffer.size,
+            aux_buffer_size: self.aux_buffer.size,
+            egui_texture_version: self.texture_version,
+            upload_budget: self.upload_budget,
+            texture_memory_usage: self.texture_memory_usage(),
+            user_textures,
+        }
+    }
+
+    /// Returns the optional features of the device the render pass was created with.
+    pub fn capabilities(&self) -> BackendCapabilities {
+        self.capabilities
+    }
+
+    /// Creates a new render pass with the given configuration. See [`RenderPass::new`].
+    ///
+    /// If `config.constants.srgb_encode` is set or `config.blend_space` is `BlendSpace::Gamma`, `output_format`
+    /// needs to be either `wgpu::TextureFormat::Rgba8Unorm` or `wgpu::TextureFormat::Bgra8Unorm` instead.
+    pub fn with_config(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        config: &RenderPassConfig,
+    ) -> Result<Self, BackendError> {
+        Self::with_context(device, &RenderContext::new(device, config), output_format)
+    }
+
+    /// Creates a new render pass with the shader modules, layouts and samplers of `context`, which are shared
+    /// with the other render passes created from it, e.g. those of the other windows of an application.
+    /// Render passes with the same output format share the egui pipelines as well.
+    pub fn with_context(
+        device: &wgpu::Device,
+        context: &RenderContext,
+        output_format: wgpu::TextureFormat,
+    ) -> Result<Self, BackendError> {
+        let config = context.config();
+        if !config.is_format_supported(output_format) {
+            return Err(BackendError::UnsupportedOutputFormat(output_format));
+        }
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[UniformBuffer::new(
+                &ScreenDescriptor {
+                    physical_width: 0,
+                    physical_height: 0,
+                    scale_factor: 1.0,
+                    viewport: None,
+                    rotation: SurfaceRotation::Identity,
+                },
+                &config.constants,
+                config.blend_space,
+            )]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let uniform_buffer = SizedBuffer {
+            buffer: uniform_buffer,
+            size: std::mem::size_of::<UniformBuffer>(),
+        };
+
+        let uniform_bind_group = create_uniform_bind_group(
+            device,
+            &context.shared.uniform_bind_group_layout,
+            &uniform_buffer.buffer,
+            "egui_uniform_bind_group",
+        );
+
+        let render_pipeline = context.pipeline(device, output_format, PipelineKey::default());
+
+        Ok(Self {
+            config: config.clone(),
+            context: context.clone(),
+            render_pipeline,
+            pipeline_variants: Vec::new(),
+            sample_kind_layouts: Vec::new(),
+            colormap_luts: Vec::new(),
+            capabilities: context.shared.capabilities,
+            push_constants: context.shared.push_constants,
+            picking_pipeline: None,
+            clear_pipeline: None,
+            bundle_pipeline: None,
+            stencil_clip: None,
+            output_format,
+            sample_count: config.sample_count(),
+            vertex_buffer: SizedBuffer::new_shared(
+                device,
+                wgpu::BufferUsage::VERTEX,
+                "egui_vertex_buffer",
+            ),
+            index_buffer: SizedBuffer::new_shared(
+                device,
+                wgpu::BufferUsage::INDEX,
+                "egui_index_buffer",
+            ),
+            aux_buffer: SizedBuffer::new_shared(
+                device,
+                wgpu::BufferUsage::VERTEX,
+                "egui_aux_buffer",
+            ),
+            mesh_ranges: Vec::new(),
+            aux_ranges: Vec::new(),
+            aux_vertex_stream: config.aux_vertex_stream,
+            aux_zero_buffer: if config.aux_vertex_stream {
+                Some(
+                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("egui_aux_zero_buffer"),
+                        contents: &[0u8; 4 * 4 * 4],
+                        usage: wgpu::BufferUsage::VERTEX,
+                    }),
+                )
+            } else {
+                None
+            },
+            uniform_buffer,
+            uniform_slots: Vec::new(),
+            uniform_bind_group,
+            draw_hook: None,
+            software_cursor: None,
+            background_gradient: None,
+            background_image: None,
+            cursor_position: None,
+            cursor_buffers: None,
+            letterbox_buffers: None,
+            gradient_buffers: None,
+            background_buffers: None,
+            backdrop: None,
+            constants: config.constants,
+            blend_space: config.blend_space,
+            sampler_options: config.sampler,
+            texture_version: None,
+            egui_texture: None,
+            texture_view: None,
+            texture_bind_group: None,
+            texture_queue: SharedTextureQueue::default(),
+            pending_user_textures: VecDeque::new(),
+            upload_budget: None,
+            upload_strategy: UploadStrategy::default(),
+            staging_uploads: Mutex::new(None),
+            gpu_timer: None,
+            stats: StatsCounters::default(),
+            #[cfg(feature = "bcn")]
+            texture_compression: false,
+            user_textures: Vec::new(),
+            shared_textures: context.shared_textures(),
+            texture_regions: HashMap::new(),
+            custom_geometry: Vec::new(),
+            frame_geometry: Vec::new(),
+            paint_callbacks: Vec::new(),
+            frame_callbacks: Vec::new(),
+            covered_meshes: Vec::new(),
+            frame: 0,
+            texture_memory_limit: None,
+            texture_provider: None,
+            evicted_textures: Vec::new(),
+        })
+    }
+
+    /// Executes the egui render pass. When `clear_on_draw` is set, the output target will get cleared before writing to it.
+    ///
+    /// `paint_jobs` can be any iterator yielding the same meshes in the same order as passed to `update_buffers()`.
+    /// With multisampling, `resolve_target` receives the resolved colors of the multisampled `color_attachment`.
+    /// Render passes created with a depth-stencil format return `BackendError::NoDepthStencilAttachment`, they need
+    /// [`RenderPass::execute_with_depth_stencil`].
+    pub fn execute<'a>(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<(), BackendError> {
+        self.execute_with_pass_descriptor(
+            encoder,
+            color_attachment,
+            paint_jobs,
+            screen_descriptor,
+            clear_color,
+            |descriptor| descriptor.color_attachments[0].resolve_target = resolve_target,
+        )
+    }
+
+    /// Like [`RenderPass::execute`], but with a depth-stencil attachment of
+    /// `RenderPassConfig::depth_stencil_format` and its load and store operations. Render passes created
+    /// with a depth-stencil format need to be executed this way.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_with_depth_stencil<'a>(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        depth_stencil_attachment: wgpu::RenderPassDepthStencilAttachmentDescriptor,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<(), BackendError> {
+        self.execute_with_pass_descriptor(
+            encoder,
+            color_attachment,
+            paint_jobs,
+            screen_descriptor,
+            clear_color,
+            |descriptor| {
+                descriptor.color_attachments[0].resolve_target = resolve_target;
+                descriptor.depth_stencil_attachment = Some(depth_stencil_attachment);
+            },
+        )
+    }
+
+    /// Like [`RenderPass::execute`], but calls `adjust` with the render pass descriptor before the pass begins,
+    /// e.g. to set the store operations or the label an engine expects. See [`PassDescriptor`] for the
+    /// changes the egui pipelines are compatible with. Returns an error if the adjusted descriptor has a
+    /// depth-stencil attachment exactly when the render pass was created without a depth-stencil format.
+    pub fn execute_with_pass_descriptor<'a, 'v>(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &'v wgpu::TextureView,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: Option<wgpu::Color>,
+        adjust: impl FnOnce(&mut PassDescriptor<'v>),
+    ) -> Result<(), BackendError> {
+        let load_operation = self.load_operation(clear_color);
+
+        let mut descriptor = PassDescriptor {
+            label: Some("egui main render pass"),
+            color_attachments: vec![wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: color_attachment,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: load_operation,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        };
+        adjust(&mut descriptor);
+        self.check_depth_stencil_attachment(descriptor.depth_stencil_attachment.is_some())?;
+
+        let timed = match &mut self.gpu_timer {
+            Some(timer) => timer.begin(encoder),
+            None => false,
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &descriptor.color_attachments,
+            depth_stencil_attachment: descriptor.depth_stencil_attachment,
+            label: descriptor.label,
+        });
+        pass.push_debug_group("egui_pass");
+        let result = self.draw_meshes(&mut pass, paint_jobs, screen_descriptor);
+        pass.pop_debug_group();
+        drop(pass);
+        if timed {
+            self.gpu_timer.as_mut().unwrap().end(encoder);
+        }
+        result
+    }
+
+    /// Records the draw calls of the paint jobs into a render pass the caller began, e.g. the pass of an engine
+    /// that draws its scene and the UI together. Only sets pipelines, bind groups, buffers, scissor rects and
+    /// viewports. The pass needs a single color attachment of the output format and a depth-stencil attachment
+    /// only if `RenderPassConfig::depth_stencil_format` is set. `update_buffers()` needs to be called with the
+    /// same paint jobs before.
+    pub fn execute_with_renderpass<'rp, 'a>(
+        &'rp self,
+        rpass: &mut wgpu::RenderPass<'rp>,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        rpass.push_debug_group("egui_pass");
+        let result = self.draw_meshes(rpass, paint_jobs, screen_descriptor);
+        rpass.pop_debug_group();
+        result
+    }
+
+    /// Like [`RenderPass::execute`], but only clears `clear` instead of the whole target, e.g. for a UI strip
+    /// on top of a video frame that shares the target. The rest of the target is loaded. The pass has no
+    /// depth-stencil attachment, so render passes created with a depth-stencil format return
+    /// `BackendError::NoDepthStencilAttachment`.
+    pub fn execute_with_clear_rect(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+        clear: ClearRect,
+    ) -> Result<(), BackendError> {
+        self.check_depth_stencil_attachment(false)?;
+        if self.clear_pipeline.is_none() {
+            self.clear_pipeline = Some(self.create_clear_pipeline(device));
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: color_attachment,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            label: Some("egui main render pass"),
+        });
+        pass.push_debug_group("egui_pass");
+
+        let (window_width, window_height) = screen_descriptor.window_size();
+        let x = clear.x.min(window_width);
+        let y = clear.y.min(window_height);
+        let width = clear.width.min(window_width - x);
+        let height = clear.height.min(window_height - y);
+        if width > 0 && height > 0 {
+            let (x, y, width, height) = screen_descriptor.rotate_rect((x, y, width, height));
+            pass.set_pipeline(self.clear_pipeline.as_ref().unwrap());
+            pass.set_blend_color(clear.color);
+            pass.set_scissor_rect(x, y, width, height);
+            pass.draw(0..3, 0..1);
+        }
+
+        let result = self.draw_meshes(&mut pass, paint_jobs, screen_descriptor);
+        pass.pop_debug_group();
+        result
+    }
+
+    /// Creates the pipeline that fills the scissor rect with the blend color.
+    fn create_clear_pipeline(&self, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let vs_module = device.create_shader_module(&include_shader!("blur.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("clear.frag"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("egui_clear_pipeline_layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let replace_with_blend_color = wgpu::BlendState {
+            src_factor: wgpu::BlendFactor::BlendColor,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        create_render_pipeline(
+            device,
+            "egui_clear_pipeline",
+            &pipeline_layout,
+            &vs_module,
+            &fs_module,
+            &[],
+            wgpu::ColorTargetState {
+                format: self.output_format,
+                color_blend: replace_with_blend_color.clone(),
+                alpha_blend: replace_with_blend_color,
+                write_mask: self.config.write_mask,
+            },
+            egui_depth_stencil_state(&self.config),
+            self.sample_count,
+        )
+    }
+
+    /// Uploads the textures and buffers of the frame and records the egui render pass into a command buffer
+    /// of its own, which the caller submits together with its other work. Textures are written through
+    /// `queue`, while the vertex, index and uniform data is copied inside the returned command buffer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_to_command_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        egui_texture: &egui::Texture,
+        color_attachment: &wgpu::TextureView,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<wgpu::CommandBuffer, BackendError> {
+        self.update_texture(device, queue, egui_texture);
+        self.update_user_textures(device, queue)?;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("egui_command_encoder"),
+        });
+        self.update_buffers_with_encoder(device, &mut encoder, paint_jobs, screen_descriptor)?;
+        self.execute(
+            &mut encoder,
+            color_attachment,
+            None,
+            paint_jobs,
+            screen_descriptor,
+            clear_color,
+        )?;
+        Ok(encoder.finish())
+    }
+
+    /// Checks that a pass has a depth-stencil attachment exactly when the egui pipelines expect one.
+    fn check_depth_stencil_attachment(&self, has_attachment: bool) -> Result<(), BackendError> {
+        match (self.config.depth_stencil_format.is_some(), has_attachment) {
+            (true, false) => Err(BackendError::NoDepthStencilAttachment),
+            (false, true) => Err(BackendError::NoDepthStencilFormat),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the load operation of the color attachment, which clears it if `clear_color` is set.
+    fn load_operation(&self, clear_color: Option<wgpu::Color>) -> wgpu::LoadOp<wgpu::Color> {
+        match clear_color {
+            // The blending with `export_alpha` expects premultiplied colors in the output.
+            Some(color) if self.config.export_alpha => wgpu::LoadOp::Clear(wgpu::Color {
+                r: color.r * color.a,
+                g: color.g * color.a,
+                b: color.b * color.a,
+                a: color.a,
+            }),
+            Some(color) => wgpu::LoadOp::Clear(color),
+            None => wgpu::LoadOp::Load,
+        }
+    }
+
+    /// Executes the egui render pass once for every target, each with its own screen descriptor.
+    /// The vertex, index and texture data is shared between the targets, so `update_buffers()`
+    /// only needs to be called once with any of the screen descriptors. When `clear_color` is set,
+    /// the targets will get cleared before writing to them. The passes have no depth-stencil attachment,
+    /// so render passes created with a depth-stencil format return `BackendError::NoDepthStencilAttachment`.
+    pub fn execute_multiple(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: &[RenderTarget],
+        paint_jobs: &[egui::paint::ClippedMesh],
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<(), BackendError> {
+        self.check_depth_stencil_attachment(false)?;
+        for target in targets {
+            self.add_screen_descriptor(device, queue, target.screen_descriptor);
+        }
+
+        let load_operation = self.load_operation(clear_color);
+
+        for target in targets {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: target.color_attachment,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: load_operation,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+                label: Some("egui target render pass"),
+            });
+            pass.push_debug_group("egui_pass");
+            let result = self.draw_meshes(&mut pass, paint_jobs, target.screen_descriptor);
+            pass.pop_debug_group();
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Makes the uniforms of another screen descriptor available in this frame, so `execute()` can be called
+    /// several times with different screen descriptors, e.g. for several windows sharing the paint jobs.
+    /// `update_buffers()` only writes the uniforms of its own screen descriptor and discards the others.
+    /// `execute()` falls back to the screen descriptor of `update_buffers()` for unknown screen descriptors.
+    pub fn add_screen_descriptor(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        screen_descriptor: &ScreenDescriptor,
+    ) {
+        let uniform = UniformBuffer::new(screen_descriptor, &self.constants, self.blend_space);
+        if !self.uniform_slots.contains(&uniform) {
+            self.write_uniform_slot(device, &mut BufferWriter::Queue(queue), uniform);
+        }
+    }
+
+    /// Appends `uniform` to the uniform buffer, growing it if needed.
+    fn write_uniform_slot(
+        &mut self,
+        device: &wgpu::Device,
+        writer: &mut BufferWriter,
+        uniform: UniformBuffer,
+    ) {
+        let index = self.uniform_slots.len();
+        self.uniform_slots.push(uniform);
+
+        let offset = index * UNIFORM_OFFSET_ALIGNMENT;
+        let uniform_size = std::mem::size_of::<UniformBuffer>();
+        if offset + uniform_size <= self.uniform_buffer.size {
+            writer.write_at(
+                device,
+                &self.uniform_buffer.buffer,
+                offset as wgpu::BufferAddress,
+                bytemuck::cast_slice(&[uniform]),
+            );
+            return;
+        }
+
+        let slot_count = self.uniform_slots.len().next_power_of_two();
+        let size = (slot_count - 1) * UNIFORM_OFFSET_ALIGNMENT + uniform_size;
+        let mut contents = vec![0u8; size];
+        for (i, uniform) in self.uniform_slots.iter().enumerate() {
+            let offset = i * UNIFORM_OFFSET_ALIGNMENT;
+            contents[offset..offset + uniform_size]
+                .copy_from_slice(bytemuck::cast_slice(&[*uniform]));
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_uniform_buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        self.uniform_bind_group = create_uniform_bind_group(
+            device,
+            &self.context.shared.uniform_bind_group_layout,
+            &buffer,
+            "egui_uniform_bind_group",
+        );
+        self.uniform_buffer = SizedBuffer { buffer, size };
+    }
+
+    /// Returns the offset of the uniforms of `screen_descriptor` in the uniform buffer.
+    fn uniform_offset(&self, screen_descriptor: &ScreenDescriptor) -> wgpu::DynamicOffset {
+        let uniform = UniformBuffer::new(screen_descriptor, &self.constants, self.blend_space);
+        let index = self
+            .uniform_slots
+            .iter()
+            .position(|slot| *slot == uniform)
+            .unwrap_or(0);
+        (index * UNIFORM_OFFSET_ALIGNMENT) as wgpu::DynamicOffset
+    }
+
+    /// Sets a callback that is invoked before every mesh is drawn by `execute()`. It can adjust
+    /// or veto the scissor rect and viewport of the draw call.
+    pub fn set_draw_hook(&mut self, draw_hook: Option<Box<DrawHook>>) {
+        self.draw_hook = draw_hook;
+    }
+
+    /// Records the draw calls of the paint jobs into `pass`.
+    fn draw_meshes<'rp, 'a>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        let mut pipeline_key = PipelineKey::default();
+        self.set_egui_state(pass, screen_descriptor);
+
+        let (region_x, region_y, region_width, region_height) = screen_descriptor.target_region();
+        self.draw_background_gradient(pass, screen_descriptor);
+        self.draw_background_image(pass, screen_descriptor)?;
+        if screen_descriptor.viewport.is_some() {
+            self.draw_letterbox(pass, screen_descriptor);
+            pass.set_viewport(
+                region_x as f32,
+                region_y as f32,
+                region_width as f32,
+                region_height as f32,
+                0.0,
+                1.0,
+            );
+        }
+
+        let mut callbacks = self.frame_callbacks.iter().peekable();
+        for (i, ((paint_job, egui::ClippedMesh(clip_rect, mesh)), range)) in
+            merge_custom_geometry(paint_jobs, &self.frame_geometry)
+                .into_iter()
+                .zip(self.mesh_ranges.iter())
+                .enumerate()
+        {
+            if let Some(paint_job) = paint_job {
+                while let Some(callback) = callbacks.peek() {
+                    if callback.position > paint_job {
+                        break;
+                    }
+                    self.run_paint_callback(pass, callback, screen_descriptor);
+                    pipeline_key = PipelineKey::default();
+                    callbacks.next();
+                }
+            }
+            if self.is_mesh_covered(i) {
+                continue;
+            }
+            // Malformed and empty meshes weren't uploaded.
+            let range = match range {
+                Some(range) => range,
+                None => {
+                    self.stats.culled_mesh();
+                    continue;
+                }
+            };
+            let (x, y, width, height) =
+                match screen_descriptor.scissor_rect(clip_rect, self.config.scissor_expansion) {
+                    Some(rect) => rect,
+                    // skip rendering with zero-sized clip areas
+                    None => {
+                        self.stats.culled_mesh();
+                        continue;
+                    }
+                };
+
+            let bind_group = match self.get_texture_bind_group(mesh.texture_id)? {
+                Some(bind_group) => bind_group,
+                // The user texture is still waiting for its upload.
+                None => {
+                    self.stats.culled_mesh();
+                    continue;
+                }
+            };
+
+            let texture_pipeline_key = self.texture_pipeline_key(mesh.texture_id);
+            if texture_pipeline_key != pipeline_key {
+                pipeline_key = texture_pipeline_key;
+                pass.set_pipeline(self.pipeline_variant(pipeline_key)?);
+            }
+
+            if let Some(draw_hook) = &self.draw_hook {
+                let info = DrawInfo {
+                    mesh_index: i,
+                    clip_rect,
+                    texture_id: mesh.texture_id,
+                };
+                let mut state = DrawState {
+                    scissor_rect: [x, y, width, height],
+                    viewport: [
+                        region_x as f32,
+                        region_y as f32,
+                        region_width as f32,
+                        region_height as f32,
+                    ],
+                };
+                if !draw_hook(&info, &mut state) {
+                    self.stats.culled_mesh();
+                    continue;
+                }
+                let [x, y, width, height] = state.scissor_rect;
+                let x = x.min(screen_descriptor.physical_width);
+                let y = y.min(screen_descriptor.physical_height);
+                let width = width.min(screen_descriptor.physical_width - x);
+                let height = height.min(screen_descriptor.physical_height - y);
+                if width == 0 || height == 0 {
+                    self.stats.culled_mesh();
+                    continue;
+                }
+                let [vx, vy, vw, vh] = state.viewport;
+                pass.set_viewport(vx, vy, vw, vh, 0.0, 1.0);
+                pass.set_scissor_rect(x, y, width, height);
+            } else {
+                pass.set_scissor_rect(x, y, width, height);
+            }
+            pass.set_bind_group(1, bind_group, &[]);
+
+            pass.set_index_buffer(
+                self.index_buffer.buffer.slice(range.indices.clone()),
+                wgpu::IndexFormat::Uint32,
+            );
+            pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(range.vertices.clone()));
+            if self.aux_vertex_stream {
+                let aux_range = self
+                    .aux_ranges
+                    .get(i)
+                    .ok_or(BackendError::AuxBuffersNotUpdated)?;
+                pass.set_vertex_buffer(1, self.aux_buffer.buffer.slice(aux_range.clone()));
+            }
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+            self.stats.draw_call();
+        }
+        for callback in callbacks {
+            self.run_paint_callback(pass, callback, screen_descriptor);
+            pipeline_key = PipelineKey::default();
+        }
+
+        if pipeline_key != PipelineKey::default() {
+            pass.set_pipeline(&self.render_pipeline);
+        }
+        self.draw_software_cursor(pass, screen_descriptor)?;
+        Ok(())
+    }
+
+    /// Sets the egui pipeline and its uniforms on `pass`.
+    fn set_egui_state<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        screen_descriptor: &ScreenDescriptor,
+    ) {
+        pass.set_pipeline(&self.render_pipeline);
+        pass.set_bind_group(
+            0,
+            &self.uniform_bind_group,
+            &[self.uniform_offset(screen_descriptor)],
+        );
+        if self.push_constants {
+            pass.set_push_constants(
+                UNIFORM_STAGES,
+                0,
+                bytemuck::cast_slice(&[UniformBuffer::new(
+                    screen_descriptor,
+                    &self.constants,
+                    self.blend_space,
+                )]),
+            );
+        }
+    }
+
+    /// Returns the pipeline variant a texture is drawn with. The egui texture always uses the default pipeline.
+    fn texture_pipeline_key(&self, texture_id: egui::TextureId) -> PipelineKey {
+        match self.resolve_texture_region(texture_id).0 {
+            egui::TextureId::Egui => PipelineKey::default(),
+            egui::TextureId::User(id) => {
+                self.user_texture(id)
+                    .map_or(PipelineKey::default(), |texture| PipelineKey {
+                        blend_mode: texture.params.blend_mode,
+                        sample_kind: texture.params.sample_kind,
+                    })
+            }
+        }
+    }
+
+    /// Returns the pipeline of `key`. It needs to be created by `set_user_texture()` before.
+    fn pipeline_variant(&self, key: PipelineKey) -> Result<&wgpu::RenderPipeline, BackendError> {
+        if key == PipelineKey::default() {
+            return Ok(&self.render_pipeline);
+        }
+        self.pipeline_variants
+            .iter()
+            .find(|(variant, _)| *variant == key)
+            .map(|(_, pipeline)| &**pipeline)
+            .ok_or(BackendError::PipelineNotCreated)
+    }
+
+    /// Sets how meshes using a user texture are composited with the output.
+    /// Only applies to `execute()` and `execute_multiple()`.
+    pub fn set_user_texture_blend_mode(
+        &mut self,
+        device: &wgpu::Device,
+        id: egui::TextureId,
+        blend_mode: TextureBlendMode,
+    ) {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return,
+        };
+        if let Some(pending) = self.pending_user_textures.iter_mut().find(|p| p.id == id) {
+            pending.params.blend_mode = blend_mode;
+            return;
+        }
+        if let Some(user_texture) = self.user_texture_mut(id) {
+            user_texture.params.blend_mode = blend_mode;
+            let key = PipelineKey {
+                blend_mode,
+                sample_kind: user_texture.params.sample_kind,
+            };
+            self.create_pipeline_variant(device, key);
+        }
+    }
+
+    /// Creates the pipeline of `key` if it doesn't exist yet.
+    fn create_pipeline_variant(&mut self, device: &wgpu::Device, key: PipelineKey) {
+        if key == PipelineKey::default()
+            || self
+                .pipeline_variants
+                .iter()
+                .any(|(variant, _)| *variant == key)
+        {
+            return;
+        }
+        self.create_sample_kind_layout(device, key.sample_kind);
+        let pipeline = self.context.pipeline(device, self.output_format, key);
+        self.pipeline_variants.push((key, pipeline));
+    }
+
+    /// Creates an egui pipeline of `key` that isn't cached by the context, e.g. with another depth stencil state.
+    fn create_keyed_pipeline(
+        &self,
+        device: &wgpu::Device,
+        key: PipelineKey,
+        label: &str,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        self.context
+            .create_keyed_pipeline(device, self.output_format, key, label, depth_stencil)
+    }
+
+    /// Returns the layout of a sample kind, or `None` for `TextureSampleKind::Float`, which uses the default layout.
+    fn sample_kind_layout(&self, sample_kind: TextureSampleKind) -> Option<&SampleKindLayout> {
+        self.sample_kind_layouts
+            .iter()
+            .find(|layout| layout.sample_kind == sample_kind)
+            .map(|layout| &**layout)
+    }
+
+    /// Creates the layout of `sample_kind` if it doesn't exist yet.
+    fn create_sample_kind_layout(&mut self, device: &wgpu::Device, sample_kind: TextureSampleKind) {
+        if sample_kind == TextureSampleKind::Float || self.sample_kind_layout(sample_kind).is_some()
+        {
+            return;
+        }
+        let layout = self.context.sample_kind_layout(device, sample_kind);
+        self.sample_kind_layouts.push(layout);
+    }
+
+    /// Sets the gradient drawn over the whole window before the UI, on top of the clear color.
+    /// `None` disables the gradient.
+    pub fn set_background_gradient(&mut self, gradient: Option<BackgroundGradient>) {
+        self.background_gradient = gradient;
+    }
+
+    /// Sets the texture drawn over the whole window before the UI, on top of the background gradient.
+    /// `None` disables the background image.
+    pub fn set_background_image(&mut self, image: Option<BackgroundImage>) {
+        self.background_image = image;
+    }
+
+    /// Sets the cursor sprite drawn on top of the UI. `None` disables the software cursor.
+    pub fn set_software_cursor(&mut self, cursor: Option<SoftwareCursor>) {
+        self.software_cursor = cursor;
+    }
+
+    /// Sets the position of the software cursor in points. Should be called every frame before
+    /// `update_buffers()`. `None` hides the cursor, e.g. when it left the window.
+    pub fn set_cursor_position(&mut self, position: Option<egui::Pos2>) {
+        self.cursor_position = position;
+    }
+
+    /// Fills the window outside of the UI region with the letterbox color. Expects the default pipeline to be set.
+    fn draw_letterbox<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        screen_descriptor: &ScreenDescriptor,
+    ) {
+        let (buffers, bind_group) = match (&self.letterbox_buffers, &self.texture_bind_group) {
+            (Some(buffers), Some(bind_group)) => (buffers, bind_group),
+            _ => return,
+        };
+        if screen_descriptor
+            .viewport
+            .and_then(|viewport| viewport.letterbox_color)
+            .is_none()
+        {
+            return;
+        }
+
+        // The quad covers the whole window with the viewport set to the window.
+        pass.set_viewport(
+            0.0,
+            0.0,
+            screen_descriptor.physical_width as f32,
+            screen_descriptor.physical_height as f32,
+            0.0,
+            1.0,
+        );
+        pass.set_bind_group(1, bind_group, &[]);
+        if let Some(aux_zero_buffer) = &self.aux_zero_buffer {
+            pass.set_vertex_buffer(1, aux_zero_buffer.slice(..));
+        }
+        for (x, y, width, height) in screen_descriptor.letterbox_bars() {
+            pass.set_scissor_rect(x, y, width, height);
+            buffers.draw(pass);
+        }
+    }
+
+    fn draw_background_gradient<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        screen_descriptor: &ScreenDescriptor,
+    ) {
+        let (buffers, bind_group) = match (&self.gradient_buffers, &self.texture_bind_group) {
+            (Some(buffers), Some(bind_group)) if self.background_gradient.is_some() => {
+                (buffers, bind_group)
+            }
+            _ => return,
+        };
+
+        // The mesh covers the whole window with the viewport set to the window.
+        pass.set_viewport(
+            0.0,
+            0.0,
+            screen_descriptor.physical_width as f32,
+            screen_descriptor.physical_height as f32,
+            0.0,
+            1.0,
+        );
+        pass.set_scissor_rect(
+            0,
+            0,
+            screen_descriptor.physical_width,
+            screen_descriptor.physical_height,
+        );
+        pass.set_bind_group(1, bind_group, &[]);
+        if let Some(aux_zero_buffer) = &self.aux_zero_buffer {
+            pass.set_vertex_buffer(1, aux_zero_buffer.slice(..));
+        }
+        buffers.draw(pass);
+    }
+
+    fn draw_background_image<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        let (image, buffers) = match (&self.background_image, &self.background_buffers) {
+            (Some(image), Some(buffers)) => (image, buffers),
+            _ => return Ok(()),
+        };
+        let bind_group = match self.get_texture_bind_group(image.texture_id)? {
+            Some(bind_group) => bind_group,
+            None => return Ok(()),
+        };
+
+        // The mesh covers the whole window with the viewport set to the window.
+        pass.set_viewport(
+            0.0,
+            0.0,
+            screen_descriptor.physical_width as f32,
+            screen_descriptor.physical_height as f32,
+            0.0,
+            1.0,
+        );
+        pass.set_scissor_rect(
+            0,
+            0,
+            screen_descriptor.physical_width,
+            screen_descriptor.physical_height,
+        );
+        let pipeline_key = self.texture_pipeline_key(image.texture_id);
+        pass.set_pipeline(self.pipeline_variant(pipeline_key)?);
+        pass.set_bind_group(1, bind_group, &[]);
+        if let Some(aux_zero_buffer) = &self.aux_zero_buffer {
+            pass.set_vertex_buffer(1, aux_zero_buffer.slice(..));
+        }
+        buffers.draw(pass);
+        if pipeline_key != PipelineKey::default() {
+            pass.set_pipeline(&self.render_pipeline);
+        }
+        Ok(())
+    }
+
+    fn draw_software_cursor<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        let (cursor, buffers) = match (&self.software_cursor, &self.cursor_buffers) {
+            (Some(cursor), Some(buffers)) if self.cursor_position.is_some() => (cursor, buffers),
+            _ => return Ok(()),
+        };
+        let bind_group = match self.get_texture_bind_group(cursor.texture_id)? {
+            Some(bind_group) => bind_group,
+            None => return Ok(()),
+        };
+
+        let (x, y, width, height) = screen_descriptor.target_region();
+        if self.draw_hook.is_some() {
+            pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        }
+        pass.set_scissor_rect(x, y, width, height);
+        pass.set_bind_group(1, bind_group, &[]);
+        if let Some(aux_zero_buffer) = &self.aux_zero_buffer {
+            pass.set_vertex_buffer(1, aux_zero_buffer.slice(..));
+        }
+        buffers.draw(pass);
+        Ok(())
+    }
+
+    /// Renders the id of the mesh that produced each pixel into `id_attachment` instead of colors.
+    /// This is a debug mode to find out which mesh drew a pixel.
+    ///
+    /// `id_attachment` needs the format `wgpu::TextureFormat::R32Uint`. It is cleared to `0` and the mesh of
+    /// `paint_jobs[i]` writes `ids[i]` if given, or `i + 1` otherwise. Custom geometry writes `0`. Fully
+    /// transparent pixels of a mesh are not written. Expects `update_buffers()` to be called with the same
+    /// paint jobs before.
+    ///
+    /// Returns `BackendError::InvalidPickingIds` if `ids` doesn't have one entry per paint job, or contains
+    /// `u32::MAX`, which is reserved.
+    /// The picking pipeline doesn't use `RenderPassConfig::depth_stencil_format`, so the pass never needs a
+    /// depth-stencil attachment.
+    pub fn execute_picking(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        id_attachment: &wgpu::TextureView,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+        ids: Option<&[u32]>,
+    ) -> Result<(), BackendError> {
+        if let Some(ids) = ids {
+            if ids.len() != paint_jobs.len() || ids.contains(&u32::MAX) {
+                return Err(BackendError::InvalidPickingIds);
+            }
+        }
+        if self.picking_pipeline.is_none() {
+            self.picking_pipeline = Some(self.create_picking_pipeline(device));
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: id_attachment,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            label: Some("egui picking render pass"),
+        });
+        pass.push_debug_group("egui_picking_pass");
+        pass.set_pipeline(self.picking_pipeline.as_ref().unwrap());
+
+        pass.set_bind_group(
+            0,
+            &self.uniform_bind_group,
+            &[self.uniform_offset(screen_descriptor)],
+        );
+        let (x, y, width, height) = screen_descriptor.target_region();
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+
+        for (i, ((paint_job, egui::ClippedMesh(clip_rect, mesh)), range)) in
+            merge_custom_geometry(paint_jobs, &self.frame_geometry)
+                .into_iter()
+                .zip(self.mesh_ranges.iter())
+                .enumerate()
+        {
+            if self.is_mesh_covered(i) {
+                continue;
+            }
+            let range = match range {
+                Some(range) => range,
+                None => continue,
+            };
+            let (x, y, width, height) =
+                match screen_descriptor.scissor_rect(clip_rect, self.config.scissor_expansion) {
+                    Some(rect) => rect,
+                    None => continue,
+                };
+            pass.set_scissor_rect(x, y, width, height);
+
+            // Only filterable textures can be drawn with the picking pipeline.
+            if self.texture_pipeline_key(mesh.texture_id).sample_kind != TextureSampleKind::Float {
+                continue;
+            }
+            let bind_group = match self.get_texture_bind_group(mesh.texture_id)? {
+                Some(bind_group) => bind_group,
+                None => continue,
+            };
+            pass.set_bind_group(1, bind_group, &[]);
+
+            let id = match (paint_job, ids) {
+                (Some(paint_job), Some(ids)) => ids[paint_job],
+                (Some(paint_job), None) => (paint_job as u32).saturating_add(1),
+                (None, _) => 0,
+            };
+            pass.set_index_buffer(
+                self.index_buffer.buffer.slice(range.indices.clone()),
+                wgpu::IndexFormat::Uint32,
+            );
+            pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(range.vertices.clone()));
+            // The id is passed to the shader as the instance index.
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, id..id.saturating_add(1));
+        }
+
+        pass.pop_debug_group();
+        Ok(())
+    }
+
+    fn create_picking_pipeline(&self, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let vs_module = device.create_shader_module(&include_shader!("egui_picking.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("egui_picking.frag"));
+
+        create_render_pipeline(
+            device,
+            "egui_picking_pipeline",
+            &self.context.shared.pipeline_layout,
+            &vs_module,
+            &fs_module,
+            &[EGUI_VERTEX_BUFFER_LAYOUT],
+            wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::R32Uint,
+                color_blend: wgpu::BlendState::REPLACE,
+                alpha_blend: wgpu::BlendState::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            },
+            None,
+            1,
+        )
+    }
+
+    /// Records the draw calls of the paint jobs into a render bundle, which can be replayed inside
+    /// render passes with a color attachment of the output format using `wgpu::RenderPass::execute_bundles()`.
+    ///
+    /// The bundle brings its own copy of the screen size, but references the vertex and index buffers
+    /// of the last `update_buffers()` call, which needs to be called with the same paint jobs before.
+    /// Calling `update_buffers()` again invalidates the bundle. Bundles can't set a viewport, so the
+    /// viewport of the screen descriptor is ignored and the bundle covers the whole window.
+    pub fn record_bundle(
+        &mut self,
+        device: &wgpu::Device,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<wgpu::RenderBundle, BackendError> {
+        let screen_descriptor = &ScreenDescriptor {
+            viewport: None,
+            ..*screen_descriptor
+        };
+        if self.bundle_pipeline.is_none() {
+            self.bundle_pipeline = Some(self.create_bundle_pipeline(device));
+        }
+        let bundle_pipeline = self.bundle_pipeline.as_ref().unwrap();
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_bundle_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[UniformBuffer::new(
+                screen_descriptor,
+                &self.constants,
+                self.blend_space,
+            )]),
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+        let uniform_bind_group = create_uniform_bind_group(
+            device,
+            &self.context.shared.uniform_bind_group_layout,
+            &uniform_buffer,
+            "egui_bundle_uniform_bind_group",
+        );
+
+        // One clip rect per paint job at aligned offsets.
+        let mesh_count = paint_jobs.len() + self.frame_geometry.len();
+        let mut clip_rects = vec![0u8; mesh_count.max(1) * UNIFORM_OFFSET_ALIGNMENT];
+        for (i, egui::ClippedMesh(clip_rect, _)) in
+            self.frame_meshes(paint_jobs).into_iter().enumerate()
+        {
+            if let Some((x, y, width, height)) =
+                screen_descriptor.scissor_rect(clip_rect, self.config.scissor_expansion)
+            {
+                let rect = [x as f32, y as f32, (x + width) as f32, (y + height) as f32];
+                let offset = i * UNIFORM_OFFSET_ALIGNMENT;
+                clip_rects[offset..offset + 16].copy_from_slice(bytemuck::cast_slice(&rect));
+            }
+        }
+        let clip_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_bundle_clip_buffer"),
+            contents: &clip_rects,
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+        let clip_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("egui_bundle_clip_bind_group"),
+            layout: &bundle_pipeline.clip_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &clip_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(16),
+                },
+            }],
+        });
+
+        let mut encoder =
+            device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some("egui_bundle_encoder"),
+                color_formats: &[self.output_format],
+                depth_stencil_format: self.config.depth_stencil_format,
+                sample_count: self.sample_count,
+            });
+        encoder.set_pipeline(&bundle_pipeline.pipeline);
+        encoder.set_bind_group(0, &uniform_bind_group, &[0]);
+
+        for (i, (egui::ClippedMesh(clip_rect, mesh), range)) in self
+            .frame_meshes(paint_jobs)
+            .into_iter()
+            .zip(self.mesh_ranges.iter())
+            .enumerate()
+        {
+            if self.is_mesh_covered(i) {
+                continue;
+            }
+            let range = match range {
+                Some(range) => range,
+                None => continue,
+            };
+            if screen_descriptor
+                .scissor_rect(clip_rect, self.config.scissor_expansion)
+                .is_none()
+            {
+                continue;
+            }
+            // Only filterable textures can be drawn with the bundle pipeline.
+            if self.texture_pipeline_key(mesh.texture_id).sample_kind != TextureSampleKind::Float {
+                continue;
+            }
+            let bind_group = match self.get_texture_bind_group(mesh.texture_id)? {
+                Some(bind_group) => bind_group,
+                None => continue,
+            };
+            encoder.set_bind_group(1, bind_group, &[]);
+            encoder.set_bind_group(
+                2,
+                &clip_bind_group,
+                &[(i * UNIFORM_OFFSET_ALIGNMENT) as wgpu::DynamicOffset],
+            );
+            encoder.set_index_buffer(
+                self.index_buffer.buffer.slice(range.indices.clone()),
+                wgpu::IndexFormat::Uint32,
+            );
+            encoder.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(range.vertices.clone()));
+            encoder.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+        }
+
+        Ok(encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("egui_bundle"),
+        }))
+    }
+
+    fn create_bundle_pipeline(&self, device: &wgpu::Device) -> BundlePipeline {
+        let vs_module = device.create_shader_module(&include_shader!("egui.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("egui_shader_clip.frag"));
+
+        let clip_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("egui_clip_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(16),
+                        ty: wgpu::BufferBindingType::Uniform,
+                    },
+                    count: None,
+                }],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("egui_bundle_pipeline_layout"),
+            bind_group_layouts: &[
+                &self.context.shared.uniform_bind_group_layout,
+                &self.context.shared.texture_bind_group_layout,
+                &clip_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_render_pipeline(
+            device,
+            "egui_bundle_pipeline",
+            &pipeline_layout,
+            &vs_module,
+            &fs_module,
+            &[EGUI_VERTEX_BUFFER_LAYOUT],
+            egui_color_target_state(self.output_format, &self.config),
+            egui_depth_stencil_state(&self.config),
+            self.sample_count,
+        );
+
+        BundlePipeline {
+            pipeline,
+            clip_bind_group_layout,
+        }
+    }
+
+    /// Returns the view of a texture, or `None` if it is not uploaded yet, evicted or freed.
+    fn texture_view(&self, texture_id: egui::TextureId) -> Option<&wgpu::TextureView> {
+        match self.resolve_texture_region(texture_id).0 {
+            egui::TextureId::Egui => self.texture_view.as_ref(),
+            egui::TextureId::User(id) if id & SHARED_TEXTURE_FLAG != 0 => {
+                self.shared_texture(id).ok().map(|texture| &texture.view)
+            }
+            egui::TextureId::User(id) => self.user_texture(id).map(|texture| &texture.view),
+        }
+    }
+
+    /// Returns the draw calls `execute()` makes for `paint_jobs`, e.g. to translate the UI to another graphics API
+    /// or export it, reusing the clipping and buffers of this render pass. Meshes with an empty scissor rect,
+    /// covered, malformed and empty meshes are skipped. `update_buffers()` needs to be called with the same paint jobs before.
+    pub fn draw_commands<'a>(
+        &'a self,
+        paint_jobs: &'a [egui::paint::ClippedMesh],
+        screen_descriptor: &'a ScreenDescriptor,
+    ) -> impl Iterator<Item = DrawCommand<'a>> + 'a {
+        self.frame_meshes(paint_jobs)
+            .into_iter()
+            .zip(self.mesh_ranges.iter())
+            .enumerate()
+            .filter_map(move |(i, (egui::ClippedMesh(clip_rect, mesh), range))| {
+                if self.is_mesh_covered(i) {
+                    return None;
+                }
+                let range = range.as_ref()?;
+                let (x, y, width, height) =
+                    screen_descriptor.scissor_rect(clip_rect, self.config.scissor_expansion)?;
+                Some(DrawCommand {
+                    mesh_index: i,
+                    mesh,
+                    scissor_rect: [x, y, width, height],
+                    texture_id: mesh.texture_id,
+                    texture_view: self.texture_view(mesh.texture_id),
+                    vertex_buffer: self.vertex_buffer.buffer.slice(range.vertices.clone()),
+                    index_buffer: self.index_buffer.buffer.slice(range.indices.clone()),
+                    index_count: mesh.indices.len() as u32,
+                })
+            })
+    }
+
+    /// Adds `mesh` to the buffers of the next `update_buffers()`, drawn with its texture and clipped to
+    /// `clip_rect` before the paint job at `position`, or after all of them if `position` is past the end.
+    /// Uses egui's vertex layout, so e.g. plots or particles can be drawn in the middle of the UI without
+    /// a separate render pass. The geometry is drawn for a single frame and counts as a mesh for the
+    /// mesh indices of draw hooks, draw commands and picking.
+    pub fn add_custom_geometry(
+        &mut self,
+        position: usize,
+        clip_rect: egui::Rect,
+        mesh: egui::paint::Mesh,
+    ) {
+        self.custom_geometry
+            .push((position, egui::ClippedMesh(clip_rect, mesh)));
+    }
+
+    /// Returns `true` if mesh `i` of the current frame is hidden behind later meshes and isn't drawn.
+    fn is_mesh_covered(&self, i: usize) -> bool {
+        self.covered_meshes.get(i).copied().unwrap_or(false)
+    }
+
+    /// Returns the paint jobs with the custom geometry of the current frame, in the order of the buffers.
+    fn frame_meshes<'s, 'a: 's>(
+        &'s self,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+    ) -> Vec<&'s egui::paint::ClippedMesh> {
+        merge_custom_geometry(paint_jobs, &self.frame_geometry)
+            .into_iter()
+            .map(|(_, mesh)| mesh)
+            .collect()
+    }
+
+    /// Returns the texture and view of a user texture the backend uploaded, e.g. to run compute passes on an
+    /// image shown in the UI. Regions return the texture they are part of. Returns `None` for textures that
+    /// are pending, evicted or freed, and for textures registered from a `wgpu::Texture`.
+    pub fn get_wgpu_texture(
+        &self,
+        id: egui::TextureId,
+    ) -> Option<(&wgpu::Texture, &wgpu::TextureView)> {
+        match self.resolve_texture_region(id).0 {
+            egui::TextureId::User(id) => {
+                let user_texture = self.user_texture(id)?;
+                Some((user_texture.texture.as_ref()?, &user_texture.view))
+            }
+            egui::TextureId::Egui => None,
+        }
+    }
+
+    /// Returns a new user texture id, reusing the slot of a freed texture with the next generation if possible.
+    fn allocate_user_texture_id(&mut self) -> u64 {
+        self.lock_texture_queue().allocate_id()
+    }
+
+    /// Marks the slot of an id chosen by the application as used, so it isn't handed out again. Returns
+    /// `BackendError::StaleUserTexture` if the slot was freed, or is used by another generation.
+    pub(crate) fn reserve_user_texture_id(&mut self, id: u64) -> Result<(), BackendError> {
+        if id & SHARED_TEXTURE_FLAG != 0 {
+            return Err(BackendError::UserTextureNotOwned(id));
+        }
+        let slot = user_texture_slot(id);
+        let other_generation = |other: u64| user_texture_slot(other) == slot && other != id;
+        let stale = matches!(self.user_textures.get(slot), Some(Some(user_texture)) if user_texture.id != id)
+            || self
+                .pending_user_textures
+                .iter()
+                .any(|p| other_generation(p.id))
+            || self.evicted_textures.iter().any(|e| other_generation(e.id));
+        if stale || !self.lock_texture_queue().reserve_id(id) {
+            return Err(BackendError::StaleUserTexture(id));
+        }
+        Ok(())
+    }
+
+    /// Returns the uploaded user texture of `id`, or `None` if its slot is empty or used by another generation.
+    fn user_texture(&self, id: u64) -> Option<&UserTexture> {
+        self.user_textures
+            .get(user_texture_slot(id))?
+            .as_ref()
+            .filter(|user_texture| user_texture.id == id)
+    }
+
+    /// Returns the texture of the context with `id` from the snapshot of the last `update_user_textures()`.
+    fn shared_texture(&self, id: u64) -> Result<&SharedTexture, BackendError> {
+        let shared_texture = self
+            .shared_textures
+            .get(user_texture_slot(id))
+            .ok_or(BackendError::UserTextureNotFound(id))?
+            .as_ref()
+            .ok_or(BackendError::UserTextureFreed(id))?;
+        if shared_texture.id != id {
+            return Err(BackendError::StaleUserTexture(id));
+        }
+        Ok(shared_texture)
+    }
+
+    fn user_texture_mut(&mut self, id: u64) -> Option<&mut UserTexture> {
+        self.user_textures
+            .get_mut(user_texture_slot(id))?
+            .as_mut()
+            .filter(|user_texture| user_texture.id == id)
+    }
+
+    /// Frees a user texture the backend uploaded and returns its texture, e.g. to hand an image uploaded for
+    /// a preview to the main renderer without copying it. The texture has `SAMPLED` and `COPY_DST` usage.
+    /// Returns `None` and keeps the texture if it is pending, evicted, a region or registered from a
+    /// `wgpu::Texture`.
+    pub fn take_texture(&mut self, id: egui::TextureId) -> Option<wgpu::Texture> {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return None,
+        };
+        self.user_texture(id)?.texture.as_ref()?;
+        let user_texture = self.user_textures[user_texture_slot(id)].take().unwrap();
+        self.lock_texture_queue().release_id(id);
+        user_texture.texture
+    }
+
+    /// Returns `None` if the user texture is still pending or evicted and can't be drawn this frame.
+    fn get_texture_bind_group(
+        &self,
+        texture_id: egui::TextureId,
+    ) -> Result<Option<&wgpu::BindGroup>, BackendError> {
+        match self.resolve_texture_region(texture_id).0 {
+            egui::TextureId::Egui => self
+                .texture_bind_group
+                .as_ref()
+                .map(Some)
+                .ok_or(BackendError::EguiTextureNotSet),
+            egui::TextureId::User(id) if id & SHARED_TEXTURE_FLAG != 0 => {
+                Ok(Some(&self.shared_texture(id)?.bind_group))
+            }
+            egui::TextureId::User(id) => {
+                if self.pending_user_textures.iter().any(|p| p.id == id)
+                    || self.evicted_textures.iter().any(|e| e.id == id)
+                {
+                    return Ok(None);
+                }
+                let user_texture = self
+                    .user_textures
+                    .get(user_texture_slot(id))
+                    .ok_or(BackendError::UserTextureNotFound(id))?
+                    .as_ref()
+                    .ok_or(BackendError::UserTextureFreed(id))?;
+                if user_texture.id != id {
+                    return Err(BackendError::StaleUserTexture(id));
+                }
+                Ok(Some(&user_texture.bind_group))
+            }
+        }
+    }
+
+    /// Updates the texture used by egui for the fonts etc. Should be called before `execute()`.
+    ///
+    /// If the size of the texture didn't change, only the region that differs from the previous version is
+    /// uploaded, so rasterizing new glyphs into a large font atlas doesn't upload all of it again.
+    pub fn update_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        egui_texture: &egui::Texture,
+    ) {
+        // Don't update the texture if it hasn't changed.
+        if self.texture_version == Some(egui_texture.version) {
+            return;
+        }
+        // Only upload the changed part if the atlas kept its size, e.g. when new glyphs were rasterized.
+        if let Some((texture, previous)) = &mut self.egui_texture {
+            if (previous.width, previous.height) == (egui_texture.width, egui_texture.height) {
+                if let Some(rect) =
+                    dirty_rect(&previous.pixels, &egui_texture.pixels, egui_texture.width)
+                {
+                    write_egui_texture_region(
+                        queue,
+                        texture,
+                        egui_texture,
+                        rect,
+                        self.config.single_channel_font_texture,
+                    );
+                    let [_, _, width, height] = rect;
+                    let texel_size = if self.config.single_channel_font_texture {
+                        1
+                    } else {
+                        4
+                    };
+                    self.stats.texture_upload(width * height * texel_size);
+                }
+                previous.pixels.copy_from_slice(&egui_texture.pixels);
+                self.texture_version = Some(egui_texture.version);
+                return;
+            }
+        }
+        let (texture, view, params) = if self.config.single_channel_font_texture {
+            let (texture, view) = self.egui_texture_to_wgpu(
+                device,
+                queue,
+                egui_texture,
+                wgpu::TextureFormat::R8Unorm,
+                "egui",
+            );
+            let params = TextureParams {
+                coverage: true,
+                ..Default::default()
+            };
+            (texture, view, params)
+        } else {
+            // we need to convert the texture into rgba_srgb format
+            let mut pixels: Vec<u8> = Vec::with_capacity(egui_texture.pixels.len() * 4);
+            for srgba in egui_texture.srgba_pixels() {
+                pixels.push(srgba.r());
+                pixels.push(srgba.g());
+                pixels.push(srgba.b());
+                pixels.push(srgba.a());
+            }
+            let srgba_texture = egui::Texture {
+                version: egui_texture.version,
+                width: egui_texture.width,
+                height: egui_texture.height,
+                pixels,
+            };
+            let (texture, view) = self.egui_texture_to_wgpu(
+                device,
+                queue,
+                &srgba_texture,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                "egui",
+            );
+            (texture, view, TextureParams::default())
+        };
+        let (_, bind_group) = self.create_texture_bind_group(device, &view, None, &params, "egui");
+        let texel_size = if params.coverage { 1 } else { 4 };
+        self.stats
+            .texture_upload(egui_texture.pixels.len() * texel_size);
+
+        self.texture_version = Some(egui_texture.version);
+        self.egui_texture = Some((texture, egui_texture.clone()));
+        self.texture_view = Some(view);
+        self.texture_bind_group = Some(bind_group);
+    }
+
+    /// Updates the user textures that the app allocated. Should be called before `execute()`.
+    ///
+    /// If an upload budget is set, only as many pending textures as fit into the budget are
+    /// uploaded, in the order they were allocated. At least one texture is uploaded each call,
+    /// so textures larger than the budget still make progress. Meshes using a texture that is
+    /// not uploaded yet are skipped by `execute()`. The commands of [`TextureCommandSender`]s and dropped
+    /// [`TextureHandle`]s are applied first. Returns the first error of these commands, e.g.
+    /// `BackendError::StaleUserTexture`, after uploading the textures anyway.
+    ///
+    /// The textures of the [`RenderContext`] are taken over as well, so textures it registered since the last
+    /// call can be drawn and the ones it freed are released.
+    pub fn update_user_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), BackendError> {
+        let result = self.apply_texture_commands();
+        self.shared_textures = self.context.shared_textures();
+        let mut uploaded = 0;
+        while let Some(pending) = self.pending_user_textures.front() {
+            if let Some(budget) = self.upload_budget {
+                if uploaded > 0 && uploaded + pending.texture.pixels.len() > budget {
+                    break;
+                }
+            }
+            let pending = self.pending_user_textures.pop_front().unwrap();
+            uploaded += pending.texture.pixels.len();
+
+            self.upload_user_texture(device, queue, pending);
+        }
+        self.evict_user_textures();
+        result
+    }
+
+    /// Uploads all pending user textures at once, ignoring the upload budget, and submits the uploads to
+    /// `queue` right away. With `wait`, blocks until the GPU finished them, e.g. before taking a screenshot
+    /// or showing a new scene whose textures need to be resident in the first frame. Returns the first error of
+    /// the texture commands like `update_user_textures()`.
+    pub fn flush_texture_uploads(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        wait: bool,
+    ) -> Result<(), BackendError> {
+        let result = self.apply_texture_commands();
+        self.shared_textures = self.context.shared_textures();
+        while let Some(pending) = self.pending_user_textures.pop_front() {
+            self.upload_user_texture(device, queue, pending);
+        }
+        self.evict_user_textures();
+
+        // Texture writes of the queue are only submitted with the next command buffers.
+        queue.submit(std::iter::empty());
+        if wait {
+            device.poll(wgpu::Maintain::Wait);
+        }
+        result
+    }
+
+    /// Sets the maximum number of bytes of uploaded user textures kept on the GPU. `None` disables the limit.
+    ///
+    /// Above the limit, `update_user_textures()` frees the textures that were drawn least recently, never the
+    /// ones drawn in the last frame. Evicted textures keep their `TextureId` and are uploaded again when a paint
+    /// job uses them, from their CPU copy or from the pixels of the texture provider if one is set. They are
+    /// missing for the frame they are requested in. Textures registered from a `wgpu::Texture` are not counted.
+    pub fn set_texture_memory_limit(&mut self, limit: Option<usize>) {
+        self.texture_memory_limit = limit;
+    }
+
+    /// Sets the callback that provides the pixels of evicted textures. With a provider, evicted textures don't
+    /// keep a CPU copy, except for data textures, which always do.
+    pub fn set_texture_provider(&mut self, provider: Option<Box<TextureProvider>>) {
+        self.texture_provider = provider;
+    }
+
+    /// Returns the number of bytes of the uploaded user textures counted by the texture memory limit.
+    pub fn texture_memory_usage(&self) -> usize {
+        self.user_textures
+            .iter()
+            .flatten()
+            .filter_map(|user_texture| user_texture.source.as_ref())
+            .map(|(texture, _)| texture.pixels.len())
+            .sum()
+    }
+
+    /// Returns the number of `wgpu::Texture`s the render pass uploaded for user textures and owns. Textures
+    /// registered from a `wgpu::Texture` are not counted.
+    pub fn owned_texture_count(&self) -> usize {
+        self.user_textures
+            .iter()
+            .flatten()
+            .filter(|user_texture| user_texture.texture.is_some())
+            .count()
+    }
+
+    /// Frees the least recently drawn user textures until the texture memory limit is met.
+    fn evict_user_textures(&mut self) {
+        let limit = match self.texture_memory_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        let mut usage = self.texture_memory_usage();
+        if usage <= limit {
+            return;
+        }
+
+        let frame = self.frame;
+        let mut candidates: Vec<(u64, usize)> = self
+            .user_textures
+            .iter()
+            .enumerate()
+            .filter_map(|(id, user_texture)| {
+                let user_texture = user_texture.as_ref()?;
+                if user_texture.source.is_some() && user_texture.last_used != frame {
+                    Some((user_texture.last_used, id))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        for (_, id) in candidates {
+            if usage <= limit {
+                break;
+            }
+            let user_texture = self.user_textures[id].take().unwrap();
+            let id = user_texture.id;
+            let (texture, format) = user_texture.source.unwrap();
+            usage -= texture.pixels.len();
+            let keep_source =
+                self.texture_provider.is_none() || format != wgpu::TextureFormat::Rgba8UnormSrgb;
+            self.evicted_textures.push(EvictedTexture {
+                id,
+                source: if keep_source {
+                    Some((texture, format))
+                } else {
+                    None
+                },
+                sampler: user_texture.sampler,
+                params: user_texture.params,
+            });
+        }
+    }
+
+    /// Marks a texture as used in the current frame and queues it for upload if it was evicted.
+    fn mark_texture_used(&mut self, texture_id: egui::TextureId) {
+        let id = match self.resolve_texture_region(texture_id).0 {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return,
+        };
+        let frame = self.frame;
+        if let Some(user_texture) = self.user_texture_mut(id) {
+            user_texture.last_used = frame;
+        } else if let Some(position) = self.evicted_textures.iter().position(|e| e.id == id) {
+            self.restore_evicted_texture(position);
+        }
+    }
+
+    fn restore_evicted_texture(&mut self, position: usize) {
+        let mut evicted = self.evicted_textures.swap_remove(position);
+        let id = evicted.id;
+        let source = evicted.source.take().or_else(|| {
+            let provider = self.texture_provider.as_mut()?;
+            let ((width, height), pixels) = provider(egui::TextureId::User(id))?;
+            let texture = egui::Texture {
+                version: 0,
+                width,
+                height,
+                pixels: pixels.iter().flat_map(|color| color.to_array()).collect(),
+            };
+            Some((texture, wgpu::TextureFormat::Rgba8UnormSrgb))
+        });
+        match source {
+            Some((texture, format)) => self.pending_user_textures.push_front(PendingTexture {
+                id,
+                texture,
+                format,
+                sampler: evicted.sampler,
+                params: evicted.params,
+            }),
+            None => self.evicted_textures.push(evicted),
+        }
+    }
+
+    fn upload_user_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pending: PendingTexture,
+    ) {
+        #[cfg(feature = "bcn")]
+        let pending = self.compress_pending_texture(pending);
+        let label = format!("user_texture{}", pending.id);
+        let (texture, view) =
+            self.egui_texture_to_wgpu(device, queue, &pending.texture, pending.format, &label);
+        self.stats.texture_upload(pending.texture.pixels.len());
+        self.set_user_texture(device, pending.id, view, pending.sampler, pending.params);
+        let user_texture = self.user_texture_mut(pending.id).unwrap();
+        user_texture.texture = Some(texture);
+        user_texture.source = Some((pending.texture, pending.format));
+    }
+
+    /// Rebuilds all GPU state on a new device, e.g. after the old device was lost. `TextureId`s stay valid.
+    ///
+    /// User textures allocated through `epi::TextureAllocator` are uploaded again from their CPU copies.
+    /// Textures registered from a `wgpu::Texture` belong to the old device and need to be registered again
+    /// with [`RenderPass::update_egui_texture_from_wgpu_texture`] before they are drawn. The egui texture is
+    /// uploaded again by the next call to `update_texture()`.
+    pub fn recreate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let output_format = self.output_format;
+        *self = self
+            .take_cpu_state()
+            .resume(device, queue, output_format)
+            .expect("the output format was supported when the render pass was created");
+    }
+
+    /// Releases all GPU resources, keeping the CPU side state, e.g. when an Android app is sent to the
+    /// background and loses its surface. Use [`SuspendedRenderPass::resume`] to continue rendering.
+    /// The same rules as for [`RenderPass::recreate`] apply to the textures.
+    pub fn suspend(mut self) -> SuspendedRenderPass {
+        self.take_cpu_state()
+    }
+
+    fn take_cpu_state(&mut self) -> SuspendedRenderPass {
+        let user_textures = std::mem::take(&mut self.user_textures)
+            .into_iter()
+            .filter_map(|user_texture| match user_texture {
+                Some(UserTexture {
+                    id,
+                    source: Some((texture, format)),
+                    sampler,
+                    params,
+                    ..
+                }) => Some(PendingTexture {
+                    id,
+                    texture,
+                    format,
+                    sampler,
+                    params,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        SuspendedRenderPass {
+            config: self.config.clone(),
+            colormap_luts: std::mem::take(&mut self.colormap_luts)
+                .into_iter()
+                .map(|lut| lut.colors)
+                .collect(),
+            draw_hook: self.draw_hook.take(),
+            software_cursor: self.software_cursor,
+            background_gradient: self.background_gradient,
+            background_image: self.background_image,
+            cursor_position: self.cursor_position,
+            backdrop_id: self.backdrop.as_ref().map(|backdrop| backdrop.id),
+            texture_queue: self.texture_queue.clone(),
+            upload_budget: self.upload_budget,
+            upload_strategy: self.upload_strategy,
+            #[cfg(feature = "bcn")]
+            texture_compression: self.texture_compression,
+            pending_user_textures: std::mem::take(&mut self.pending_user_textures),
+            user_textures,
+            texture_regions: std::mem::take(&mut self.texture_regions),
+            texture_memory_limit: self.texture_memory_limit,
+            texture_provider: self.texture_provider.take(),
+            evicted_textures: std::mem::take(&mut self.evicted_textures),
+        }
+    }
+
+    /// Sets the maximum number of texture bytes `update_user_textures()` uploads per call.
+    /// `None` disables the budget, which uploads all pending textures at once.
+    pub fn set_upload_budget(&mut self, budget: Option<usize>) {
+        self.upload_budget = budget;
+    }
+
+    /// Sets how `update_buffers()` and the texture updates upload their data. Use
+    /// [`UploadStrategy::for_adapter`] to pick the thresholds for the adapter.
+    pub fn set_upload_strategy(&mut self, strategy: UploadStrategy) {
+        self.upload_strategy = strategy;
+    }
+
+    /// Moves a pending user texture to the front of the upload queue.
+    pub fn prioritize_user_texture(&mut self, id: egui::TextureId) {
+        if let egui::TextureId::User(id) = id {
+            if let Some(position) = self.pending_user_textures.iter().position(|p| p.id == id) {
+                let entry = self.pending_user_textures.remove(position).unwrap();
+                self.pending_user_textures.push_front(entry);
+            }
+        }
+    }
+
+    /// Creates a texture from single channel float data, e.g. the output of a simulation, with `data` holding
+    /// `width * height` values row by row. The texture is uploaded by `update_user_textures()` like the
+    /// textures of `epi::TextureAllocator`.
+    ///
+    /// The values are stored as `R32Float` and shown with [`Colormap::Grayscale`], mapping `0.0..=1.0` to
+    /// black and white. Use [`RenderPass::set_user_texture_colormap`] and
+    /// [`RenderPass::set_user_texture_value_range`] to change this. The texture is always sampled with the
+    /// nearest texel and not drawn by `execute_picking()` and `record_bundle()`.
+    ///
+    /// Returns `BackendError::InvalidPixelData` if `data` doesn't hold `width * height` values.
+    pub fn create_data_texture_f32(
+        &mut self,
+        data: &[f32],
+        width: usize,
+        height: usize,
+    ) -> Result<egui::TextureId, BackendError> {
+        check_pixel_data(width * height * 4, data.len() * 4)?;
+        let id = self.allocate_user_texture_id();
+
+        self.pending_user_textures.push_back(PendingTexture {
+            id,
+            texture: egui::Texture {
+                version: 0,
+                width,
+                height,
+                pixels: bytemuck::cast_slice(data).to_vec(),
+            },
+            format: wgpu::TextureFormat::R32Float,
+            sampler: None,
+            params: TextureParams {
+                sample_kind: TextureSampleKind::UnfilterableFloat,
+                colormap: Colormap::Grayscale,
+                ..Default::default()
+            },
+        });
+
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Creates a texture from premultiplied RGBA pixels with 16 bits per channel, e.g. from RAW or medical
+    /// images, with `pixels` holding `width * height * 4` values row by row. The texture is uploaded by
+    /// `update_user_textures()` like the textures of `epi::TextureAllocator`.
+    ///
+    /// `srgb` tells whether the color channels are sRGB encoded like the pixels of egui, or linear. wgpu 0.7 has
+    /// no `Rgba16Unorm` format, so the pixels are stored as linear `Rgba16Float`, which keeps more precision than
+    /// 8 bit textures and avoids their banding.
+    ///
+    /// Returns `BackendError::InvalidPixelData` if `pixels` doesn't hold `width * height * 4` values.
+    pub fn create_texture_rgba16(
+        &mut self,
+        pixels: &[u16],
+        width: usize,
+        height: usize,
+        srgb: bool,
+    ) -> Result<egui::TextureId, BackendError> {
+        check_pixel_data(width * height * 4 * 2, pixels.len() * 2)?;
+        let id = self.allocate_user_texture_id();
+
+        let to_linear = |value: u16| {
+            let value = value as f32 / u16::MAX as f32;
+            if !srgb {
+                value
+            } else if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let halves: Vec<u16> = pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                [
+                    f16_from_f32(to_linear(p[0])),
+                    f16_from_f32(to_linear(p[1])),
+                    f16_from_f32(to_linear(p[2])),
+                    f16_from_f32(p[3] as f32 / u16::MAX as f32),
+                ]
+            })
+            .collect();
+
+        self.pending_user_textures.push_back(PendingTexture {
+            id,
+            texture: egui::Texture {
+                version: 0,
+                width,
+                height,
+                pixels: bytemuck::cast_slice(&halves).to_vec(),
+            },
+            format: wgpu::TextureFormat::Rgba16Float,
+            sampler: None,
+            params: TextureParams::default(),
+        });
+
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Creates a texture from linear, premultiplied RGBA float pixels, e.g. HDR photos or lightmaps, with
+    /// `pixels` holding `width * height * 4` values row by row. The texture is uploaded by
+    /// `update_user_textures()` like the textures of `epi::TextureAllocator`.
+    ///
+    /// `format` needs to be `Rgba16Float` or `Rgba32Float`. `Rgba32Float` keeps the full precision, but can't be
+    /// filtered in wgpu 0.7, so it is sampled with the nearest texel. Other formats return
+    /// `BackendError::UnsupportedTextureFormat`. Values above `1.0` are clipped on screen, use
+    /// [`RenderPass::set_user_texture_exposure`] to bring them into range.
+    ///
+    /// Returns `BackendError::InvalidPixelData` if `pixels` doesn't hold `width * height * 4` values.
+    pub fn create_hdr_texture(
+        &mut self,
+        pixels: &[f32],
+        width: usize,
+        height: usize,
+        format: wgpu::TextureFormat,
+    ) -> Result<egui::TextureId, BackendError> {
+        check_pixel_data(width * height * 4 * 4, pixels.len() * 4)?;
+        let (bytes, sample_kind) = match format {
+            wgpu::TextureFormat::Rgba16Float => {
+                let halves: Vec<u16> = pixels.iter().map(|&value| f16_from_f32(value)).collect();
+                (
+                    bytemuck::cast_slice(&halves).to_vec(),
+                    TextureSampleKind::Float,
+                )
+            }
+            wgpu::TextureFormat::Rgba32Float => (
+                bytemuck::cast_slice(pixels).to_vec(),
+                TextureSampleKind::UnfilterableFloat,
+            ),
+            _ => return Err(BackendError::UnsupportedTextureFormat(format)),
+        };
+        let id = self.allocate_user_texture_id();
+
+        self.pending_user_textures.push_back(PendingTexture {
+            id,
+            texture: egui::Texture {
+                version: 0,
+                width,
+                height,
+                pixels: bytes,
+            },
+            format,
+            sampler: None,
+            params: TextureParams {
+                sample_kind,
+                ..Default::default()
+            },
+        });
+
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Writes new premultiplied sRGBA `pixels` of `size` into the user texture `id`, row by row with 4 bytes
+    /// per pixel. The texture keeps its id, sampler and parameters.
+    ///
+    /// An uploaded texture of the same size is written in place through `queue`. Textures of another size,
+    /// compressed textures and textures that are pending or evicted are uploaded again by the next
+    /// `update_user_textures()` instead. Textures registered from a `wgpu::Texture` can't be updated, write
+    /// into the `wgpu::Texture` instead.
+    ///
+    /// Returns `BackendError::NotUserTexture` for `TextureId::Egui` and `BackendError::InvalidPixelData` if
+    /// `pixels` doesn't hold `size.0 * size.1 * 4` bytes.
+    pub fn update_user_texture(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        size: (usize, usize),
+        pixels: &[u8],
+    ) -> Result<(), BackendError> {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return Err(BackendError::NotUserTexture),
+        };
+        check_pixel_data(size.0 * size.1 * 4, pixels.len())?;
+        if self.pending_user_textures.iter().any(|p| p.id == id)
+            || self.evicted_textures.iter().any(|e| e.id == id)
+        {
+            return self.set_rgba_texture(id, size, pixels.to_vec());
+        }
+
+        let user_texture = self
+            .user_textures
+            .get_mut(user_texture_slot(id))
+            .ok_or(BackendError::UserTextureNotFound(id))?
+            .as_mut()
+            .ok_or(BackendError::UserTextureFreed(id))?;
+        if user_texture.id != id {
+            return Err(BackendError::StaleUserTexture(id));
+        }
+        let (texture, (source, format)) = match (&user_texture.texture, &mut user_texture.source) {
+            (Some(texture), Some(source)) => (texture, source),
+            _ => return Err(BackendError::UserTextureNotOwned(id)),
+        };
+        if *format != wgpu::TextureFormat::Rgba8UnormSrgb || (source.width, source.height) != size {
+            return self.set_rgba_texture(id, size, pixels.to_vec());
+        }
+
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            pixels,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: size.0 as u32 * 4,
+                rows_per_image: size.1 as u32,
+            },
+            wgpu::Extent3d {
+                width: size.0 as u32,
+                height: size.1 as u32,
+                depth: 1,
+            },
+        );
+        // Keep the CPU copy in sync, so `recreate()` and evictions restore the new pixels.
+        source.pixels.clear();
+        source.pixels.extend_from_slice(pixels);
+        self.stats.texture_upload(pixels.len());
+        Ok(())
+    }
+
+    /// Returns `true` if there are user textures waiting to be uploaded.
+    pub fn has_pending_user_textures(&self) -> bool {
+        !self.pending_user_textures.is_empty()
+    }
+
+    /// Sets the sampler settings of a user texture. `None` uses the settings the render pass
+    /// was created with.
+    pub fn set_user_texture_sampler(
+        &mut self,
+        device: &wgpu::Device,
+        id: egui::TextureId,
+        sampler: Option<SamplerOptions>,
+    ) {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return,
+        };
+        if let Some(pending) = self.pending_user_textures.iter_mut().find(|p| p.id == id) {
+            pending.sampler = sampler;
+            return;
+        }
+        if let Some(user_texture) = self.user_texture(id) {
+            let label = format!("user_texture{}", id);
+            let (uniform_buffer, bind_group) = self.create_texture_bind_group(
+                device,
+                &user_texture.view,
+                sampler.as_ref(),
+                &user_texture.params,
+                &label,
+            );
+            let user_texture = self.user_texture_mut(id).unwrap();
+            user_texture.sampler = sampler;
+            user_texture.uniform_buffer = uniform_buffer;
+            user_texture.bind_group = bind_group;
+        }
+    }
+
+    /// Creates a colormap lookup table from `colors`, which map the values `0.0..=1.0` in equal steps.
+    /// Use it with [`Colormap::Custom`]. Returns `BackendError::EmptyColormap` if `colors` is empty.
+    pub fn create_colormap_lut(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        colors: &[egui::Color32],
+    ) -> Result<ColormapLut, BackendError> {
+        if colors.is_empty() {
+            return Err(BackendError::EmptyColormap);
+        }
+        let index = self.colormap_luts.len();
+        let view = self.upload_colormap_lut(device, queue, colors, index);
+        self.colormap_luts.push(ColormapLutData {
+            colors: colors.to_vec(),
+            view,
+        });
+        Ok(ColormapLut(index))
+    }
+
+    /// Returns the number of colors of the lookup table of `colormap`.
+    fn colormap_size(&self, colormap: Colormap) -> u32 {
+        match colormap {
+            Colormap::Custom(ColormapLut(index)) => self.colormap_luts[index].colors.len() as u32,
+            _ => 1,
+        }
+    }
+
+    fn upload_colormap_lut(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        colors: &[egui::Color32],
+        index: usize,
+    ) -> wgpu::TextureView {
+        let label = format!("egui_colormap_lut{}", index);
+        let texture = egui::Texture {
+            version: 0,
+            width: colors.len(),
+            height: 1,
+            pixels: colors.iter().flat_map(|color| color.to_array()).collect(),
+        };
+        let (_, view) = self.egui_texture_to_wgpu(
+            device,
+            queue,
+            &texture,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            &label,
+        );
+        view
+    }
+
+    /// Sets the colormap applied to a user texture when it is drawn.
+    pub fn set_user_texture_colormap(
+        &mut self,
+        device: &wgpu::Device,
+        id: egui::TextureId,
+        colormap: Colormap,
+    ) {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return,
+        };
+        if let Some(pending) = self.pending_user_textures.iter_mut().find(|p| p.id == id) {
+            pending.params.colormap = colormap;
+            return;
+        }
+        if let Some(user_texture) = self.user_texture(id) {
+            let params = TextureParams {
+                colormap,
+                ..user_texture.params
+            };
+            // The lookup table is part of the bind group.
+            let label = format!("user_texture{}", id);
+            let (uniform_buffer, bind_group) = self.create_texture_bind_group(
+                device,
+                &user_texture.view,
+                user_texture.sampler.as_ref(),
+                &params,
+                &label,
+            );
+            let user_texture = self.user_texture_mut(id).unwrap();
+            user_texture.params = params;
+            user_texture.uniform_buffer = uniform_buffer;
+            user_texture.bind_group = bind_group;
+        }
+    }
+
+    /// Sets the data values that are mapped to `0.0` and `1.0` before the colormap is applied, e.g. to auto scale
+    /// a plot. Also stretches textures without a colormap. Cheap enough to be called every frame.
+    pub fn set_user_texture_value_range(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        min: f32,
+        max: f32,
+    ) {
+        self.update_texture_params(queue, id, |params| params.value_range = (min, max));
+    }
+
+    /// Sets the exposure of a user texture in stops (EV). The color is multiplied with `2^exposure` when it is
+    /// sampled, e.g. to inspect the bright or dark parts of HDR images. Cheap enough to be called every frame.
+    pub fn set_user_texture_exposure(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        exposure: f32,
+    ) {
+        self.update_texture_params(queue, id, |params| params.exposure = exposure);
+    }
+
+    /// Sets the operator that maps the colors of a user texture into the displayable range after the exposure,
+    /// e.g. for HDR textures from [`RenderPass::create_hdr_texture`]. Defaults to [`ToneMapping::Clamp`].
+    pub fn set_user_texture_tone_mapping(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        tone_mapping: ToneMapping,
+    ) {
+        self.update_texture_params(queue, id, |params| params.tone_mapping = tone_mapping);
+    }
+
+    /// Creates a `TextureId` showing the part `uv_rect` of `parent`, e.g. a sprite of an atlas. Meshes drawn
+    /// with it use UVs from 0 to 1, which are remapped into `uv_rect` when the vertices are uploaded, and share
+    /// the bind group, sampler and settings of the parent. Regions of regions map into the original texture.
+    ///
+    /// The region is freed like any other user texture and needs to be freed before its parent.
+    pub fn create_texture_region(
+        &mut self,
+        parent: egui::TextureId,
+        uv_rect: egui::Rect,
+    ) -> egui::TextureId {
+        let (parent, parent_region) = self.resolve_texture_region(parent);
+        let region = match parent_region {
+            Some(parent_region) => TextureRegion {
+                parent,
+                uv_rect: egui::Rect::from_min_max(
+                    parent_region.map_uv(uv_rect.min),
+                    parent_region.map_uv(uv_rect.max),
+                ),
+            },
+            None => TextureRegion { parent, uv_rect },
+        };
+
+        let id = self.allocate_user_texture_id();
+        self.texture_regions.insert(id, region);
+        egui::TextureId::User(id)
+    }
+
+    /// Returns the texture a region belongs to and the region, or `texture_id` itself if it's no region.
+    fn resolve_texture_region(
+        &self,
+        texture_id: egui::TextureId,
+    ) -> (egui::TextureId, Option<TextureRegion>) {
+        match texture_id {
+            egui::TextureId::User(id) => match self.texture_regions.get(&id) {
+                Some(region) => (region.parent, Some(*region)),
+                None => (texture_id, None),
+            },
+            egui::TextureId::Egui => (texture_id, None),
+        }
+    }
+
+    /// Sets the orientation of a user texture, which is applied to the UVs in the shader, so the pixels don't need
+    /// to be copied in another orientation. Doesn't apply to textures sampled in screen space.
+    pub fn set_user_texture_uv_transform(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        uv_transform: UvTransform,
+    ) {
+        self.update_texture_params(queue, id, |params| params.uv_transform = uv_transform);
+    }
+
+    /// Changes the shader settings of a user texture. Pending textures pick them up when uploaded.
+    fn update_texture_params(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: egui::TextureId,
+        update: impl FnOnce(&mut TextureParams),
+    ) {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return,
+        };
+        if let Some(pending) = self.pending_user_textures.iter_mut().find(|p| p.id == id) {
+            update(&mut pending.params);
+            return;
+        }
+        let sampler_options = self.sampler_options;
+        let colormap_size = match self.user_texture(id) {
+            Some(user_texture) => self.colormap_size(user_texture.params.colormap),
+            None => return,
+        };
+        if let Some(user_texture) = self.user_texture_mut(id) {
+            update(&mut user_texture.params);
+            let sampler = user_texture.sampler.as_ref().unwrap_or(&sampler_options);
+            queue.write_buffer(
+                &user_texture.uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[TextureUniformBuffer::new(
+                    sampler,
+                    &user_texture.params,
+                    colormap_size,
+                )]),
+            );
+        }
+    }
+
+    fn set_user_texture(
+        &mut self,
+        device: &wgpu::Device,
+        id: u64,
+        view: wgpu::TextureView,
+        sampler: Option<SamplerOptions>,
+        params: TextureParams,
+    ) {
+        self.create_pipeline_variant(
+            device,
+            PipelineKey {
+                blend_mode: params.blend_mode,
+                sample_kind: params.sample_kind,
+            },
+        );
+        let label = format!("user_texture{}", id);
+        let (uniform_buffer, bind_group) =
+            self.create_texture_bind_group(device, &view, sampler.as_ref(), &params, &label);
+
+        let slot = user_texture_slot(id);
+        if slot >= self.user_textures.len() {
+            self.user_textures.resize_with(slot + 1, || None);
+        }
+        self.user_textures[slot] = Some(UserTexture {
+            id,
+            texture: None,
+            view,
+            source: None,
+            sampler,
+            params,
+            uniform_buffer,
+            bind_group,
+            last_used: self.frame,
+        });
+    }
+
+    /// Creates the uniform buffer and bind group of a texture. Uses the shared sampler if `sampler` is `None`.
+    /// Textures that are not filterable always use the nearest sampler of their sample kind.
+    fn create_texture_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        sampler: Option<&SamplerOptions>,
+        params: &TextureParams,
+        label: &str,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let options = sampler.unwrap_or(&self.sampler_options);
+        let own_sampler =
+            sampler.map(|options| options.create_sampler(device, &format!("{}_sampler", label)));
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(format!("{}_uniform_buffer", label).as_str()),
+            contents: bytemuck::cast_slice(&[TextureUniformBuffer::new(
+                options,
+                params,
+                self.colormap_size(params.colormap),
+            )]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let (layout, sampler) = match self.sample_kind_layout(params.sample_kind) {
+            Some(layout) => (&layout.bind_group_layout, &layout.sampler),
+            None => (
+                &self.context.shared.texture_bind_group_layout,
+                own_sampler.as_ref().unwrap_or(&self.context.shared.sampler),
+            ),
+        };
+        let colormap_lut = match params.colormap {
+            Colormap::Custom(ColormapLut(index)) => &self.colormap_luts[index].view,
+            _ => &self.context.shared.default_colormap_lut,
+        };
+
+        let entries = [
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: None,
+                },
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(colormap_lut),
+            },
+        ];
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(format!("{}_texture_bind_group", label).as_str()),
+            layout,
+            entries: if params.sample_kind.has_colormap_lut() {
+                &entries
+            } else {
+                &entries[..3]
+            },
+        });
+
+        (uniform_buffer, bind_group)
+    }
+
+    // Assumes egui_texture contains data in `format`, which is srgb for egui and user textures.
+    // This does not match how egui::Texture is documented as of writing, but this is how it is used for user textures.
     fn egui_texture_to_wgpu(
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         egui_texture: &egui::Texture,
+        format: wgpu::TextureFormat,
         label: &str,
-    ) -> wgpu::BindGroup {
+    ) -> (wgpu::Texture, wgpu::TextureView) {
         let size = wgpu::Extent3d {
             width: egui_texture.width as u32,
             height: egui_texture.height as u32,
@@ -399,163 +4277,580 @@ impl RenderPass {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
         });
 
-        queue.write_texture(
-            wgpu::TextureCopyView {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            egui_texture.pixels.as_slice(),
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: (egui_texture.pixels.len() / egui_texture.height) as u32,
-                rows_per_image: egui_texture.height as u32,
-            },
-            size,
-        );
+        // Compressed formats store rows of blocks.
+        let block_rows = egui_texture.height / format.describe().block_dimensions.1 as usize;
+        let bytes_per_row = (egui_texture.pixels.len() / block_rows) as u32;
+        let copy_view = wgpu::TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        };
+        if matches!(self.upload_strategy.texture_staging_threshold,
+            Some(threshold) if egui_texture.pixels.len() >= threshold)
+        {
+            // Rows of buffer to texture copies need to be aligned.
+            let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+            let padded_bytes_per_row = (bytes_per_row + alignment - 1) / alignment * alignment;
+            let mut padded = vec![0u8; padded_bytes_per_row as usize * block_rows];
+            for (target, row) in padded
+                .chunks_exact_mut(padded_bytes_per_row as usize)
+                .zip(egui_texture.pixels.chunks_exact(bytes_per_row as usize))
+            {
+                target[..row.len()].copy_from_slice(row);
+            }
+            let staging_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(format!("{}_staging_buffer", label).as_str()),
+                contents: &padded,
+                usage: wgpu::BufferUsage::COPY_SRC,
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("egui_upload_encoder"),
+            });
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: &staging_buffer,
+                    layout: wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: padded_bytes_per_row,
+                        rows_per_image: size.height,
+                    },
+                },
+                copy_view,
+                size,
+            );
+            queue.submit(Some(encoder.finish()));
+        } else {
+            queue.write_texture(
+                copy_view,
+                egui_texture.pixels.as_slice(),
+                wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row,
+                    rows_per_image: egui_texture.height as u32,
+                },
+                size,
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    /// Registers a `wgpu::Texture` with a `egui::TextureId`.
+    ///
+    /// This enables the application to reference
+    /// the texture inside an image ui element. This effectively enables off-screen rendering inside
+    /// the egui UI. Texture must be a 2D texture with a filterable color format, e.g. `TextureFormat::Rgba8UnormSrgb`,
+    /// and the texture usage `TextureUsage::SAMPLED`. Use [`RenderPass::try_egui_texture_from_wgpu_texture`]
+    /// to check this.
+    pub fn egui_texture_from_wgpu_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+    ) -> egui::TextureId {
+        self.egui_texture_from_wgpu_texture_with_sampler(device, texture, None)
+    }
+
+    /// Registers a `wgpu::Texture` with an existing `egui::TextureId`, replacing the texture it referenced.
+    /// Keeps the sampler settings and blend mode of the previous texture. Returns
+    /// `BackendError::StaleUserTexture` if the texture was freed and its slot reused, and
+    /// `BackendError::NotUserTexture` for `TextureId::Egui`.
+    pub fn update_egui_texture_from_wgpu_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        id: egui::TextureId,
+    ) -> Result<(), BackendError> {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return Err(BackendError::NotUserTexture),
+        };
+        self.reserve_user_texture_id(id)?;
+        self.pending_user_textures.retain(|p| p.id != id);
+        let (sampler, params) = match self.user_texture(id) {
+            Some(user_texture) => (user_texture.sampler, user_texture.params),
+            _ => (None, TextureParams::default()),
+        };
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.set_user_texture(device, id, view, sampler, params);
+        Ok(())
+    }
+
+    /// Registers a `wgpu::Texture` with a `egui::TextureId` that is composited with `blend_mode`.
+    /// See [`RenderPass::egui_texture_from_wgpu_texture`].
+    pub fn egui_texture_from_wgpu_texture_with_blend_mode(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        blend_mode: TextureBlendMode,
+    ) -> egui::TextureId {
+        let id = self.allocate_user_texture_id();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let params = TextureParams {
+            blend_mode,
+            ..Default::default()
+        };
+        self.set_user_texture(device, id, view, None, params);
+
+        egui::TextureId::User(id)
+    }
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(format!("{}_texture_bind_group", label).as_str()),
-            layout: &self.texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                ),
-            }],
+    /// Registers a `wgpu::Texture` with a `egui::TextureId` after checking that the render pass can sample it.
+    /// `descriptor` needs to be the descriptor `texture` was created with. Depth and integer textures are
+    /// registered like with [`RenderPass::egui_texture_from_wgpu_texture_with_aspect`], without the `spirv` feature
+    /// they return `TextureRegistrationError::UnsupportedFormat`.
+    pub fn try_egui_texture_from_wgpu_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        descriptor: &wgpu::TextureDescriptor,
+    ) -> Result<egui::TextureId, TextureRegistrationError> {
+        if !descriptor.usage.contains(wgpu::TextureUsage::SAMPLED) {
+            return Err(TextureRegistrationError::NotSampled);
+        }
+        if descriptor.dimension != wgpu::TextureDimension::D2 {
+            return Err(TextureRegistrationError::UnsupportedDimension(
+                descriptor.dimension,
+            ));
+        }
+        if descriptor.sample_count != 1 {
+            return Err(TextureRegistrationError::Multisampled);
+        }
+        match TextureSampleKind::from_format(descriptor.format, wgpu::TextureAspect::All) {
+            Some(TextureSampleKind::Float) => {
+                Ok(self.egui_texture_from_wgpu_texture(device, texture))
+            }
+            _ => self.egui_texture_from_wgpu_texture_with_aspect(
+                device,
+                texture,
+                descriptor.format,
+                wgpu::TextureAspect::All,
+            ),
+        }
+    }
+
+    /// Registers a view of one aspect of a `wgpu::Texture` with a `egui::TextureId`, e.g. to show a depth buffer
+    /// for debugging. `format` needs to be the format of `texture`. With `wgpu::TextureAspect::All` this also
+    /// registers textures with `Uint` and `Sint` formats, like object id maps or segmentation masks, and float formats
+    /// that can't be filtered, like `R32Float`.
+    ///
+    /// Depth is shown as gray scale and stencil and integer values as distinct colors, with zero being
+    /// transparent. These textures are always sampled with the nearest texel, are not drawn by `execute_picking()`
+    /// and `record_bundle()` and ignore custom fragment shaders. Everything but float textures needs the `spirv`
+    /// feature. Returns `TextureRegistrationError::UnsupportedFormat` if the aspect of `format` can't be
+    /// displayed.
+    pub fn egui_texture_from_wgpu_texture_with_aspect(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+        aspect: wgpu::TextureAspect,
+    ) -> Result<egui::TextureId, TextureRegistrationError> {
+        let sample_kind = TextureSampleKind::from_format(format, aspect)
+            .ok_or(TextureRegistrationError::UnsupportedFormat(format))?;
+        let id = self.allocate_user_texture_id();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect,
+            ..Default::default()
         });
+        let params = TextureParams {
+            sample_kind,
+            ..Default::default()
+        };
+        self.set_user_texture(device, id, view, None, params);
 
-        bind_group
+        Ok(egui::TextureId::User(id))
     }
 
-    /// Registers a `wgpu::Texture` with a `egui::TextureId`.
+    /// Registers a `wgpu::TextureView` with a `egui::TextureId`, e.g. to show a single mip level or array layer
+    /// of a render target. The view needs the same format and usage as the textures of
+    /// [`RenderPass::egui_texture_from_wgpu_texture`] and has to be 2D, which can't be checked here.
     ///
-    /// This enables the application to reference
-    /// the texture inside an image ui element. This effectively enables off-screen rendering inside
-    /// the egui UI. Texture must have the texture format `TextureFormat::Rgba8UnormSrgb` and 
-    /// Texture usage `TextureUsage::SAMPLED`.
-    pub fn egui_texture_from_wgpu_texture(
+    /// The render pass keeps the view, since it is needed again when the sampler or other settings of the
+    /// texture change.
+    pub fn egui_texture_from_wgpu_texture_view(
         &mut self,
         device: &wgpu::Device,
-        texture: &wgpu::Texture,
+        view: wgpu::TextureView,
     ) -> egui::TextureId {
+        let id = self.allocate_user_texture_id();
+        self.set_user_texture(device, id, view, None, TextureParams::default());
+
+        egui::TextureId::User(id)
+    }
 
+    /// Registers a `wgpu::Texture` with a `egui::TextureId` and its own sampler settings.
+    /// See [`RenderPass::egui_texture_from_wgpu_texture`].
+    pub fn egui_texture_from_wgpu_texture_with_sampler(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        sampler: Option<SamplerOptions>,
+    ) -> egui::TextureId {
         // We have to bind it here, so that we don't add it as a pending texture.
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(format!("{}_texture_bind_group", self.next_user_texture_id).as_str()),
-            layout: &self.texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                ),
-            }],
-        });
-        let texture_id = egui::TextureId::User(self.next_user_texture_id);
-        self.user_textures.push(Some(bind_group));
-        self.next_user_texture_id += 1;
+        let id = self.allocate_user_texture_id();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.set_user_texture(device, id, view, sampler, TextureParams::default());
+
+        egui::TextureId::User(id)
+    }
 
-        texture_id
+    /// Registers a `wgpu::Texture` with a `egui::TextureId` that is magnified and minified with `filter`,
+    /// e.g. `FilterMode::Nearest` to keep pixel art sharp. The other sampler settings are the ones the render
+    /// pass was created with. See [`RenderPass::egui_texture_from_wgpu_texture`].
+    pub fn egui_texture_from_wgpu_texture_with_filter(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        filter: wgpu::FilterMode,
+    ) -> egui::TextureId {
+        let sampler = SamplerOptions {
+            mag_filter: filter,
+            min_filter: filter,
+            ..self.sampler_options
+        };
+        self.egui_texture_from_wgpu_texture_with_sampler(device, texture, Some(sampler))
     }
 
     /// Uploads the uniform, vertex and index data used by the render pass. Should be called before `execute()`.
-    pub fn update_buffers(
+    ///
+    /// `paint_jobs` can be a slice or any iterator over the meshes, e.g. a filter, so they don't need to be
+    /// collected first.
+    ///
+    /// With `RenderPassConfig::validate_meshes`, malformed meshes aren't drawn and the first one is returned as
+    /// `BackendError::MalformedMesh`. The other meshes are uploaded anyway.
+    pub fn update_buffers<'a>(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        paint_jobs: &[egui::paint::ClippedMesh],
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
         screen_descriptor: &ScreenDescriptor,
-    ) {
-        let index_size = self.index_buffers.len();
-        let vertex_size = self.vertex_buffers.len();
-
-        let (logical_width, logical_height) = screen_descriptor.logical_size();
+    ) -> Result<(), BackendError> {
+        let mut writer = match self.upload_strategy.buffer_staging_threshold {
+            Some(threshold) => BufferWriter::Threshold {
+                queue,
+                threshold,
+                encoder: None,
+            },
+            None => BufferWriter::Queue(queue),
+        };
+        let result = self.write_buffers(device, &mut writer, paint_jobs, screen_descriptor);
+        writer.submit();
+        result
+    }
 
-        self.update_buffer(
+    /// Like [`RenderPass::update_buffers`], but records the uploads as copies from staging buffers into
+    /// `encoder` instead of writing through the queue. The data is only written once `encoder` is submitted,
+    /// so it needs to be recorded before the commands of `execute()`.
+    pub fn update_buffers_with_encoder<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        self.write_buffers(
             device,
-            queue,
-            BufferType::Uniform,
-            0,
-            bytemuck::cast_slice(&[UniformBuffer {
-                screen_size: [logical_width as f32, logical_height as f32],
-            }]),
-        );
+            &mut BufferWriter::Encoder(encoder),
+            paint_jobs,
+            screen_descriptor,
+        )
+    }
+
+    fn write_buffers<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        writer: &mut BufferWriter,
+        paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+        screen_descriptor: &ScreenDescriptor,
+    ) -> Result<(), BackendError> {
+        self.frame += 1;
+        self.stats.begin_frame();
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.poll(device);
+        }
+        if let Some(image) = self.background_image {
+            self.mark_texture_used(image.texture_id);
+        }
+
+        if let Some(gradient) = &self.background_gradient {
+            let (width, height) = screen_descriptor.logical_size();
+            let mesh = gradient.mesh(egui::vec2(width as f32, height as f32));
+            MeshBuffers::update(
+                device,
+                writer,
+                &mut self.gradient_buffers,
+                &mesh,
+                "egui_gradient",
+            );
+        }
+
+        if let Some(image) = &self.background_image {
+            let (width, height) = screen_descriptor.logical_size();
+            let (window_width, window_height) = screen_descriptor.window_size();
+            let mesh = image.mesh(
+                egui::vec2(width as f32, height as f32),
+                window_width as f32 / window_height.max(1) as f32,
+            );
+            MeshBuffers::update(
+                device,
+                writer,
+                &mut self.background_buffers,
+                &mesh,
+                "egui_background",
+            );
+        }
+
+        if let Some(color) = screen_descriptor
+            .viewport
+            .and_then(|viewport| viewport.letterbox_color)
+        {
+            let (width, height) = screen_descriptor.logical_size();
+            let mut mesh = egui::paint::Mesh::with_texture(egui::TextureId::Egui);
+            mesh.add_rect_with_uv(
+                egui::Rect::from_min_size(
+                    egui::pos2(0.0, 0.0),
+                    egui::vec2(width as f32, height as f32),
+                ),
+                egui::Rect::from_min_max(egui::paint::WHITE_UV, egui::paint::WHITE_UV),
+                color,
+            );
+            MeshBuffers::update(
+                device,
+                writer,
+                &mut self.letterbox_buffers,
+                &mesh,
+                "egui_letterbox",
+            );
+        }
+
+        if let (Some(cursor), Some(position)) = (&self.software_cursor, self.cursor_position) {
+            let mut mesh = egui::paint::Mesh::with_texture(cursor.texture_id);
+            mesh.add_rect_with_uv(
+                egui::Rect::from_min_size(position - cursor.hotspot, cursor.size),
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+            MeshBuffers::update(
+                device,
+                writer,
+                &mut self.cursor_buffers,
+                &mesh,
+                "egui_cursor",
+            );
+        }
 
-        for (i, egui::ClippedMesh(_, mesh)) in paint_jobs.iter().enumerate() {
-            let data: &[u8] = bytemuck::cast_slice(&mesh.indices);
-            if i < index_size {
-                self.update_buffer(device, queue, BufferType::Index, i, data)
+        // The uniforms rarely change, and with push constants only the pipeline variants read them from the
+        // buffer, so it is only written when they differ from the last frame.
+        let uniform = UniformBuffer::new(screen_descriptor, &self.constants, self.blend_space);
+        if self.uniform_slots.first() == Some(&uniform) {
+            self.uniform_slots.truncate(1);
+        } else {
+            self.uniform_slots.clear();
+            self.write_uniform_slot(device, writer, uniform);
+            self.stats.frame_mut().buffer_bytes += std::mem::size_of::<UniformBuffer>();
+        }
+
+        let mut frame_geometry = std::mem::take(&mut self.custom_geometry);
+        frame_geometry.sort_by_key(|(position, _)| *position);
+        self.frame_callbacks = std::mem::take(&mut self.paint_callbacks);
+        self.frame_callbacks
+            .sort_by_key(|callback| callback.position);
+        let meshes = merge_custom_geometry(paint_jobs, &frame_geometry);
+        self.covered_meshes = if self.config.skip_covered_meshes {
+            let expansion = self.config.scissor_expansion / screen_descriptor.scale_factor;
+            overdraw::covered_meshes(meshes.iter().map(|(_, mesh)| *mesh), expansion)
+        } else {
+            Vec::new()
+        };
+        let mut vertices: Vec<u8> = Vec::new();
+        let mut indices: Vec<u8> = Vec::new();
+        let mut result = Ok(());
+        self.mesh_ranges.clear();
+        for (i, (_, egui::ClippedMesh(_, mesh))) in meshes.into_iter().enumerate() {
+            let vertex_start = vertices.len() as wgpu::BufferAddress;
+            let index_start = indices.len() as wgpu::BufferAddress;
+            // Covered and malformed meshes aren't drawn, they only get a `None` range to keep the mesh indices.
+            let covered = self.is_mesh_covered(i);
+            let validation = if self.config.validate_meshes && !covered {
+                validate_mesh(i, mesh)
             } else {
-                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("egui_index_buffer"),
-                    contents: data,
-                    usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST,
-                });
-                self.index_buffers.push(SizedBuffer {
-                    buffer,
-                    size: data.len(),
-                });
-            }
-
-            let data: &[u8] = as_byte_slice(&mesh.vertices);
-            if i < vertex_size {
-                self.update_buffer(device, queue, BufferType::Vertex, i, data)
+                Ok(())
+            };
+            let stats = self.stats.frame_mut();
+            stats.meshes += 1;
+            if covered {
+                stats.covered_meshes += 1;
+            } else if let Err(err) = validation {
+                result = result.and(Err(err));
             } else {
-                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("egui_vertex_buffer"),
-                    contents: data,
-                    usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
-                });
+                stats.vertices += mesh.vertices.len();
+                stats.indices += mesh.indices.len();
+                self.mark_texture_used(mesh.texture_id);
 
-                self.vertex_buffers.push(SizedBuffer {
-                    buffer,
-                    size: data.len(),
-                });
+                indices.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+                match self.resolve_texture_region(mesh.texture_id).1 {
+                    Some(region) => {
+                        for vertex in &mesh.vertices {
+                            let vertex = egui::paint::Vertex {
+                                uv: region.map_uv(vertex.uv),
+                                ..*vertex
+                            };
+                            vertices
+                                .extend_from_slice(as_byte_slice(std::slice::from_ref(&vertex)));
+                        }
+                    }
+                    None => vertices.extend_from_slice(as_byte_slice(&mesh.vertices)),
+                }
             }
+
+            let range = MeshRange {
+                vertices: vertex_start..vertices.len() as wgpu::BufferAddress,
+                indices: index_start..indices.len() as wgpu::BufferAddress,
+            };
+            self.mesh_ranges
+                .push(Some(range).filter(|range| !range.is_empty()));
         }
+        self.index_buffer.write_shared(
+            device,
+            writer,
+            wgpu::BufferUsage::INDEX,
+            "egui_index_buffer",
+            &indices,
+        );
+        self.vertex_buffer.write_shared(
+            device,
+            writer,
+            wgpu::BufferUsage::VERTEX,
+            "egui_vertex_buffer",
+            &vertices,
+        );
+        self.stats.frame_mut().buffer_bytes += vertices.len() + indices.len();
+        self.frame_geometry = frame_geometry;
+        result
     }
 
-    /// Updates the buffers used by egui. Will properly re-size the buffers if needed.
-    fn update_buffer(
+    /// Uploads the auxiliary vertex data used by the render pass. Needs `RenderPassConfig::aux_vertex_stream`
+    /// to be set. `aux[i]` belongs to `paint_jobs[i]` and needs one entry per vertex. Meshes without
+    /// data and custom geometry get zeros. Should be called before `execute()`.
+    ///
+    /// Returns `BackendError::MissingAuxVertexStream` without the aux vertex stream. Meshes whose data doesn't
+    /// match their vertex count get zeros and the first one is returned as `BackendError::InvalidAuxData`.
+    pub fn update_aux_buffers(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        buffer_type: BufferType,
-        index: usize,
-        data: &[u8],
-    ) {
-        let (buffer, storage, name) = match buffer_type {
-            BufferType::Index => (
-                &mut self.index_buffers[index],
-                wgpu::BufferUsage::INDEX,
-                "index",
-            ),
-            BufferType::Vertex => (
-                &mut self.vertex_buffers[index],
-                wgpu::BufferUsage::VERTEX,
-                "vertex",
-            ),
-            BufferType::Uniform => (
-                &mut self.uniform_buffer,
-                wgpu::BufferUsage::UNIFORM,
-                "uniform",
-            ),
-        };
+        paint_jobs: &[egui::paint::ClippedMesh],
+        aux: &[Option<&[[f32; 4]]>],
+    ) -> Result<(), BackendError> {
+        if !self.aux_vertex_stream {
+            return Err(BackendError::MissingAuxVertexStream);
+        }
+        let mut result = Ok(());
+        let mut writer = BufferWriter::Queue(queue);
+        let mut data: Vec<u8> = Vec::new();
+        self.aux_ranges.clear();
 
-        if data.len() > buffer.size {
-            buffer.size = data.len();
-            buffer.buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(format!("egui_{}_buffer", name).as_str()),
-                contents: bytemuck::cast_slice(data),
-                usage: storage | wgpu::BufferUsage::COPY_DST,
-            });
-        } else {
-            queue.write_buffer(&buffer.buffer, 0, data);
+        let frame_geometry = std::mem::take(&mut self.frame_geometry);
+        let meshes = merge_custom_geometry(paint_jobs, &frame_geometry);
+        for (i, (paint_job, egui::ClippedMesh(_, mesh))) in meshes.into_iter().enumerate() {
+            let start = data.len() as wgpu::BufferAddress;
+            if !self.is_mesh_covered(i) {
+                match paint_job.and_then(|job| aux.get(job).copied().flatten()) {
+                    Some(aux) if aux.len() == mesh.vertices.len() => {
+                        data.extend_from_slice(bytemuck::cast_slice(aux));
+                    }
+                    Some(aux) => {
+                        result = result.and(Err(BackendError::InvalidAuxData {
+                            mesh: paint_job.unwrap(),
+                            expected: mesh.vertices.len(),
+                            actual: aux.len(),
+                        }));
+                        data.resize(data.len() + mesh.vertices.len() * 4 * 4, 0);
+                    }
+                    None => data.resize(data.len() + mesh.vertices.len() * 4 * 4, 0),
+                }
+            }
+            self.aux_ranges
+                .push(start..data.len() as wgpu::BufferAddress);
+        }
+        self.aux_buffer.write_shared(
+            device,
+            &mut writer,
+            wgpu::BufferUsage::VERTEX,
+            "egui_aux_buffer",
+            &data,
+        );
+        self.stats.frame_mut().buffer_bytes += data.len();
+        self.frame_geometry = frame_geometry;
+        result
+    }
+}
+
+/// The CPU side state of a [`RenderPass`] without any GPU resources. Created by [`RenderPass::suspend`].
+pub struct SuspendedRenderPass {
+    config: RenderPassConfig,
+    colormap_luts: Vec<Vec<egui::Color32>>,
+    draw_hook: Option<Box<DrawHook>>,
+    software_cursor: Option<SoftwareCursor>,
+    background_gradient: Option<BackgroundGradient>,
+    background_image: Option<BackgroundImage>,
+    cursor_position: Option<egui::Pos2>,
+    backdrop_id: Option<u64>,
+    texture_queue: SharedTextureQueue,
+    upload_budget: Option<usize>,
+    upload_strategy: UploadStrategy,
+    #[cfg(feature = "bcn")]
+    texture_compression: bool,
+    pending_user_textures: VecDeque<PendingTexture>,
+    /// Uploaded user textures, which are uploaded again on resume.
+    user_textures: Vec<PendingTexture>,
+    texture_regions: HashMap<u64, TextureRegion>,
+    texture_memory_limit: Option<usize>,
+    texture_provider: Option<Box<TextureProvider>>,
+    evicted_textures: Vec<EvictedTexture>,
+}
+
+impl SuspendedRenderPass {
+    /// Creates the GPU resources again on `device` and uploads the user textures. `output_format` can differ
+    /// from the format the render pass was created with, but needs to be supported by its configuration,
+    /// otherwise the suspended state is dropped with an error.
+    pub fn resume(
+        self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        output_format: wgpu::TextureFormat,
+    ) -> Result<RenderPass, BackendError> {
+        let mut render_pass = RenderPass::with_config(device, output_format, &self.config)?;
+        for colors in &self.colormap_luts {
+            render_pass.create_colormap_lut(device, queue, colors)?;
+        }
+        render_pass.draw_hook = self.draw_hook;
+        render_pass.software_cursor = self.software_cursor;
+        render_pass.background_gradient = self.background_gradient;
+        render_pass.background_image = self.background_image;
+        render_pass.cursor_position = self.cursor_position;
+        render_pass.backdrop = self.backdrop_id.map(|id| Backdrop::new(device, id));
+        render_pass.texture_queue = self.texture_queue;
+        render_pass.upload_budget = self.upload_budget;
+        render_pass.upload_strategy = self.upload_strategy;
+        #[cfg(feature = "bcn")]
+        {
+            render_pass.texture_compression = self.texture_compression;
+        }
+        render_pass.pending_user_textures = self.pending_user_textures;
+        render_pass.texture_regions = self.texture_regions;
+        render_pass.texture_memory_limit = self.texture_memory_limit;
+        render_pass.texture_provider = self.texture_provider;
+        render_pass.evicted_textures = self.evicted_textures;
+        for pending in self.user_textures {
+            render_pass.upload_user_texture(device, queue, pending);
         }
+        Ok(render_pass)
     }
 }
 
@@ -565,8 +4860,7 @@ impl epi::TextureAllocator for RenderPass {
         size: (usize, usize),
         srgba_pixels: &[egui::Color32],
     ) -> egui::TextureId {
-        let id = self.next_user_texture_id;
-        self.next_user_texture_id += 1;
+        let id = self.allocate_user_texture_id();
 
         let mut pixels = vec![0u8; srgba_pixels.len() * 4];
         for (target, given) in pixels.chunks_exact_mut(4).zip(srgba_pixels.iter()) {
@@ -574,31 +4868,638 @@ impl epi::TextureAllocator for RenderPass {
         }
 
         let (width, height) = size;
-        self.pending_user_textures.push((
+        self.pending_user_textures.push_back(PendingTexture {
             id,
-            egui::Texture {
+            texture: egui::Texture {
                 version: 0,
                 width,
                 height,
                 pixels,
             },
-        ));
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            sampler: None,
+            params: TextureParams::default(),
+        });
 
         egui::TextureId::User(id)
     }
 
+    /// Frees a user texture. Textures the backend uploaded are destroyed right away, so they must not be used
+    /// by command buffers or render bundles that were recorded but not submitted yet.
     fn free(&mut self, id: egui::TextureId) {
         if let egui::TextureId::User(id) = id {
-            self.user_textures
-                .get_mut(id as usize)
-                .and_then(|option| option.take());
+            let pending = self.pending_user_textures.len();
+            let evicted = self.evicted_textures.len();
+            self.pending_user_textures.retain(|p| p.id != id);
+            self.evicted_textures.retain(|e| e.id != id);
+            let mut freed = self.pending_user_textures.len() != pending
+                || self.evicted_textures.len() != evicted
+                || self.texture_regions.remove(&id).is_some();
+            if self.user_texture(id).is_some() {
+                let user_texture = self.user_textures[user_texture_slot(id)].take().unwrap();
+                // Release the video memory right away instead of when the last bind group is dropped.
+                if let Some(texture) = user_texture.texture {
+                    texture.destroy();
+                }
+                freed = true;
+            }
+            if freed {
+                self.lock_texture_queue().release_id(id);
+            }
+        }
+    }
+}
+
+/// Layout of the egui vertex buffer.
+const EGUI_VERTEX_BUFFER_LAYOUT: wgpu::VertexBufferLayout = wgpu::VertexBufferLayout {
+    array_stride: 5 * 4,
+    step_mode: wgpu::InputStepMode::Vertex,
+    // 0: vec2 position
+    // 1: vec2 texture coordinates
+    // 2: uint color
+    attributes: &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Uint],
+};
+
+/// Layout of the auxiliary vertex buffer.
+const AUX_VERTEX_BUFFER_LAYOUT: wgpu::VertexBufferLayout = wgpu::VertexBufferLayout {
+    array_stride: 4 * 4,
+    step_mode: wgpu::InputStepMode::Vertex,
+    // 3: vec4 auxiliary data
+    attributes: &wgpu::vertex_attr_array![3 => Float4],
+};
+
+/// Returns the vertex buffer layouts of the egui pipeline.
+fn egui_vertex_buffer_layouts(
+    aux_vertex_stream: bool,
+) -> &'static [wgpu::VertexBufferLayout<'static>] {
+    if aux_vertex_stream {
+        &[EGUI_VERTEX_BUFFER_LAYOUT, AUX_VERTEX_BUFFER_LAYOUT]
+    } else {
+        &[EGUI_VERTEX_BUFFER_LAYOUT]
+    }
+}
+
+/// Creates a `width` x 1 texture holding a colormap lookup table.
+fn create_colormap_lut_view(device: &wgpu::Device, width: u32, label: &str) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height: 1,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Push constant ranges of the egui pipelines.
+fn egui_push_constant_ranges(push_constants: bool) -> &'static [wgpu::PushConstantRange] {
+    if push_constants {
+        &[wgpu::PushConstantRange {
+            stages: UNIFORM_STAGES,
+            range: 0..std::mem::size_of::<UniformBuffer>() as u32,
+        }]
+    } else {
+        &[]
+    }
+}
+
+/// Creates the layout of bind group 1, which holds a texture, its sampler and its uniforms.
+fn create_texture_bind_group_layout(
+    device: &wgpu::Device,
+    label: &str,
+    sample_kind: TextureSampleKind,
+) -> wgpu::BindGroupLayout {
+    let (sample_type, filtering) = match sample_kind {
+        TextureSampleKind::Float => (wgpu::TextureSampleType::Float { filterable: true }, true),
+        TextureSampleKind::UnfilterableFloat => {
+            (wgpu::TextureSampleType::Float { filterable: false }, false)
+        }
+        TextureSampleKind::Depth => (wgpu::TextureSampleType::Depth, false),
+        TextureSampleKind::Uint => (wgpu::TextureSampleType::Uint, false),
+        TextureSampleKind::Sint => (wgpu::TextureSampleType::Sint, false),
+    };
+    let entries = [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Sampler {
+                filtering,
+                comparison: false,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                has_dynamic_offset: false,
+                min_binding_size: None,
+                ty: wgpu::BufferBindingType::Uniform,
+            },
+            count: None,
+        },
+        // Colormap lookup table
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        },
+    ];
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        // Only color textures can use a lookup table, the others have no float texture to look it up with.
+        entries: if sample_kind.has_colormap_lut() {
+            &entries
+        } else {
+            &entries[..3]
+        },
+    })
+}
+
+/// Creates the bind group of the uniforms in `buffer`, which are bound with a dynamic offset.
+fn create_uniform_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    label: &str,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer {
+                buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(
+                    std::mem::size_of::<UniformBuffer>() as wgpu::BufferAddress
+                ),
+            },
+        }],
+    })
+}
+
+/// Returns the depth-stencil state of the egui pipelines, which neither tests nor writes the attachment.
+fn egui_depth_stencil_state(config: &RenderPassConfig) -> Option<wgpu::DepthStencilState> {
+    config
+        .depth_stencil_format
+        .map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState {
+                front: wgpu::StencilFaceState::IGNORE,
+                back: wgpu::StencilFaceState::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+            bias: wgpu::DepthBiasState::default(),
+            clamp_depth: false,
+        })
+}
+
+/// Returns the color target used to render egui into `format`.
+fn egui_color_target_state(
+    format: wgpu::TextureFormat,
+    config: &RenderPassConfig,
+) -> wgpu::ColorTargetState {
+    let alpha_blend = if let Some(alpha_blend) = &config.alpha_blend {
+        alpha_blend.clone()
+    } else if config.export_alpha {
+        // Premultiplied `over`, so the alpha channel holds the coverage of the UI.
+        wgpu::BlendState {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        }
+    } else {
+        wgpu::BlendState {
+            src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        }
+    };
+    wgpu::ColorTargetState {
+        format,
+        color_blend: config
+            .color_blend
+            .clone()
+            .unwrap_or_else(|| TextureBlendMode::Normal.color_blend()),
+        alpha_blend,
+        write_mask: config.write_mask,
+    }
+}
+
+/// Creates a render pipeline drawing egui triangle lists into a single color target with `sample_count` samples
+/// and an optional depth stencil attachment.
+#[allow(clippy::too_many_arguments)]
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    vs_module: &wgpu::ShaderModule,
+    fs_module: &wgpu::ShaderModule,
+    vertex_buffers: &[wgpu::VertexBufferLayout],
+    target: wgpu::ColorTargetState,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            entry_point: "main",
+            module: vs_module,
+            buffers: vertex_buffers,
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: wgpu::CullMode::default(),
+            front_face: wgpu::FrontFace::default(),
+            polygon_mode: wgpu::PolygonMode::default(),
+            strip_index_format: Some(wgpu::IndexFormat::Uint32),
+        },
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            alpha_to_coverage_enabled: false,
+            count: sample_count,
+            mask: !0,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fs_module,
+            entry_point: "main",
+            targets: &[target],
+        }),
+    })
+}
+
+/// Inserts the custom geometry, sorted by position, before the paint jobs at its positions and appends
+/// the rest. Returns the index of the paint job for each mesh, or `None` for custom geometry.
+fn merge_custom_geometry<'a: 'b, 'b>(
+    paint_jobs: impl IntoIterator<Item = &'a egui::paint::ClippedMesh>,
+    geometry: &'b [(usize, egui::paint::ClippedMesh)],
+) -> Vec<(Option<usize>, &'b egui::paint::ClippedMesh)> {
+    let mut geometry = geometry.iter().peekable();
+    let mut meshes = Vec::new();
+    for (i, paint_job) in paint_jobs.into_iter().enumerate() {
+        while let Some((position, mesh)) = geometry.peek() {
+            if *position > i {
+                break;
+            }
+            meshes.push((None, mesh));
+            geometry.next();
+        }
+        meshes.push((Some(i), paint_job));
+    }
+    meshes.extend(geometry.map(|(_, mesh)| (None, mesh)));
+    meshes
+}
+
+/// Returns `BackendError::MalformedMesh` if `mesh` can't be drawn safely.
+fn validate_mesh(index: usize, mesh: &egui::paint::Mesh) -> Result<(), BackendError> {
+    let out_of_range = mesh
+        .indices
+        .iter()
+        .any(|&i| i as usize >= mesh.vertices.len());
+    if mesh.indices.len() % 3 != 0 || out_of_range {
+        return Err(BackendError::MalformedMesh {
+            index,
+            indices: mesh.indices.len(),
+            vertices: mesh.vertices.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Returns `BackendError::InvalidPixelData` if the `actual` bytes of pixel data don't match the `expected` ones.
+fn check_pixel_data(expected: usize, actual: usize) -> Result<(), BackendError> {
+    if expected != actual {
+        return Err(BackendError::InvalidPixelData { expected, actual });
+    }
+    Ok(())
+}
+
+/// Returns the smallest rect `[x, y, width, height]` containing all pixels that differ between two single
+/// channel images with `width` pixels per row, or `None` if they are equal.
+fn dirty_rect(old: &[u8], new: &[u8], width: usize) -> Option<[usize; 4]> {
+    if width == 0 {
+        return None;
+    }
+    let mut bounds: Option<[usize; 4]> = None;
+    for (y, (old_row, new_row)) in old
+        .chunks_exact(width)
+        .zip(new.chunks_exact(width))
+        .enumerate()
+    {
+        let differs = |(old, new): (&u8, &u8)| old != new;
+        let first = match old_row.iter().zip(new_row).position(differs) {
+            Some(first) => first,
+            None => continue,
+        };
+        let last = old_row.iter().zip(new_row).rposition(differs).unwrap();
+        let [min_x, min_y, max_x, _] = bounds.unwrap_or([first, y, last, y]);
+        bounds = Some([min_x.min(first), min_y, max_x.max(last), y]);
+    }
+    bounds.map(|[min_x, min_y, max_x, max_y]| [min_x, min_y, max_x - min_x + 1, max_y - min_y + 1])
+}
+
+/// Writes the pixels of `egui_texture` inside `rect` into the same region of `texture`, converted to sRGBA
+/// unless the texture has a single channel.
+fn write_egui_texture_region(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    egui_texture: &egui::Texture,
+    [x, y, width, height]: [usize; 4],
+    single_channel: bool,
+) {
+    let bytes_per_pixel = if single_channel { 1 } else { 4 };
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height * bytes_per_pixel);
+    for row in egui_texture.pixels[y * egui_texture.width..]
+        .chunks_exact(egui_texture.width)
+        .take(height)
+    {
+        let row = &row[x..x + width];
+        if single_channel {
+            pixels.extend_from_slice(row);
+        } else {
+            for &alpha in row {
+                pixels.extend_from_slice(&egui::Color32::from_white_alpha(alpha).to_array());
+            }
+        }
+    }
+    queue.write_texture(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: x as u32,
+                y: y as u32,
+                z: 0,
+            },
+        },
+        &pixels,
+        wgpu::TextureDataLayout {
+            offset: 0,
+            bytes_per_row: (width * bytes_per_pixel) as u32,
+            rows_per_image: height as u32,
+        },
+        wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth: 1,
+        },
+    );
+}
+
+/// Converts a `f32` into the bits of a half precision float, rounding to the nearest value.
+fn f16_from_f32(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal half, or zero if the value is too small.
+        if exponent < -10 {
+            return sign;
         }
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift) as u16;
     }
+    // A carry of the rounding correctly moves into the exponent.
+    let half = (((exponent as u32) << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1);
+    sign | half as u16
+}
+
+/// User texture ids hold the slot in `RenderPass::user_textures` in their lower 32 bits and the generation
+/// of the slot in the 31 bits above, so ids of freed textures don't show the texture reusing their slot.
+fn user_texture_slot(id: u64) -> usize {
+    (id & 0xffff_ffff) as usize
+}
+
+/// Marks the ids of the textures registered with a `RenderContext`, whose slots are in the table of the context.
+const SHARED_TEXTURE_FLAG: u64 = 1 << 63;
+
+/// Returns the id of the next texture using the slot of `id`, without `SHARED_TEXTURE_FLAG`.
+fn next_generation(id: u64) -> u64 {
+    id.wrapping_add(1 << 32) & !SHARED_TEXTURE_FLAG
 }
 
 // Needed since we can't use bytemuck for external types.
 fn as_byte_slice<T>(slice: &[T]) -> &[u8] {
-    let len = slice.len() * std::mem::size_of::<T>();
+    let len = std::mem::size_of_val(slice);
     let ptr = slice.as_ptr() as *const u8;
     unsafe { std::slice::from_raw_parts(ptr, len) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dirty_rect, egui, f16_from_f32, merge_custom_geometry, BackendError, RenderContext,
+        RenderPass, RenderPassConfig, ScreenDescriptor, ScreenViewport, SurfaceRotation,
+    };
+
+    /// Returns a device of the first adapter, or `None` without a GPU, e.g. on CI.
+    fn request_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+        Some(pollster::block_on(adapter.request_device(&Default::default(), None)).unwrap())
+    }
+
+    fn clipped_mesh(x: f32) -> egui::ClippedMesh {
+        let clip_rect = egui::Rect::from_min_max(egui::pos2(x, 0.0), egui::pos2(x + 1.0, 1.0));
+        egui::ClippedMesh(clip_rect, egui::paint::Mesh::default())
+    }
+
+    #[test]
+    fn dirty_rect_bounds_the_changed_pixels() {
+        let old = [0u8; 16];
+        assert_eq!(dirty_rect(&old, &old, 4), None);
+        assert_eq!(dirty_rect(&old, &old, 0), None);
+
+        let mut new = old;
+        new[2 * 4 + 1] = 1;
+        assert_eq!(dirty_rect(&old, &new, 4), Some([1, 2, 1, 1]));
+        new[3] = 1;
+        new[3 * 4 + 2] = 1;
+        assert_eq!(dirty_rect(&old, &new, 4), Some([1, 0, 3, 4]));
+    }
+
+    #[test]
+    fn f16_from_f32_rounds_to_the_nearest_half() {
+        assert_eq!(f16_from_f32(0.0), 0x0000);
+        assert_eq!(f16_from_f32(-0.0), 0x8000);
+        assert_eq!(f16_from_f32(1.0), 0x3c00);
+        assert_eq!(f16_from_f32(-2.0), 0xc000);
+        assert_eq!(f16_from_f32(1.0 + 1.0 / 1024.0), 0x3c01);
+        assert_eq!(f16_from_f32(65504.0), 0x7bff);
+        // The rounding carries into the exponent.
+        assert_eq!(f16_from_f32(2047.9), 0x6800);
+        assert_eq!(f16_from_f32(2f32.powi(-24)), 0x0001);
+        assert_eq!(f16_from_f32(2f32.powi(-26)), 0x0000);
+        assert_eq!(f16_from_f32(1.0e6), 0x7c00);
+        assert_eq!(f16_from_f32(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(f16_from_f32(f32::NAN), 0x7e00);
+    }
+
+    #[test]
+    fn merge_custom_geometry_inserts_before_the_paint_jobs() {
+        let paint_jobs: Vec<_> = (0..3).map(|i| clipped_mesh(i as f32)).collect();
+        let geometry: Vec<_> = [0, 2, 5]
+            .iter()
+            .map(|&position| (position, clipped_mesh(10.0 + position as f32)))
+            .collect();
+        let meshes = merge_custom_geometry(&paint_jobs, &geometry);
+
+        let paint_job_indices: Vec<_> = meshes.iter().map(|(index, _)| *index).collect();
+        assert_eq!(
+            paint_job_indices,
+            [None, Some(0), Some(1), None, Some(2), None]
+        );
+        let expected = [
+            &geometry[0].1,
+            &paint_jobs[0],
+            &paint_jobs[1],
+            &geometry[1].1,
+            &paint_jobs[2],
+            &geometry[2].1,
+        ];
+        for ((_, mesh), expected) in meshes.iter().zip(expected.iter()) {
+            assert!(std::ptr::eq(*mesh, *expected));
+        }
+        assert!(merge_custom_geometry(&[], &[]).is_empty());
+    }
+
+    /// wgpu 0.7 parses WGSL with naga 0.3, which only knows the syntax of its time.
+    #[test]
+    fn wgsl_shaders_parse_with_the_naga_of_wgpu() {
+        let shaders = [
+            ("blur.vert", include_str!("shader/blur.vert.wgsl")),
+            ("blur.frag", include_str!("shader/blur.frag.wgsl")),
+            ("clear.frag", include_str!("shader/clear.frag.wgsl")),
+            ("egui.vert", include_str!("shader/egui.vert.wgsl")),
+            ("egui.frag", include_str!("shader/egui.frag.wgsl")),
+            ("egui_aux.vert", include_str!("shader/egui_aux.vert.wgsl")),
+            (
+                "egui_picking.vert",
+                include_str!("shader/egui_picking.vert.wgsl"),
+            ),
+            (
+                "egui_picking.frag",
+                include_str!("shader/egui_picking.frag.wgsl"),
+            ),
+            (
+                "egui_shader_clip.frag",
+                include_str!("shader/egui_shader_clip.frag.wgsl"),
+            ),
+        ];
+        for (name, source) in shaders.iter() {
+            let module = naga::front::wgsl::parse_str(source)
+                .unwrap_or_else(|err| panic!("{} doesn't parse: {:?}", name, err));
+            naga::proc::Validator::new()
+                .validate(&module)
+                .unwrap_or_else(|err| panic!("{} is invalid: {:?}", name, err));
+        }
+    }
+
+    #[test]
+    fn malformed_meshes_are_not_drawn() {
+        let (device, queue) = match request_device() {
+            Some(device) => device,
+            None => return,
+        };
+        let config = RenderPassConfig {
+            validate_meshes: true,
+            ..RenderPassConfig::default()
+        };
+        let mut render_pass =
+            RenderPass::with_config(&device, wgpu::TextureFormat::Bgra8UnormSrgb, &config).unwrap();
+
+        let mut malformed = clipped_mesh(0.0);
+        malformed.1.indices = vec![0, 1, 2];
+        let mut valid = clipped_mesh(1.0);
+        for pos in [(1.0, 0.0), (2.0, 0.0), (1.0, 1.0)].iter() {
+            valid
+                .1
+                .colored_vertex(egui::pos2(pos.0, pos.1), egui::Color32::WHITE);
+        }
+        valid.1.indices = vec![0, 1, 2];
+        let paint_jobs = [malformed, valid];
+        let screen_descriptor = ScreenDescriptor {
+            physical_width: 4,
+            physical_height: 4,
+            scale_factor: 1.0,
+            viewport: None::<ScreenViewport>,
+            rotation: SurfaceRotation::Identity,
+        };
+
+        let result = render_pass.update_buffers(&device, &queue, &paint_jobs, &screen_descriptor);
+        assert!(matches!(
+            result,
+            Err(BackendError::MalformedMesh { index: 0, .. })
+        ));
+        let mesh_indices: Vec<usize> = render_pass
+            .draw_commands(&paint_jobs, &screen_descriptor)
+            .map(|command| command.mesh_index)
+            .collect();
+        assert_eq!(mesh_indices, vec![1]);
+    }
+
+    #[test]
+    fn shared_textures_are_drawn_by_every_render_pass_of_the_context() {
+        let (device, queue) = match request_device() {
+            Some(device) => device,
+            None => return,
+        };
+        let context = RenderContext::new(&device, &RenderPassConfig::default());
+        let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        let mut windows = [
+            RenderPass::with_context(&device, &context, format).unwrap(),
+            RenderPass::with_context(&device, &context, format).unwrap(),
+        ];
+
+        let id = context
+            .register_shared_texture(&device, &queue, (1, 1), &[egui::Color32::RED])
+            .unwrap();
+        for render_pass in windows.iter_mut() {
+            assert!(render_pass.texture_view(id).is_none());
+            render_pass.update_user_textures(&device, &queue).unwrap();
+            assert!(render_pass.texture_view(id).is_some());
+        }
+
+        // Freeing keeps the texture of the snapshots until the next update.
+        context.free_shared_texture(id);
+        assert!(windows[1].texture_view(id).is_some());
+        windows[0].update_user_textures(&device, &queue).unwrap();
+        assert!(windows[0].texture_view(id).is_none());
+        assert!(windows[1].texture_view(id).is_some());
+    }
+}
@@ -0,0 +1,104 @@
+//! Rendering the UI into a texture of its own, e.g. for screens inside of a 3D scene.
+
+use crate::{egui, BackendError, RenderPass, ScreenDescriptor};
+
+impl RenderPass {
+    /// Renders `paint_jobs` into a new texture of the size of `screen_descriptor`, cleared to `clear_color`,
+    /// and returns it once the commands are submitted to `queue`. The texture has the output format of the
+    /// render pass and can be sampled, e.g. on a quad in a 3D scene, or copied with [`crate::read_pixels`].
+    ///
+    /// Uploads the user textures and buffers itself, the egui texture needs to be updated with
+    /// `update_texture()` before. Creates the multisampled and depth-stencil attachments the configuration
+    /// needs for the call, so an application drawing into the same texture every frame is better off creating
+    /// it once and calling `execute()` with its view.
+    pub fn render_to_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: wgpu::Color,
+    ) -> Result<wgpu::Texture, BackendError> {
+        let size = wgpu::Extent3d {
+            width: screen_descriptor.physical_width,
+            height: screen_descriptor.physical_height,
+            depth: 1,
+        };
+        let create_texture = |label, sample_count, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+            })
+        };
+        let texture = create_texture(
+            "egui_offscreen_texture",
+            1,
+            self.output_format,
+            wgpu::TextureUsage::RENDER_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let multisampled = if self.sample_count > 1 {
+            let texture = create_texture(
+                "egui_offscreen_multisampled_texture",
+                self.sample_count,
+                self.output_format,
+                wgpu::TextureUsage::RENDER_ATTACHMENT,
+            );
+            Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        } else {
+            None
+        };
+        let (color_attachment, resolve_target) = match &multisampled {
+            Some(multisampled) => (multisampled, Some(&view)),
+            None => (&view, None),
+        };
+
+        self.update_user_textures(device, queue)?;
+        self.update_buffers(device, queue, paint_jobs, screen_descriptor)?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("egui_offscreen_encoder"),
+        });
+        match self.config.depth_stencil_format {
+            Some(format) => {
+                let depth_stencil = create_texture(
+                    "egui_offscreen_depth_stencil_texture",
+                    self.sample_count,
+                    format,
+                    wgpu::TextureUsage::RENDER_ATTACHMENT,
+                );
+                let depth_stencil_view =
+                    depth_stencil.create_view(&wgpu::TextureViewDescriptor::default());
+                self.execute_with_depth_stencil(
+                    &mut encoder,
+                    color_attachment,
+                    resolve_target,
+                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                        attachment: &depth_stencil_view,
+                        depth_ops: None,
+                        stencil_ops: None,
+                    },
+                    paint_jobs,
+                    screen_descriptor,
+                    Some(clear_color),
+                )?;
+            }
+            None => self.execute(
+                &mut encoder,
+                color_attachment,
+                resolve_target,
+                paint_jobs,
+                screen_descriptor,
+                Some(clear_color),
+            )?,
+        }
+        queue.submit(Some(encoder.finish()));
+        Ok(texture)
+    }
+}
@@ -0,0 +1,158 @@
+//! Detection of meshes hidden behind opaque rectangles drawn later, e.g. the content behind a modal panel.
+
+use crate::egui;
+
+/// Returns for every mesh whether it is completely covered by an opaque rectangle of a later mesh.
+///
+/// Only rectangles egui fills without rounding are found, as two triangles with a solid color that split
+/// the rectangle along a diagonal. `expansion` is the scissor expansion in points, which widens the visible
+/// part of a mesh beyond its clip rect.
+pub(crate) fn covered_meshes<'a>(
+    meshes: impl DoubleEndedIterator<Item = &'a egui::paint::ClippedMesh>,
+    expansion: f32,
+) -> Vec<bool> {
+    let mut occluders: Vec<egui::Rect> = Vec::new();
+    let mut covered: Vec<bool> = meshes
+        .rev()
+        .map(|egui::ClippedMesh(clip_rect, mesh)| {
+            let visible = mesh_bounds(mesh).intersect(clip_rect.expand(expansion));
+            let is_covered = has_area(visible)
+                && occluders.iter().any(|occluder| {
+                    occluder.contains(visible.min) && occluder.contains(visible.max)
+                });
+            if !is_covered {
+                occluders.extend(
+                    opaque_rects(mesh)
+                        .map(|rect| rect.intersect(*clip_rect))
+                        .filter(|rect| has_area(*rect)),
+                );
+            }
+            is_covered
+        })
+        .collect();
+    covered.reverse();
+    covered
+}
+
+fn has_area(rect: egui::Rect) -> bool {
+    rect.width() > 0.0 && rect.height() > 0.0
+}
+
+fn mesh_bounds(mesh: &egui::paint::Mesh) -> egui::Rect {
+    let mut bounds = egui::Rect::NOTHING;
+    for vertex in &mesh.vertices {
+        bounds.extend_with(vertex.pos);
+    }
+    bounds
+}
+
+/// Returns the opaque rectangles of a mesh drawn with the egui texture, found in consecutive triangles.
+fn opaque_rects(mesh: &egui::paint::Mesh) -> impl Iterator<Item = egui::Rect> + '_ {
+    let triangles = if mesh.texture_id == egui::TextureId::Egui {
+        mesh.indices.chunks_exact(3).collect()
+    } else {
+        Vec::new()
+    };
+    (1..triangles.len()).filter_map(move |i| {
+        let (first, missing_first) = opaque_corner_triangle(mesh, triangles[i - 1])?;
+        let (second, missing_second) = opaque_corner_triangle(mesh, triangles[i])?;
+        // Each triangle covers the half of the rectangle opposite to the corner it leaves out.
+        if first == second && missing_first ^ missing_second == 3 {
+            Some(first)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the bounding box of an opaque, solid colored triangle whose vertices are corners of it, and the
+/// index of the corner it leaves out, with bit 0 for the right and bit 1 for the bottom edge.
+fn opaque_corner_triangle(mesh: &egui::paint::Mesh, triangle: &[u32]) -> Option<(egui::Rect, u32)> {
+    let mut vertices = [egui::pos2(0.0, 0.0); 3];
+    for (vertex, &index) in vertices.iter_mut().zip(triangle) {
+        let source = mesh.vertices.get(index as usize)?;
+        if source.color.a() != 255 || source.uv != egui::paint::WHITE_UV {
+            return None;
+        }
+        *vertex = source.pos;
+    }
+    let mut rect = egui::Rect::NOTHING;
+    for vertex in &vertices {
+        rect.extend_with(*vertex);
+    }
+    if !has_area(rect) {
+        return None;
+    }
+
+    let mut corners = 0u32;
+    for vertex in &vertices {
+        let right = if vertex.x == rect.min.x {
+            0
+        } else if vertex.x == rect.max.x {
+            1
+        } else {
+            return None;
+        };
+        let bottom = if vertex.y == rect.min.y {
+            0
+        } else if vertex.y == rect.max.y {
+            2
+        } else {
+            return None;
+        };
+        corners |= 1 << (right | bottom);
+    }
+    if corners.count_ones() != 3 {
+        return None;
+    }
+    Some((rect, (!corners & 0xf).trailing_zeros()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::covered_meshes;
+    use crate::egui;
+
+    fn rect(min: f32, max: f32) -> egui::Rect {
+        egui::Rect::from_min_max(egui::pos2(min, min), egui::pos2(max, max))
+    }
+
+    fn filled(rect: egui::Rect, color: egui::Color32) -> egui::ClippedMesh {
+        let mut mesh = egui::paint::Mesh::default();
+        mesh.add_colored_rect(rect, color);
+        egui::ClippedMesh(egui::Rect::EVERYTHING, mesh)
+    }
+
+    #[test]
+    fn covered_meshes_finds_meshes_behind_opaque_rects() {
+        let translucent = egui::Color32::from_rgba_premultiplied(0, 0, 0, 128);
+        let meshes = [
+            filled(rect(0.0, 10.0), egui::Color32::RED),
+            filled(rect(15.0, 30.0), egui::Color32::RED),
+            filled(rect(0.0, 20.0), egui::Color32::BLACK),
+            filled(rect(0.0, 40.0), translucent),
+        ];
+        assert_eq!(
+            covered_meshes(meshes.iter(), 0.0),
+            [true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn covered_meshes_uses_the_expanded_clip_rect() {
+        let mut clipped = filled(rect(0.0, 30.0), egui::Color32::RED);
+        clipped.0 = rect(0.0, 10.0);
+        let meshes = [clipped, filled(rect(0.0, 20.0), egui::Color32::BLACK)];
+        assert_eq!(covered_meshes(meshes.iter(), 0.0), [true, false]);
+        // The expanded clip rect of the first mesh reaches past the occluder.
+        assert_eq!(covered_meshes(meshes.iter(), 15.0), [false, false]);
+    }
+
+    #[test]
+    fn covered_meshes_ignores_user_textures() {
+        let mut image = filled(rect(0.0, 20.0), egui::Color32::WHITE);
+        image.1.texture_id = egui::TextureId::User(0);
+        let meshes = [filled(rect(0.0, 10.0), egui::Color32::RED), image];
+        assert_eq!(covered_meshes(meshes.iter(), 0.0), [false, false]);
+    }
+}
@@ -0,0 +1,243 @@
+//! A wrapper with the method names of egui's GL painter, to ease porting applications between the backends.
+
+use std::sync::Arc;
+
+use crate::{egui, BackendError, RenderPass, ScreenDescriptor, SurfaceRotation};
+
+/// The parts of a painter that don't depend on the graphics API. The trait is object safe, so applications can
+/// pick this backend or another one at runtime, e.g. when no compatible adapter exists, and keep a
+/// `Box<dyn EguiPainter>`.
+pub trait EguiPainter {
+    /// Creates or replaces the user texture `id` with `size` premultiplied sRGBA pixels.
+    fn set_texture(
+        &mut self,
+        id: egui::TextureId,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+    );
+
+    /// Frees the user texture `id`.
+    fn free_texture(&mut self, id: egui::TextureId);
+
+    /// Paints `clipped_meshes` into a target of `dimensions` physical pixels and presents it.
+    fn paint(
+        &mut self,
+        dimensions: [u32; 2],
+        pixels_per_point: f32,
+        clipped_meshes: &[egui::paint::ClippedMesh],
+        egui_texture: &egui::Texture,
+    );
+}
+
+/// Paints egui output with the method names and semantics of egui's GL painter.
+///
+/// Unlike the GL painter, wgpu needs the device, queue and an encoder to record into, so they are passed to
+/// the methods that touch the GPU. Use [`Painter::render_pass`] for everything else this backend offers.
+pub struct Painter {
+    render_pass: RenderPass,
+}
+
+impl Painter {
+    /// Creates a painter that renders into targets of `output_format`.
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+    ) -> Result<Self, BackendError> {
+        Ok(Self {
+            render_pass: RenderPass::new(device, output_format, 1)?,
+        })
+    }
+
+    /// Returns the wrapped render pass.
+    pub fn render_pass(&mut self) -> &mut RenderPass {
+        &mut self.render_pass
+    }
+
+    /// Uploads the egui texture and the textures set since the last call, then paints `clipped_meshes`
+    /// into `color_attachment` of `dimensions` physical pixels, without clearing it first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint_and_update_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        dimensions: [u32; 2],
+        pixels_per_point: f32,
+        clipped_meshes: &[egui::paint::ClippedMesh],
+        egui_texture: &egui::Texture,
+    ) -> Result<(), BackendError> {
+        let screen_descriptor = ScreenDescriptor {
+            physical_width: dimensions[0],
+            physical_height: dimensions[1],
+            scale_factor: pixels_per_point,
+            viewport: None,
+            rotation: SurfaceRotation::Identity,
+        };
+        self.render_pass.update_texture(device, queue, egui_texture);
+        self.render_pass.update_user_textures(device, queue)?;
+        self.render_pass
+            .update_buffers(device, queue, clipped_meshes, &screen_descriptor)?;
+        self.render_pass.execute(
+            encoder,
+            color_attachment,
+            None,
+            clipped_meshes,
+            &screen_descriptor,
+            None,
+        )
+    }
+
+    /// Creates or replaces the user texture `id` with `size` premultiplied sRGBA pixels. The pixels are
+    /// uploaded by the next `paint_and_update_textures()`, a replaced texture is drawn until then.
+    /// Replaced textures keep their sampler and parameters.
+    ///
+    /// The egui texture is passed to `paint_and_update_textures()`, so `TextureId::Egui` is ignored. Returns
+    /// `BackendError::StaleUserTexture` if the texture was freed and its slot reused and
+    /// `BackendError::InvalidPixelData` if `srgba_pixels` doesn't hold `size.0 * size.1` pixels.
+    pub fn set_texture(
+        &mut self,
+        id: egui::TextureId,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<(), BackendError> {
+        let id = match id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Egui => return Ok(()),
+        };
+        self.render_pass.set_srgba_texture(id, size, srgba_pixels)
+    }
+
+    /// Frees the user texture `id`.
+    pub fn free_texture(&mut self, id: egui::TextureId) {
+        epi::TextureAllocator::free(&mut self.render_pass, id);
+    }
+}
+
+/// Paints into the swap chain of a surface, so it can be used as a [`EguiPainter`] trait object.
+///
+/// The device and queue are shared with the application. The swap chain is created again when the dimensions
+/// passed to `paint()` change.
+pub struct SurfacePainter {
+    painter: Painter,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    surface: wgpu::Surface,
+    format: wgpu::TextureFormat,
+    swap_chain: Option<(wgpu::SwapChain, [u32; 2])>,
+    clear_color: wgpu::Color,
+}
+
+impl SurfacePainter {
+    /// Creates a painter for `surface`, whose swap chain uses `format`.
+    pub fn new(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        surface: wgpu::Surface,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self, BackendError> {
+        Ok(Self {
+            painter: Painter::new(&device, format)?,
+            device,
+            queue,
+            surface,
+            format,
+            swap_chain: None,
+            clear_color: wgpu::Color::BLACK,
+        })
+    }
+
+    /// Sets the color the surface is cleared with before painting. Defaults to black.
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    /// Returns the wrapped render pass.
+    pub fn render_pass(&mut self) -> &mut RenderPass {
+        self.painter.render_pass()
+    }
+}
+
+impl EguiPainter for SurfacePainter {
+    fn set_texture(
+        &mut self,
+        id: egui::TextureId,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+    ) {
+        // Like the texture commands, updates of stale ids are dropped.
+        self.painter.set_texture(id, size, srgba_pixels).ok();
+    }
+
+    fn free_texture(&mut self, id: egui::TextureId) {
+        self.painter.free_texture(id);
+    }
+
+    fn paint(
+        &mut self,
+        dimensions: [u32; 2],
+        pixels_per_point: f32,
+        clipped_meshes: &[egui::paint::ClippedMesh],
+        egui_texture: &egui::Texture,
+    ) {
+        if dimensions[0] == 0 || dimensions[1] == 0 {
+            return;
+        }
+        if !matches!(&self.swap_chain, Some((_, size)) if *size == dimensions) {
+            let swap_chain = self.device.create_swap_chain(
+                &self.surface,
+                &wgpu::SwapChainDescriptor {
+                    usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+                    format: self.format,
+                    width: dimensions[0],
+                    height: dimensions[1],
+                    present_mode: wgpu::PresentMode::Fifo,
+                },
+            );
+            self.swap_chain = Some((swap_chain, dimensions));
+        }
+
+        let frame = match self.swap_chain.as_ref().unwrap().0.get_current_frame() {
+            Ok(frame) => frame,
+            Err(wgpu::SwapChainError::Outdated) | Err(wgpu::SwapChainError::Lost) => {
+                self.swap_chain = None;
+                return;
+            }
+            Err(_) => return,
+        };
+
+        let (device, queue) = (&*self.device, &*self.queue);
+        let screen_descriptor = ScreenDescriptor {
+            physical_width: dimensions[0],
+            physical_height: dimensions[1],
+            scale_factor: pixels_per_point,
+            viewport: None,
+            rotation: SurfaceRotation::Identity,
+        };
+        let clear_color = self.clear_color;
+        let render_pass = self.painter.render_pass();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("egui_surface_encoder"),
+        });
+        render_pass.update_texture(device, queue, egui_texture);
+        let result = render_pass
+            .update_user_textures(device, queue)
+            .and_then(|()| {
+                render_pass.update_buffers(device, queue, clipped_meshes, &screen_descriptor)
+            })
+            .and_then(|()| {
+                render_pass.execute(
+                    &mut encoder,
+                    &frame.output.view,
+                    None,
+                    clipped_meshes,
+                    &screen_descriptor,
+                    Some(clear_color),
+                )
+            });
+        // Like a lost swap chain, an error only skips the frame.
+        if result.is_ok() {
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+}
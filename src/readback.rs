@@ -0,0 +1,302 @@
+//! Reading the pixels of a texture back to the CPU, e.g. to take screenshots of the UI.
+
+use std::future::Future;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::RenderPass;
+
+/// Copies the pixels of the first mip level of `texture` into a buffer and returns a future that resolves to
+/// them once the GPU finished the copy. `texture` needs to be a 2D texture of `size` pixels with `format`
+/// and `wgpu::TextureUsage::COPY_SRC`. The pixels are returned row by row without padding.
+///
+/// The copy is submitted right away. On native backends the future only makes progress while the device is
+/// polled, e.g. with `device.poll(wgpu::Maintain::Poll)` once per frame, so it doesn't need to block.
+/// On the web it resolves on its own.
+pub fn read_pixels_async(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    size: (u32, u32),
+    format: wgpu::TextureFormat,
+) -> impl Future<Output = Vec<u8>> {
+    let (width, height) = size;
+    let bytes_per_pixel = format.describe().block_size as u32;
+    let row_size = width * bytes_per_pixel;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row_size = (row_size + alignment - 1) / alignment * alignment;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("egui_readback_buffer"),
+        size: (padded_row_size * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("egui_readback_encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            layout: wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: padded_row_size,
+                rows_per_image: height,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let mapping = buffer.slice(..).map_async(wgpu::MapMode::Read);
+    async move {
+        mapping.await.expect("failed to map the readback buffer");
+        let data = buffer.slice(..).get_mapped_range();
+        let pixels = data
+            .chunks_exact(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect();
+        drop(data);
+        buffer.unmap();
+        pixels
+    }
+}
+
+/// Blocking version of [`read_pixels_async`], which waits for the GPU to finish all submitted work.
+/// Panics on the web, where the device can't be waited for.
+pub fn read_pixels(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    size: (u32, u32),
+    format: wgpu::TextureFormat,
+) -> Vec<u8> {
+    let mut future = Box::pin(read_pixels_async(device, queue, texture, size, format));
+    device.poll(wgpu::Maintain::Wait);
+    // The mapping is done after waiting, so the future is ready on the first poll.
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(&noop_waker()))
+    {
+        Poll::Ready(pixels) => pixels,
+        Poll::Pending => panic!("readback buffer was not mapped after waiting for the device"),
+    }
+}
+
+/// Returns a waker that does nothing, for futures that are polled again anyway, e.g. once per frame.
+pub(crate) fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // Safe since the functions of the vtable don't touch the data pointer.
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+impl RenderPass {
+    /// Reads back a render target of the render pass, e.g. to save a screenshot of the UI. Like
+    /// [`read_pixels_async`] with the output format of the render pass. `texture` is the resolve target
+    /// with multisampling and needs `wgpu::TextureUsage::COPY_SRC`.
+    pub fn read_back_async(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        size: (u32, u32),
+    ) -> impl Future<Output = Vec<u8>> {
+        read_pixels_async(device, queue, texture, size, self.output_format)
+    }
+
+    /// Blocking version of [`RenderPass::read_back_async`], see [`read_pixels`].
+    pub fn read_back(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        size: (u32, u32),
+    ) -> Vec<u8> {
+        read_pixels(device, queue, texture, size, self.output_format)
+    }
+}
+
+/// Converts pixels returned by [`read_pixels`] from a texture of `format` into sRGB encoded RGBA with 8 bits
+/// per channel, e.g. to save a screenshot that matches what is shown on screen.
+///
+/// sRGB formats store encoded values, while the values of the other formats are encoded here, unless
+/// `encoded_in_shader` is set. Set it for `Rgba8Unorm` and `Bgra8Unorm` targets of a render pass that encodes
+/// in the shader, see `ShaderConstants::srgb_encode`. Alpha is never encoded.
+///
+/// Supports the 8 bit RGBA and BGRA formats, `Rgb10a2Unorm`, `Rgba16Float` and `Rgba32Float`.
+/// Panics for other formats.
+pub fn srgba8_from_pixels(
+    pixels: &[u8],
+    format: wgpu::TextureFormat,
+    encoded_in_shader: bool,
+) -> Vec<u8> {
+    use wgpu::TextureFormat;
+
+    let encode = srgb_from_linear;
+    let unorm = |value: u8| {
+        if encoded_in_shader {
+            value
+        } else {
+            encode(value as f32 / 255.0)
+        }
+    };
+    let alpha = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    match format {
+        TextureFormat::Rgba8UnormSrgb => pixels.to_vec(),
+        TextureFormat::Bgra8UnormSrgb => convert(pixels, 4, |p| [p[2], p[1], p[0], p[3]]),
+        TextureFormat::Rgba8Unorm => {
+            convert(pixels, 4, |p| [unorm(p[0]), unorm(p[1]), unorm(p[2]), p[3]])
+        }
+        TextureFormat::Bgra8Unorm => {
+            convert(pixels, 4, |p| [unorm(p[2]), unorm(p[1]), unorm(p[0]), p[3]])
+        }
+        TextureFormat::Rgb10a2Unorm => convert(pixels, 4, |p| {
+            let packed = u32::from_le_bytes([p[0], p[1], p[2], p[3]]);
+            let channel = |shift: u32| ((packed >> shift) & 0x3ff) as f32 / 1023.0;
+            [
+                encode(channel(0)),
+                encode(channel(10)),
+                encode(channel(20)),
+                alpha((packed >> 30) as f32 / 3.0),
+            ]
+        }),
+        TextureFormat::Rgba16Float => convert(pixels, 8, |p| {
+            let channel = |i: usize| f32_from_f16(u16::from_le_bytes([p[2 * i], p[2 * i + 1]]));
+            [
+                encode(channel(0)),
+                encode(channel(1)),
+                encode(channel(2)),
+                alpha(channel(3)),
+            ]
+        }),
+        TextureFormat::Rgba32Float => convert(pixels, 16, |p| {
+            let channel =
+                |i: usize| f32::from_le_bytes([p[4 * i], p[4 * i + 1], p[4 * i + 2], p[4 * i + 3]]);
+            [
+                encode(channel(0)),
+                encode(channel(1)),
+                encode(channel(2)),
+                alpha(channel(3)),
+            ]
+        }),
+        _ => panic!("can't convert pixels of {:?} to sRGB", format),
+    }
+}
+
+/// Converts every pixel of `bytes_per_pixel` bytes into sRGBA with `pixel`.
+fn convert(pixels: &[u8], bytes_per_pixel: usize, pixel: impl Fn(&[u8]) -> [u8; 4]) -> Vec<u8> {
+    let mut srgba = Vec::with_capacity(pixels.len() / bytes_per_pixel * 4);
+    for p in pixels.chunks_exact(bytes_per_pixel) {
+        srgba.extend_from_slice(&pixel(p));
+    }
+    srgba
+}
+
+/// Encodes a linear value to sRGB, clamping it to `[0, 1]`.
+fn srgb_from_linear(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+/// Converts a half precision float into a `f32`.
+fn f32_from_f16(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{f32_from_f16, srgba8_from_pixels};
+
+    #[test]
+    fn f32_from_f16_decodes_the_special_values() {
+        assert_eq!(f32_from_f16(0x3c00), 1.0);
+        assert_eq!(f32_from_f16(0xc000), -2.0);
+        assert_eq!(f32_from_f16(0x0001), 2f32.powi(-24));
+        assert_eq!(f32_from_f16(0x7c00), f32::INFINITY);
+        assert_eq!(f32_from_f16(0xfc00), f32::NEG_INFINITY);
+        assert!(f32_from_f16(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn f32_from_f16_round_trips_every_finite_half() {
+        for half in (0..0x7c00).chain(0x8000..0xfc00) {
+            assert_eq!(crate::f16_from_f32(f32_from_f16(half)), half);
+        }
+    }
+
+    #[test]
+    fn srgba8_from_pixels_encodes_linear_formats() {
+        use wgpu::TextureFormat;
+
+        let pixels = [10, 20, 30, 40];
+        assert_eq!(
+            srgba8_from_pixels(&pixels, TextureFormat::Rgba8UnormSrgb, false),
+            pixels
+        );
+        assert_eq!(
+            srgba8_from_pixels(&pixels, TextureFormat::Bgra8UnormSrgb, false),
+            [30, 20, 10, 40]
+        );
+        assert_eq!(
+            srgba8_from_pixels(&pixels, TextureFormat::Bgra8Unorm, true),
+            [30, 20, 10, 40]
+        );
+        // Linear 0.5 is 188 in sRGB, alpha stays linear.
+        assert_eq!(
+            srgba8_from_pixels(&[0, 128, 255, 128], TextureFormat::Rgba8Unorm, false),
+            [0, 188, 255, 128]
+        );
+
+        let packed: u32 = 1023 | 3 << 30;
+        assert_eq!(
+            srgba8_from_pixels(&packed.to_le_bytes(), TextureFormat::Rgb10a2Unorm, false),
+            [255, 0, 0, 255]
+        );
+        let halves: Vec<u8> = [0x3c00u16, 0x3800, 0x0000, 0x3c00]
+            .iter()
+            .flat_map(|half| half.to_le_bytes().to_vec())
+            .collect();
+        assert_eq!(
+            srgba8_from_pixels(&halves, TextureFormat::Rgba16Float, false),
+            [255, 188, 0, 255]
+        );
+        // Values out of range are clamped.
+        let floats: Vec<u8> = [2.0f32, -1.0, 0.5, 2.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        assert_eq!(
+            srgba8_from_pixels(&floats, TextureFormat::Rgba32Float, false),
+            [255, 0, 188, 255]
+        );
+    }
+}
@@ -0,0 +1,29 @@
+// glslc -g -x glsl -O blur.frag -o blur.frag.spirv
+// The WGSL build is ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
+
+#version 450
+
+layout(location = 0) in vec2 v_tex_coord;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform texture2D t_texture;
+layout(set = 0, binding = 1) uniform sampler s_texture;
+layout(set = 0, binding = 2) uniform BlurUniformBuffer {
+    // Offset between two taps in texture coordinates.
+    vec2 u_step;
+};
+
+const int TAPS = 8;
+
+void main() {
+    // Gaussian weights with a standard deviation of half the tap count.
+    float sigma = float(TAPS) * 0.5;
+    vec4 sum = vec4(0.0);
+    float weight_sum = 0.0;
+    for (int i = -TAPS; i <= TAPS; i++) {
+        float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+        sum += weight * texture(sampler2D(t_texture, s_texture), v_tex_coord + float(i) * u_step);
+        weight_sum += weight;
+    }
+    f_color = sum / weight_sum;
+}
@@ -0,0 +1,44 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from blur.frag. Keep in sync with the GLSL source.
+
+[[location(0)]]
+var<in> v_tex_coord: vec2<f32>;
+[[location(0)]]
+var<out> f_color: vec4<f32>;
+
+[[group(0), binding(0)]]
+var t_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_texture: sampler;
+
+[[block]]
+struct BlurUniforms {
+    // Offset between two taps in texture coordinates.
+    step: vec2<f32>;
+};
+
+[[group(0), binding(2)]]
+var<uniform> blur_uniforms: BlurUniforms;
+
+const TAPS: i32 = 8;
+
+[[stage(fragment)]]
+fn main() {
+    // Gaussian weights with a standard deviation of half the tap count.
+    const sigma: f32 = f32(TAPS) * 0.5;
+    var sum: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    var weight_sum: f32 = 0.0;
+    var i: i32 = -TAPS;
+    loop {
+        if (i > TAPS) {
+            break;
+        }
+        const weight: f32 = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        sum = sum + weight * textureSample(t_texture, s_texture, v_tex_coord + f32(i) * blur_uniforms.step);
+        weight_sum = weight_sum + weight;
+        continuing {
+            i = i + 1;
+        }
+    }
+    f_color = sum * (1.0 / weight_sum);
+}
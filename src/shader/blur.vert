@@ -0,0 +1,13 @@
+// glslc -g -x glsl -O blur.vert -o blur.vert.spirv
+// The WGSL build is ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
+
+#version 450
+
+layout(location = 0) out vec2 v_tex_coord;
+
+void main() {
+    // Fullscreen triangle.
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+    v_tex_coord = position;
+    gl_Position = vec4(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0, 0.0, 1.0);
+}
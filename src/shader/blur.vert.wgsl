@@ -0,0 +1,17 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from blur.vert. Keep in sync with the GLSL source.
+
+[[builtin(vertex_index)]]
+var<in> in_vertex_index: u32;
+[[location(0)]]
+var<out> v_tex_coord: vec2<f32>;
+[[builtin(position)]]
+var<out> out_position: vec4<f32>;
+
+[[stage(vertex)]]
+fn main() {
+    // Fullscreen triangle.
+    const position: vec2<f32> = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    v_tex_coord = position;
+    out_position = vec4<f32>(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0, 0.0, 1.0);
+}
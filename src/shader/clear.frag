@@ -0,0 +1,11 @@
+// glslc -g -x glsl -O clear.frag -o clear.frag.spirv
+// The WGSL build is ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
+
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    // The clear color is applied as the blend constant.
+    f_color = vec4(1.0);
+}
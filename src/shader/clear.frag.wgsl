@@ -0,0 +1,11 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from clear.frag. Keep in sync with the GLSL source.
+
+[[location(0)]]
+var<out> f_color: vec4<f32>;
+
+[[stage(fragment)]]
+fn main() {
+    // The clear color is applied as the blend constant.
+    f_color = vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
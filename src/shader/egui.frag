@@ -1,4 +1,10 @@
 // glslc -g -x glsl -O egui.frag -o egui.frag.spirv
+// glslc -g -x glsl -O -DSHADER_CLIP egui.frag -o egui_shader_clip.frag.spirv
+// glslc -g -x glsl -O -DPUSH_CONSTANTS egui.frag -o egui_push.frag.spirv
+// glslc -g -x glsl -O -DDEPTH_TEXTURE egui.frag -o egui_depth.frag.spirv
+// glslc -g -x glsl -O -DUINT_TEXTURE egui.frag -o egui_uint.frag.spirv
+// glslc -g -x glsl -O -DSINT_TEXTURE egui.frag -o egui_sint.frag.spirv
+// The WGSL builds are ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
 
 #version 450
 
@@ -6,9 +12,210 @@ layout(location = 0) in vec2 v_tex_coord;
 layout(location = 1) in vec4 v_color;
 layout(location = 0) out vec4 f_color;
 
+#ifdef PUSH_CONSTANTS
+layout(push_constant) uniform UniformBuffer {
+#else
+layout(set = 0, binding = 0) uniform UniformBuffer {
+#endif
+    vec2 u_screen_size;
+    float u_gamma;
+    float u_dither_strength;
+    uint u_srgb_encode;
+    uint u_gamma_blending;
+    vec2 u_physical_size;
+    uint u_rotation;
+    uint u_color_transform;
+    mat3 u_color_matrix;
+    vec3 u_tone_curve;
+};
+
+#if defined(UINT_TEXTURE)
+layout(set = 1, binding = 0) uniform utexture2D t_texture;
+#elif defined(SINT_TEXTURE)
+layout(set = 1, binding = 0) uniform itexture2D t_texture;
+#else
 layout(set = 1, binding = 0) uniform texture2D t_texture;
-layout(set = 0, binding = 1) uniform sampler s_texture;
+#endif
+layout(set = 1, binding = 1) uniform sampler s_texture;
+layout(set = 1, binding = 2) uniform TextureUniformBuffer {
+    float u_lod_bias;
+    uint u_screen_space_uv;
+    uint u_colormap;
+    float u_value_min;
+    float u_value_max;
+    float u_exposure;
+    uint u_tone_mapping;
+    uint u_uv_transform;
+    uint u_coverage;
+    uint u_colormap_size;
+};
+
+#if !defined(UINT_TEXTURE) && !defined(SINT_TEXTURE) && !defined(DEPTH_TEXTURE)
+layout(set = 1, binding = 3) uniform texture2D t_colormap;
+#endif
+
+#ifdef SHADER_CLIP
+// Clip rect in physical pixels as (min_x, min_y, max_x, max_y).
+layout(set = 2, binding = 0) uniform ClipUniformBuffer {
+    vec4 u_clip_rect;
+};
+#endif
+
+vec3 srgb_from_linear(vec3 rgb) {
+    bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+    vec3 lower = rgb * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+// Polynomial fits of the matplotlib colormaps in sRGB, see https://www.shadertoy.com/view/WlfXRN.
+vec3 viridis(float t) {
+    const vec3 c0 = vec3(0.2777273272234177, 0.005407344544966578, 0.3340998053353061);
+    const vec3 c1 = vec3(0.1050930431085774, 1.404613529898575, 1.384590162594685);
+    const vec3 c2 = vec3(-0.3308618287255563, 0.214847559468213, 0.09509516302823659);
+    const vec3 c3 = vec3(-4.634230498983486, -5.799100973351585, -19.33244095627987);
+    const vec3 c4 = vec3(6.228269936347081, 14.17993336680509, 56.69055260068105);
+    const vec3 c5 = vec3(4.776384997670288, -13.74514537774601, -65.35303263337234);
+    const vec3 c6 = vec3(-5.435455855934631, 4.645852612178535, 26.3124352495832);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+vec3 magma(float t) {
+    const vec3 c0 = vec3(-0.002136485053939582, -0.000749655052795221, -0.005386127855323933);
+    const vec3 c1 = vec3(0.2516605407371642, 0.6775232436837668, 2.494026599312351);
+    const vec3 c2 = vec3(8.353717279216625, -3.577719514958484, 0.3144679030132573);
+    const vec3 c3 = vec3(-27.66873308576866, 14.26473078096533, -13.64921318813922);
+    const vec3 c4 = vec3(52.17613981234068, -27.94360607168351, 12.94416944238394);
+    const vec3 c5 = vec3(-50.76852536473588, 29.04658282127291, 4.23415299384598);
+    const vec3 c6 = vec3(18.65570506591883, -11.48977351997711, -5.601961508734096);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+// Maps the red channel of single channel data to a color. Returns the texel unchanged without a colormap.
+vec4 apply_colormap(vec4 texel) {
+    if (u_colormap == 0u) {
+        return texel;
+    }
+    float value = clamp(texel.r, 0.0, 1.0);
+    vec3 rgb;
+    if (u_colormap == 1u) {
+        rgb = linear_from_srgb(vec3(value));
+    } else if (u_colormap == 2u) {
+        rgb = linear_from_srgb(clamp(viridis(value), 0.0, 1.0));
+    } else if (u_colormap == 3u) {
+        rgb = linear_from_srgb(clamp(magma(value), 0.0, 1.0));
+    } else {
+#if !defined(UINT_TEXTURE) && !defined(SINT_TEXTURE) && !defined(DEPTH_TEXTURE)
+        // The lookup table is a sRGB texture with one row, so it is already linear.
+        float size = float(u_colormap_size);
+        vec2 lut_coord = vec2((value * (size - 1.0) + 0.5) / size, 0.5);
+        rgb = textureLod(sampler2D(t_colormap, s_texture), lut_coord, 0.0).rgb;
+#else
+        rgb = vec3(value);
+#endif
+    }
+    return vec4(rgb, 1.0);
+}
+
+// Returns a pseudo random value in [-0.5, 0.5] for the given pixel.
+float dither_noise(vec2 frag_coord) {
+    return fract(sin(dot(frag_coord, vec2(12.9898, 78.233))) * 43758.5453) - 0.5;
+}
+
+// Rotates the UVs by 90 degrees clockwise and flips them as selected for the texture.
+vec2 transform_uv(vec2 uv) {
+    if ((u_uv_transform & 4u) != 0u) {
+        uv = vec2(uv.y, 1.0 - uv.x);
+    }
+    if ((u_uv_transform & 1u) != 0u) {
+        uv.x = 1.0 - uv.x;
+    }
+    if ((u_uv_transform & 2u) != 0u) {
+        uv.y = 1.0 - uv.y;
+    }
+    return uv;
+}
+
+// Maps HDR colors into [0, 1] with the operator selected for the texture.
+vec3 tone_map(vec3 rgb) {
+    if (u_tone_mapping == 1u) {
+        return rgb / (vec3(1.0) + rgb);
+    } else if (u_tone_mapping == 2u) {
+        // Krzysztof Narkowicz' ACES approximation.
+        return clamp((rgb * (2.51 * rgb + 0.03)) / (rgb * (2.43 * rgb + 0.59) + 0.14), 0.0, 1.0);
+    }
+    return rgb;
+}
+
+#if defined(UINT_TEXTURE) || defined(SINT_TEXTURE)
+// Maps integer values like object ids to distinct colors. Zero stays transparent.
+vec4 color_from_value(uint value) {
+    if (value == 0u) {
+        return vec4(0.0);
+    }
+    float hue = float((value * 2654435761u) >> 8) / 16777216.0;
+    vec3 rgb = clamp(abs(fract(hue + vec3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0, 0.0, 1.0);
+    return vec4(rgb, 1.0);
+}
+#endif
+
+vec4 sample_texture(vec2 tex_coord) {
+#if defined(UINT_TEXTURE)
+    ivec2 size = textureSize(usampler2D(t_texture, s_texture), 0);
+    ivec2 texel = clamp(ivec2(tex_coord * vec2(size)), ivec2(0), size - 1);
+    return color_from_value(texelFetch(usampler2D(t_texture, s_texture), texel, 0).r);
+#elif defined(SINT_TEXTURE)
+    ivec2 size = textureSize(isampler2D(t_texture, s_texture), 0);
+    ivec2 texel = clamp(ivec2(tex_coord * vec2(size)), ivec2(0), size - 1);
+    return color_from_value(uint(texelFetch(isampler2D(t_texture, s_texture), texel, 0).r));
+#elif defined(DEPTH_TEXTURE)
+    // Shows the depth as gray scale.
+    float depth = texture(sampler2D(t_texture, s_texture), tex_coord).r;
+    return vec4(vec3(depth), 1.0);
+#else
+    return texture(sampler2D(t_texture, s_texture), tex_coord, u_lod_bias);
+#endif
+}
 
 void main() {
-    f_color = v_color * texture(sampler2D(t_texture, s_texture), v_tex_coord);
+#ifdef SHADER_CLIP
+    if (any(lessThan(gl_FragCoord.xy, u_clip_rect.xy)) || any(greaterThanEqual(gl_FragCoord.xy, u_clip_rect.zw))) {
+        discard;
+    }
+#endif
+    vec2 tex_coord = u_screen_space_uv != 0u ? gl_FragCoord.xy / u_physical_size : transform_uv(v_tex_coord);
+    vec4 texel = sample_texture(tex_coord);
+    if (u_coverage != 0u) {
+        // Single channel font texture: the red channel is the coverage of premultiplied white in sRGB.
+        texel = vec4(linear_from_srgb(vec3(texel.r)), texel.r);
+    }
+    texel.rgb *= u_exposure;
+    texel.rgb = tone_map(texel.rgb);
+    if (u_value_min != 0.0 || u_value_max != 1.0) {
+        // Normalizes data values to [0, 1] before color mapping.
+        texel.rgb = (texel.rgb - vec3(u_value_min)) / vec3(u_value_max - u_value_min);
+    }
+    texel = apply_colormap(texel);
+    if (u_gamma_blending != 0u) {
+        // Vertex colors stay in gamma space, so the texture has to be brought there as well.
+        texel.rgb = srgb_from_linear(texel.rgb);
+    }
+    vec4 color = v_color * texel;
+    if (u_color_transform != 0u && u_gamma_blending == 0u) {
+        // Converts the linear sRGB color into the color space of the output.
+        color.rgb = pow(clamp(u_color_matrix * color.rgb, 0.0, 1.0), u_tone_curve);
+    }
+    color.rgb = pow(color.rgb, vec3(u_gamma));
+    if (u_srgb_encode != 0u && u_gamma_blending == 0u) {
+        color.rgb = srgb_from_linear(color.rgb);
+    }
+    color.rgb += vec3(dither_noise(gl_FragCoord.xy) * u_dither_strength / 255.0);
+    f_color = color;
 }
@@ -1,9 +1,24 @@
 // glslc -g -x glsl -O egui.vert -o egui.vert.spirv
+// glslc -g -x glsl -O -DAUX_STREAM egui.vert -o egui_aux.vert.spirv
+// glslc -g -x glsl -O -DPICKING egui.vert -o egui_picking.vert.spirv
+// glslc -g -x glsl -O -DPUSH_CONSTANTS egui.vert -o egui_push.vert.spirv
+// glslc -g -x glsl -O -DAUX_STREAM -DPUSH_CONSTANTS egui.vert -o egui_aux_push.vert.spirv
+// The WGSL builds are ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
 
 #version 450
 
+#ifdef PUSH_CONSTANTS
+layout(push_constant) uniform UniformBuffer {
+#else
 layout(set = 0, binding = 0) uniform UniformBuffer {
+#endif
     vec2 u_screen_size;
+    float u_gamma;
+    float u_dither_strength;
+    uint u_srgb_encode;
+    uint u_gamma_blending;
+    vec2 u_physical_size;
+    uint u_rotation;
 };
 
 layout(location = 0) in vec2 a_pos;
@@ -12,6 +27,15 @@ layout(location = 2) in uint a_color;
 layout(location = 0) out vec2 v_tex_coord;
 layout(location = 1) out vec4 v_color;
 
+#ifdef AUX_STREAM
+layout(location = 3) in vec4 a_aux;
+layout(location = 2) out vec4 v_aux;
+#endif
+
+#ifdef PICKING
+layout(location = 2) flat out uint v_id;
+#endif
+
 vec3 linear_from_srgb(vec3 srgb) {
     bvec3 cutoff = lessThan(srgb, vec3(10.31475));
     vec3 lower = srgb / vec3(3294.6);
@@ -21,8 +45,28 @@ vec3 linear_from_srgb(vec3 srgb) {
 
 void main() {
     v_tex_coord = a_tex_coord;
+#ifdef AUX_STREAM
+    v_aux = a_aux;
+#endif
+#ifdef PICKING
+    // The id is passed as the first instance of the draw call.
+    v_id = gl_InstanceIndex;
+#endif
     // [u8; 4] SRGB as u32 -> [r, g, b, a]
     vec4 color = vec4(a_color & 0xFFu, (a_color >> 8) & 0xFFu, (a_color >> 16) & 0xFFu, (a_color >> 24) & 0xFFu);
-    v_color = vec4(linear_from_srgb(color.rgb), color.a / 255.0);
-    gl_Position = vec4(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y, 0.0, 1.0);
+    if (u_gamma_blending != 0u) {
+        v_color = color / 255.0;
+    } else {
+        v_color = vec4(linear_from_srgb(color.rgb), color.a / 255.0);
+    }
+    vec2 pos = vec2(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y);
+    // Clockwise pre-rotation of the surface in quarter turns.
+    if (u_rotation == 1u) {
+        pos = vec2(pos.y, -pos.x);
+    } else if (u_rotation == 2u) {
+        pos = -pos;
+    } else if (u_rotation == 3u) {
+        pos = vec2(-pos.y, pos.x);
+    }
+    gl_Position = vec4(pos, 0.0, 1.0);
 }
@@ -0,0 +1,35 @@
+// glslc -g -x glsl -O egui_picking.frag -o egui_picking.frag.spirv
+// The WGSL build is ported by hand, since the WGSL frontend of wgpu 0.7 predates the current syntax.
+
+#version 450
+
+layout(location = 0) in vec2 v_tex_coord;
+layout(location = 1) in vec4 v_color;
+layout(location = 2) flat in uint v_id;
+layout(location = 0) out uint f_id;
+
+layout(set = 1, binding = 0) uniform texture2D t_texture;
+layout(set = 1, binding = 1) uniform sampler s_texture;
+layout(set = 1, binding = 2) uniform TextureUniformBuffer {
+    float u_lod_bias;
+    uint u_screen_space_uv;
+    uint u_colormap;
+    float u_value_min;
+    float u_value_max;
+    float u_exposure;
+    uint u_tone_mapping;
+    uint u_uv_transform;
+    uint u_coverage;
+    uint u_colormap_size;
+};
+
+void main() {
+    vec4 texel = texture(sampler2D(t_texture, s_texture), v_tex_coord);
+    // The single channel font texture holds the coverage in the red channel.
+    float alpha = v_color.a * (u_coverage != 0u ? texel.r : texel.a);
+    // Transparent pixels don't belong to the mesh.
+    if (alpha <= 0.0) {
+        discard;
+    }
+    f_id = v_id;
+}
@@ -0,0 +1,48 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from egui_picking.frag. Keep in sync with the GLSL source.
+
+[[location(0)]]
+var<in> v_tex_coord: vec2<f32>;
+[[location(1)]]
+var<in> v_color: vec4<f32>;
+[[location(2), interpolate(flat)]]
+var<in> v_id: u32;
+[[location(0)]]
+var<out> f_id: u32;
+
+[[group(1), binding(0)]]
+var t_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var s_texture: sampler;
+
+[[block]]
+struct TextureUniforms {
+    lod_bias: f32;
+    screen_space_uv: u32;
+    colormap: u32;
+    value_min: f32;
+    value_max: f32;
+    exposure: f32;
+    tone_mapping: u32;
+    uv_transform: u32;
+    coverage: u32;
+    colormap_size: u32;
+};
+
+[[group(1), binding(2)]]
+var<uniform> texture_uniforms: TextureUniforms;
+
+[[stage(fragment)]]
+fn main() {
+    const texel: vec4<f32> = textureSample(t_texture, s_texture, v_tex_coord);
+    // The single channel font texture holds the coverage in the red channel.
+    var alpha: f32 = v_color.w * texel.w;
+    if (texture_uniforms.coverage != 0u) {
+        alpha = v_color.w * texel.x;
+    }
+    // Transparent pixels don't belong to the mesh.
+    if (alpha <= 0.0) {
+        discard;
+    }
+    f_id = v_id;
+}
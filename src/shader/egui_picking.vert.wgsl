@@ -0,0 +1,73 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from egui.vert with PICKING defined. Keep in sync with the GLSL source.
+
+[[block]]
+struct Uniforms {
+    screen_size: vec2<f32>;
+    gamma: f32;
+    dither_strength: f32;
+    srgb_encode: u32;
+    gamma_blending: u32;
+    physical_size: vec2<f32>;
+    rotation: u32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+[[location(0)]]
+var<in> a_pos: vec2<f32>;
+[[location(1)]]
+var<in> a_tex_coord: vec2<f32>;
+[[location(2)]]
+var<in> a_color: u32;
+[[location(0)]]
+var<out> v_tex_coord: vec2<f32>;
+[[location(1)]]
+var<out> v_color: vec4<f32>;
+[[builtin(position)]]
+var<out> out_position: vec4<f32>;
+
+[[builtin(instance_index)]]
+var<in> in_instance_index: u32;
+[[location(2), interpolate(flat)]]
+var<out> v_id: u32;
+
+fn linear_from_srgb_channel(srgb: f32) -> f32 {
+    if (srgb < 10.31475) {
+        return srgb / 3294.6;
+    }
+    return pow((srgb + 14.025) / 269.025, 2.4);
+}
+
+fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(linear_from_srgb_channel(srgb.x), linear_from_srgb_channel(srgb.y), linear_from_srgb_channel(srgb.z));
+}
+
+[[stage(vertex)]]
+fn main() {
+    v_tex_coord = a_tex_coord;
+    // The id is passed as the first instance of the draw call.
+    v_id = in_instance_index;
+    // [u8; 4] SRGB as u32 -> [r, g, b, a]
+    const color: vec4<f32> = vec4<f32>(f32(a_color & 255u), f32((a_color >> 8u) & 255u), f32((a_color >> 16u) & 255u), f32((a_color >> 24u) & 255u));
+    if (uniforms.gamma_blending != 0u) {
+        v_color = color * (1.0 / 255.0);
+    } else {
+        v_color = vec4<f32>(linear_from_srgb(color.xyz), color.w / 255.0);
+    }
+    var pos: vec2<f32> = vec2<f32>(2.0 * a_pos.x / uniforms.screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / uniforms.screen_size.y);
+    // Clockwise pre-rotation of the surface in quarter turns.
+    if (uniforms.rotation == 1u) {
+        pos = vec2<f32>(pos.y, -pos.x);
+    } else {
+        if (uniforms.rotation == 2u) {
+            pos = -pos;
+        } else {
+            if (uniforms.rotation == 3u) {
+                pos = vec2<f32>(-pos.y, pos.x);
+            }
+        }
+    }
+    out_position = vec4<f32>(pos, 0.0, 1.0);
+}
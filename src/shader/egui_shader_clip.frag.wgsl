@@ -0,0 +1,220 @@
+// WGSL port of the GLSL shader for the WGSL frontend of wgpu 0.7 (naga 0.3), which has no preprocessor,
+// push constants or texture queries. Built from egui.frag with SHADER_CLIP defined. Keep in sync with the GLSL source.
+
+[[location(0)]]
+var<in> v_tex_coord: vec2<f32>;
+[[location(1)]]
+var<in> v_color: vec4<f32>;
+[[builtin(frag_coord)]]
+var<in> in_frag_coord: vec4<f32>;
+[[location(0)]]
+var<out> f_color: vec4<f32>;
+
+// The spans pad the members to the std140 layout of the GLSL block, the matrix is split into its columns.
+[[block]]
+struct Uniforms {
+    screen_size: vec2<f32>;
+    gamma: f32;
+    dither_strength: f32;
+    srgb_encode: u32;
+    gamma_blending: u32;
+    physical_size: vec2<f32>;
+    rotation: u32;
+    [[span(12)]] color_transform: u32;
+    [[span(16)]] color_matrix_x: vec3<f32>;
+    [[span(16)]] color_matrix_y: vec3<f32>;
+    [[span(16)]] color_matrix_z: vec3<f32>;
+    [[span(16)]] tone_curve: vec3<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+[[group(1), binding(0)]]
+var t_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var s_texture: sampler;
+
+[[block]]
+struct TextureUniforms {
+    lod_bias: f32;
+    screen_space_uv: u32;
+    colormap: u32;
+    value_min: f32;
+    value_max: f32;
+    exposure: f32;
+    tone_mapping: u32;
+    uv_transform: u32;
+    coverage: u32;
+    colormap_size: u32;
+};
+
+[[group(1), binding(2)]]
+var<uniform> texture_uniforms: TextureUniforms;
+
+[[group(1), binding(3)]]
+var t_colormap: texture_2d<f32>;
+
+// Clip rect in physical pixels as (min_x, min_y, max_x, max_y).
+[[block]]
+struct ClipUniforms {
+    clip_rect: vec4<f32>;
+};
+
+[[group(2), binding(0)]]
+var<uniform> clip_uniforms: ClipUniforms;
+
+fn srgb_from_linear_channel(rgb: f32) -> f32 {
+    if (rgb < 0.0031308) {
+        return rgb * 12.92;
+    }
+    return 1.055 * pow(rgb, 1.0 / 2.4) - 0.055;
+}
+
+fn srgb_from_linear(rgb: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(srgb_from_linear_channel(rgb.x), srgb_from_linear_channel(rgb.y), srgb_from_linear_channel(rgb.z));
+}
+
+fn linear_from_srgb_channel(srgb: f32) -> f32 {
+    if (srgb < 0.04045) {
+        return srgb / 12.92;
+    }
+    return pow((srgb + 0.055) / 1.055, 2.4);
+}
+
+fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(linear_from_srgb_channel(srgb.x), linear_from_srgb_channel(srgb.y), linear_from_srgb_channel(srgb.z));
+}
+
+// Polynomial fits of the matplotlib colormaps in sRGB, see https://www.shadertoy.com/view/WlfXRN.
+fn viridis(t: f32) -> vec3<f32> {
+    const c0: vec3<f32> = vec3<f32>(0.2777273272234177, 0.005407344544966578, 0.3340998053353061);
+    const c1: vec3<f32> = vec3<f32>(0.1050930431085774, 1.404613529898575, 1.384590162594685);
+    const c2: vec3<f32> = vec3<f32>(-0.3308618287255563, 0.214847559468213, 0.09509516302823659);
+    const c3: vec3<f32> = vec3<f32>(-4.634230498983486, -5.799100973351585, -19.33244095627987);
+    const c4: vec3<f32> = vec3<f32>(6.228269936347081, 14.17993336680509, 56.69055260068105);
+    const c5: vec3<f32> = vec3<f32>(4.776384997670288, -13.74514537774601, -65.35303263337234);
+    const c6: vec3<f32> = vec3<f32>(-5.435455855934631, 4.645852612178535, 26.3124352495832);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+fn magma(t: f32) -> vec3<f32> {
+    const c0: vec3<f32> = vec3<f32>(-0.002136485053939582, -0.000749655052795221, -0.005386127855323933);
+    const c1: vec3<f32> = vec3<f32>(0.2516605407371642, 0.6775232436837668, 2.494026599312351);
+    const c2: vec3<f32> = vec3<f32>(8.353717279216625, -3.577719514958484, 0.3144679030132573);
+    const c3: vec3<f32> = vec3<f32>(-27.66873308576866, 14.26473078096533, -13.64921318813922);
+    const c4: vec3<f32> = vec3<f32>(52.17613981234068, -27.94360607168351, 12.94416944238394);
+    const c5: vec3<f32> = vec3<f32>(-50.76852536473588, 29.04658282127291, 4.23415299384598);
+    const c6: vec3<f32> = vec3<f32>(18.65570506591883, -11.48977351997711, -5.601961508734096);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+fn saturate3(rgb: vec3<f32>) -> vec3<f32> {
+    return clamp(rgb, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0));
+}
+
+// Maps the red channel of single channel data to a color. Returns the texel unchanged without a colormap.
+fn apply_colormap(texel: vec4<f32>) -> vec4<f32> {
+    if (texture_uniforms.colormap == 0u) {
+        return texel;
+    }
+    const value: f32 = clamp(texel.x, 0.0, 1.0);
+    var rgb: vec3<f32> = vec3<f32>(value, value, value);
+    if (texture_uniforms.colormap == 1u) {
+        rgb = linear_from_srgb(rgb);
+    } else {
+        if (texture_uniforms.colormap == 2u) {
+            rgb = linear_from_srgb(saturate3(viridis(value)));
+        } else {
+            if (texture_uniforms.colormap == 3u) {
+                rgb = linear_from_srgb(saturate3(magma(value)));
+            }
+            else {
+                // The lookup table is a sRGB texture with one row, so it is already linear.
+                const size: f32 = f32(texture_uniforms.colormap_size);
+                const lut_coord: vec2<f32> = vec2<f32>((value * (size - 1.0) + 0.5) / size, 0.5);
+                rgb = textureSampleLevel(t_colormap, s_texture, lut_coord, 0.0).xyz;
+            }
+        }
+    }
+    return vec4<f32>(rgb, 1.0);
+}
+
+// Returns a pseudo random value in [-0.5, 0.5] for the given pixel.
+fn dither_noise(frag_coord: vec2<f32>) -> f32 {
+    return fract(sin(dot(frag_coord, vec2<f32>(12.9898, 78.233))) * 43758.5453) - 0.5;
+}
+
+// Rotates the UVs by 90 degrees clockwise and flips them as selected for the texture.
+fn transform_uv(tex_coord: vec2<f32>) -> vec2<f32> {
+    var uv: vec2<f32> = tex_coord;
+    if ((texture_uniforms.uv_transform & 4u) != 0u) {
+        uv = vec2<f32>(uv.y, 1.0 - uv.x);
+    }
+    if ((texture_uniforms.uv_transform & 1u) != 0u) {
+        uv = vec2<f32>(1.0 - uv.x, uv.y);
+    }
+    if ((texture_uniforms.uv_transform & 2u) != 0u) {
+        uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    }
+    return uv;
+}
+
+// Maps HDR colors into [0, 1] with the operator selected for the texture.
+fn tone_map(rgb: vec3<f32>) -> vec3<f32> {
+    if (texture_uniforms.tone_mapping == 1u) {
+        return rgb / (vec3<f32>(1.0, 1.0, 1.0) + rgb);
+    }
+    if (texture_uniforms.tone_mapping == 2u) {
+        // Krzysztof Narkowicz' ACES approximation.
+        const numerator: vec3<f32> = rgb * (2.51 * rgb + vec3<f32>(0.03, 0.03, 0.03));
+        const denominator: vec3<f32> = rgb * (2.43 * rgb + vec3<f32>(0.59, 0.59, 0.59)) + vec3<f32>(0.14, 0.14, 0.14);
+        return saturate3(numerator / denominator);
+    }
+    return rgb;
+}
+
+fn sample_texture(tex_coord: vec2<f32>) -> vec4<f32> {
+    return textureSampleBias(t_texture, s_texture, tex_coord, texture_uniforms.lod_bias);
+}
+
+[[stage(fragment)]]
+fn main() {
+    const clip_rect: vec4<f32> = clip_uniforms.clip_rect;
+    if (in_frag_coord.x < clip_rect.x || in_frag_coord.y < clip_rect.y || in_frag_coord.x >= clip_rect.z || in_frag_coord.y >= clip_rect.w) {
+        discard;
+    }
+    var tex_coord: vec2<f32> = transform_uv(v_tex_coord);
+    if (texture_uniforms.screen_space_uv != 0u) {
+        tex_coord = in_frag_coord.xy / uniforms.physical_size;
+    }
+    var texel: vec4<f32> = sample_texture(tex_coord);
+    if (texture_uniforms.coverage != 0u) {
+        // Single channel font texture: the red channel is the coverage of premultiplied white in sRGB.
+        texel = vec4<f32>(linear_from_srgb(vec3<f32>(texel.x, texel.x, texel.x)), texel.x);
+    }
+    texel = vec4<f32>(tone_map(texel.xyz * texture_uniforms.exposure), texel.w);
+    if (texture_uniforms.value_min != 0.0 || texture_uniforms.value_max != 1.0) {
+        // Normalizes data values to [0, 1] before color mapping.
+        const value_min: vec3<f32> = vec3<f32>(texture_uniforms.value_min, texture_uniforms.value_min, texture_uniforms.value_min);
+        texel = vec4<f32>((texel.xyz - value_min) * (1.0 / (texture_uniforms.value_max - texture_uniforms.value_min)), texel.w);
+    }
+    texel = apply_colormap(texel);
+    if (uniforms.gamma_blending != 0u) {
+        // Vertex colors stay in gamma space, so the texture has to be brought there as well.
+        texel = vec4<f32>(srgb_from_linear(texel.xyz), texel.w);
+    }
+    var color: vec4<f32> = v_color * texel;
+    if (uniforms.color_transform != 0u && uniforms.gamma_blending == 0u) {
+        // Converts the linear sRGB color into the color space of the output.
+        const transformed: vec3<f32> = uniforms.color_matrix_x * color.x + uniforms.color_matrix_y * color.y + uniforms.color_matrix_z * color.z;
+        color = vec4<f32>(pow(saturate3(transformed), uniforms.tone_curve), color.w);
+    }
+    const gamma: vec3<f32> = vec3<f32>(uniforms.gamma, uniforms.gamma, uniforms.gamma);
+    color = vec4<f32>(pow(color.xyz, gamma), color.w);
+    if (uniforms.srgb_encode != 0u && uniforms.gamma_blending == 0u) {
+        color = vec4<f32>(srgb_from_linear(color.xyz), color.w);
+    }
+    const dither: f32 = dither_noise(in_frag_coord.xy) * uniforms.dither_strength / 255.0;
+    f_color = vec4<f32>(color.xyz + vec3<f32>(dither, dither, dither), color.w);
+}
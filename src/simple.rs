@@ -0,0 +1,92 @@
+//! Bootstrap for prototypes and examples that only need an egui closure on screen. Needs the `simple` feature.
+
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Window, WindowBuilder};
+
+use crate::{egui, EguiIntegration, WindowSurface};
+
+/// Opens a window titled `title` and calls `ui_fn` every frame to build the UI. Never returns.
+///
+/// Creates the event loop, window, wgpu instance, surface, device and render pass with default settings.
+/// Panics if no suitable adapter or device is found. Applications that need more control should use
+/// [`EguiIntegration`] directly.
+pub fn run<F>(title: &str, mut ui_fn: F) -> !
+where
+    F: FnMut(&egui::CtxRef) + 'static,
+{
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(title)
+        .build(&event_loop)
+        .expect("failed to create the window");
+
+    let (device, queue, mut window_surface) = create_device(&window, "egui_simple_device");
+    let mut integration = EguiIntegration::new(&device, window_surface.format(), &window)
+        .expect("the swap chain format is not supported");
+
+    event_loop.run(move |event, _, control_flow| {
+        integration.handle_event(&event);
+        window_surface.handle_event(&event);
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let frame = match window_surface.get_current_frame(&device) {
+                    Some(frame) => frame,
+                    None => return,
+                };
+
+                integration.begin_frame();
+                ui_fn(&integration.context());
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("egui_simple_encoder"),
+                });
+                let output = integration
+                    .render(
+                        &device,
+                        &queue,
+                        &mut encoder,
+                        &frame.output.view,
+                        Some(wgpu::Color::BLACK),
+                    )
+                    .expect("failed to render the UI");
+                queue.submit(Some(encoder.finish()));
+                integration.handle_output(&window, &output);
+            }
+            _ => {}
+        }
+    })
+}
+
+/// Creates the surface of `window` and a device with default settings that can present to it.
+/// Panics if no suitable adapter or device is found.
+pub(crate) fn create_device(
+    window: &Window,
+    label: &str,
+) -> (wgpu::Device, wgpu::Queue, WindowSurface) {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let surface = unsafe { instance.create_surface(window) };
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: Some(&surface),
+    }))
+    .expect("no suitable adapter found");
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some(label),
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+        },
+        None,
+    ))
+    .expect("failed to create the device");
+
+    let format = adapter.get_swap_chain_preferred_format(&surface);
+    let window_surface = WindowSurface::new(surface, window, format, wgpu::PresentMode::Fifo);
+    (device, queue, window_surface)
+}
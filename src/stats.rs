@@ -0,0 +1,84 @@
+//! Counters of the work done for a frame, e.g. for the profiler overlay of an application.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::RenderPass;
+
+/// Statistics of the frame of the last `update_buffers()` call, returned by [`RenderPass::render_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Meshes of the frame, including custom geometry.
+    pub meshes: usize,
+    /// Meshes that weren't uploaded because they are hidden, see `RenderPassConfig::skip_covered_meshes`.
+    pub covered_meshes: usize,
+    /// Vertices uploaded by `update_buffers()`.
+    pub vertices: usize,
+    /// Indices uploaded by `update_buffers()`.
+    pub indices: usize,
+    /// Bytes of vertex, index, auxiliary and uniform data written by `update_buffers()` and
+    /// `update_aux_buffers()`.
+    pub buffer_bytes: usize,
+    /// Textures uploaded since the `update_buffers()` of the previous frame, counting a partial update of the
+    /// egui texture as one upload.
+    pub texture_uploads: usize,
+    /// Bytes of the texture uploads.
+    pub texture_bytes: usize,
+    /// Draw calls of meshes recorded since `update_buffers()`, summed over all execute calls.
+    pub draw_calls: usize,
+    /// Meshes the execute calls skipped, e.g. with an empty clip rect, malformed or without indices, with a
+    /// texture waiting for its upload or vetoed by the draw hook. Covered meshes aren't counted again.
+    pub culled_meshes: usize,
+}
+
+/// Collects the statistics of the current frame. The draws are counted through a shared reference.
+#[derive(Default)]
+pub(crate) struct StatsCounters {
+    frame: RenderStats,
+    /// Texture uploads and their bytes since the start of the frame, counted towards the next frame.
+    pending_textures: (usize, usize),
+    draw_calls: AtomicUsize,
+    culled_meshes: AtomicUsize,
+}
+
+impl StatsCounters {
+    /// Starts a new frame with the textures uploaded since the previous one.
+    pub(crate) fn begin_frame(&mut self) {
+        let (texture_uploads, texture_bytes) = std::mem::take(&mut self.pending_textures);
+        self.frame = RenderStats {
+            texture_uploads,
+            texture_bytes,
+            ..Default::default()
+        };
+        *self.draw_calls.get_mut() = 0;
+        *self.culled_meshes.get_mut() = 0;
+    }
+
+    pub(crate) fn frame_mut(&mut self) -> &mut RenderStats {
+        &mut self.frame
+    }
+
+    pub(crate) fn texture_upload(&mut self, bytes: usize) {
+        self.pending_textures.0 += 1;
+        self.pending_textures.1 += bytes;
+    }
+
+    pub(crate) fn draw_call(&self) {
+        self.draw_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn culled_mesh(&self) {
+        self.culled_meshes.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl RenderPass {
+    /// Returns the statistics of the frame of the last `update_buffers()` call, including the draws of the
+    /// execute calls since.
+    pub fn render_stats(&self) -> RenderStats {
+        RenderStats {
+            draw_calls: self.stats.draw_calls.load(Ordering::Relaxed),
+            culled_meshes: self.stats.culled_meshes.load(Ordering::Relaxed),
+            ..self.stats.frame
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Clipping with a stencil buffer instead of scissor rects, for UIs a custom vertex shader rotates or projects.
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    as_byte_slice, create_render_pipeline, egui, egui_color_target_state,
+    egui_vertex_buffer_layouts, BackendError, PipelineKey, RenderPass, ScreenDescriptor,
+    UniformBuffer, UNIFORM_STAGES,
+};
+
+/// Format of the stencil attachment of [`RenderPass::execute_with_stencil_clip`].
+pub const STENCIL_CLIP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Size of the four vertices of a clip rect quad.
+const QUAD_SIZE: wgpu::BufferAddress = 4 * 5 * 4;
+
+/// Pipelines of the stencil clipping, created when first used.
+pub(crate) struct StencilClip {
+    /// Writes the stencil reference inside of a clip rect, without touching the colors.
+    mask_pipeline: wgpu::RenderPipeline,
+    /// Egui pipelines drawing only where the stencil equals the reference.
+    pipelines: Vec<(PipelineKey, wgpu::RenderPipeline)>,
+    quad_index_buffer: wgpu::Buffer,
+}
+
+impl StencilClip {
+    /// Sets the stencil inside of a clip rect to `reference`.
+    fn draw_mask<'rp>(
+        &'rp self,
+        pass: &mut wgpu::RenderPass<'rp>,
+        egui_bind_group: &'rp wgpu::BindGroup,
+        quad: wgpu::BufferSlice<'rp>,
+        aux_zero_buffer: Option<&'rp wgpu::Buffer>,
+        reference: u32,
+    ) {
+        pass.set_pipeline(&self.mask_pipeline);
+        pass.set_stencil_reference(reference);
+        pass.set_bind_group(1, egui_bind_group, &[]);
+        pass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        pass.set_vertex_buffer(0, quad);
+        if let Some(aux_zero_buffer) = aux_zero_buffer {
+            pass.set_vertex_buffer(1, aux_zero_buffer.slice(..));
+        }
+        pass.draw_indexed(0..6, 0, 0..1);
+    }
+}
+
+fn stencil_state(face: wgpu::StencilFaceState, write_mask: u32) -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: STENCIL_CLIP_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState {
+            front: face.clone(),
+            back: face,
+            read_mask: 0xff,
+            write_mask,
+        },
+        bias: wgpu::DepthBiasState::default(),
+        clamp_depth: false,
+    }
+}
+
+impl RenderPass {
+    /// Like [`RenderPass::execute`], but clips the meshes with `stencil_attachment` instead of scissor rects.
+    ///
+    /// The clip rects are drawn into the stencil buffer through the vertex shader of the render pass, so they
+    /// are clipped correctly when a custom vertex shader rotates or projects the UI, where axis-aligned
+    /// scissor rects don't match anymore. `stencil_attachment` needs the format [`STENCIL_CLIP_FORMAT`] and
+    /// the size and sample count of `color_attachment`. Each mesh takes three draws and the draw hook is not
+    /// called. Expects `update_buffers()` to be called with the same paint jobs before.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_with_stencil_clip(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        color_attachment: &wgpu::TextureView,
+        stencil_attachment: &wgpu::TextureView,
+        paint_jobs: &[egui::paint::ClippedMesh],
+        screen_descriptor: &ScreenDescriptor,
+        clear_color: Option<wgpu::Color>,
+    ) -> Result<(), BackendError> {
+        if self.stencil_clip.is_none() {
+            self.stencil_clip = Some(self.create_stencil_clip(device));
+        }
+        let keys: Vec<PipelineKey> = self
+            .frame_meshes(paint_jobs)
+            .into_iter()
+            .map(|egui::ClippedMesh(_, mesh)| self.texture_pipeline_key(mesh.texture_id))
+            .collect();
+        // Index of the pipeline of each mesh in `StencilClip::pipelines`.
+        let mut pipeline_indices = Vec::with_capacity(keys.len());
+        for key in keys {
+            let stencil_clip = self.stencil_clip.as_ref().unwrap();
+            if let Some(index) = stencil_clip
+                .pipelines
+                .iter()
+                .position(|(variant, _)| *variant == key)
+            {
+                pipeline_indices.push(index);
+                continue;
+            }
+            let face = wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::Equal,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Keep,
+            };
+            let label = format!(
+                "egui_stencil_{:?}_{:?}_pipeline",
+                key.blend_mode, key.sample_kind
+            )
+            .to_lowercase();
+            let pipeline =
+                self.create_keyed_pipeline(device, key, &label, Some(stencil_state(face, 0)));
+            let pipelines = &mut self.stencil_clip.as_mut().unwrap().pipelines;
+            pipeline_indices.push(pipelines.len());
+            pipelines.push((key, pipeline));
+        }
+        let egui_bind_group = self
+            .texture_bind_group
+            .as_ref()
+            .ok_or(BackendError::EguiTextureNotSet)?;
+
+        let quads: Vec<egui::paint::Vertex> = self
+            .frame_meshes(paint_jobs)
+            .into_iter()
+            .flat_map(|egui::ClippedMesh(clip_rect, _)| {
+                let vertex = |pos| egui::paint::Vertex {
+                    pos,
+                    uv: egui::paint::WHITE_UV,
+                    color: egui::Color32::WHITE,
+                };
+                vec![
+                    vertex(clip_rect.left_top()),
+                    vertex(clip_rect.right_top()),
+                    vertex(clip_rect.left_bottom()),
+                    vertex(clip_rect.right_bottom()),
+                ]
+            })
+            .collect();
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_stencil_clip_vertex_buffer"),
+            contents: as_byte_slice(&quads),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let load_operation = self.load_operation(clear_color);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: color_attachment,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: load_operation,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: stencil_attachment,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: false,
+                }),
+            }),
+            label: Some("egui stencil clip render pass"),
+        });
+        pass.push_debug_group("egui_stencil_clip_pass");
+
+        pass.set_bind_group(
+            0,
+            &self.uniform_bind_group,
+            &[self.uniform_offset(screen_descriptor)],
+        );
+        if self.push_constants {
+            pass.set_push_constants(
+                UNIFORM_STAGES,
+                0,
+                bytemuck::cast_slice(&[UniformBuffer::new(
+                    screen_descriptor,
+                    &self.constants,
+                    self.blend_space,
+                )]),
+            );
+        }
+        if screen_descriptor.viewport.is_some() {
+            let (x, y, width, height) = screen_descriptor.target_region();
+            pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        }
+
+        let stencil_clip = self.stencil_clip.as_ref().unwrap();
+
+        for (i, (egui::ClippedMesh(_, mesh), range)) in self
+            .frame_meshes(paint_jobs)
+            .into_iter()
+            .zip(self.mesh_ranges.iter())
+            .enumerate()
+        {
+            if self.is_mesh_covered(i) {
+                continue;
+            }
+            // Malformed and empty meshes weren't uploaded.
+            let range = match range {
+                Some(range) => range,
+                None => {
+                    self.stats.culled_mesh();
+                    continue;
+                }
+            };
+            let bind_group = match self.get_texture_bind_group(mesh.texture_id)? {
+                Some(bind_group) => bind_group,
+                // The user texture is still waiting for its upload.
+                None => continue,
+            };
+
+            // Mark the clip rect, draw the mesh where it is marked and remove the mark again.
+            let offset = i as wgpu::BufferAddress * QUAD_SIZE;
+            let quad = quad_buffer.slice(offset..offset + QUAD_SIZE);
+            let aux_zero_buffer = self.aux_zero_buffer.as_ref();
+            stencil_clip.draw_mask(&mut pass, egui_bind_group, quad, aux_zero_buffer, 1);
+            pass.set_pipeline(&stencil_clip.pipelines[pipeline_indices[i]].1);
+            pass.set_bind_group(1, bind_group, &[]);
+            pass.set_index_buffer(
+                self.index_buffer.buffer.slice(range.indices.clone()),
+                wgpu::IndexFormat::Uint32,
+            );
+            pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(range.vertices.clone()));
+            if self.aux_vertex_stream {
+                let aux_range = self
+                    .aux_ranges
+                    .get(i)
+                    .ok_or(BackendError::AuxBuffersNotUpdated)?;
+                pass.set_vertex_buffer(1, self.aux_buffer.buffer.slice(aux_range.clone()));
+            }
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+            stencil_clip.draw_mask(&mut pass, egui_bind_group, quad, aux_zero_buffer, 0);
+        }
+
+        pass.pop_debug_group();
+        Ok(())
+    }
+
+    fn create_stencil_clip(&self, device: &wgpu::Device) -> StencilClip {
+        let mut target = egui_color_target_state(self.output_format, &self.config);
+        target.write_mask = wgpu::ColorWrite::empty();
+        let replace = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
+        let mask_pipeline = create_render_pipeline(
+            device,
+            "egui_stencil_mask_pipeline",
+            &self.context.shared.pipeline_layout,
+            &self.context.shared.vs_module,
+            &self.context.shared.fs_module,
+            egui_vertex_buffer_layouts(self.aux_vertex_stream),
+            target,
+            Some(stencil_state(replace, 0xff)),
+            self.sample_count,
+        );
+
+        // Same order as `egui::paint::Mesh::add_rect_with_uv()`.
+        let quad_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("egui_stencil_clip_index_buffer"),
+            contents: bytemuck::cast_slice(&[0u32, 1, 2, 2, 1, 3]),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        StencilClip {
+            mask_pipeline,
+            pipelines: Vec::new(),
+            quad_index_buffer,
+        }
+    }
+}
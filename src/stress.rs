@@ -0,0 +1,276 @@
+//! Synthetic workloads to measure the performance of the render pass. Needs the `stress` feature.
+//!
+//! The workload is generated from a seed, so runs with the same [`StressConfig`] render the same frames
+//! and can be compared between changes.
+
+use std::time::{Duration, Instant};
+
+use epi::TextureAllocator;
+
+use crate::{egui, RenderPass, ScreenDescriptor, SurfaceRotation};
+
+/// Format of the offscreen target the workload is rendered into.
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Size of the user textures of the workload in pixels.
+const TEXTURE_SIZE: usize = 64;
+
+/// Describes the synthetic workload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StressConfig {
+    /// Number of meshes per frame.
+    pub mesh_count: usize,
+    /// Number of quads in each mesh.
+    pub quads_per_mesh: usize,
+    /// Number of user textures the meshes are spread over. The egui texture is used when 0.
+    pub texture_count: usize,
+    /// Number of distinct clip rectangles the meshes are spread over.
+    pub clip_rect_count: usize,
+    /// Size of the offscreen target in physical pixels.
+    pub target_size: (u32, u32),
+    /// Number of frames to render.
+    pub frames: usize,
+    /// Seed of the workload.
+    pub seed: u64,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            mesh_count: 5000,
+            quads_per_mesh: 4,
+            texture_count: 64,
+            clip_rect_count: 1000,
+            target_size: (1920, 1080),
+            frames: 100,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// Measurements of a stress run. Durations are summed over all frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StressReport {
+    /// Number of rendered frames.
+    pub frames: usize,
+    /// Number of meshes per frame.
+    pub meshes: usize,
+    /// Number of vertices per frame.
+    pub vertices: usize,
+    /// Number of indices per frame.
+    pub indices: usize,
+    /// Time spent in `update_user_textures()` and `update_buffers()`.
+    pub upload_time: Duration,
+    /// Time spent in `execute()` and finishing the command buffer.
+    pub encode_time: Duration,
+    /// Time spent waiting for the GPU to finish the frame.
+    pub gpu_wait_time: Duration,
+    /// Bytes of vertex buffers allocated by the render pass at the end of the run.
+    pub vertex_buffer_bytes: usize,
+    /// Bytes of index buffers allocated by the render pass at the end of the run.
+    pub index_buffer_bytes: usize,
+    /// Bytes of user textures uploaded by the render pass at the end of the run.
+    pub texture_bytes: usize,
+}
+
+impl StressReport {
+    /// Returns the total time spent on the CPU and waiting for the GPU.
+    pub fn total_time(&self) -> Duration {
+        self.upload_time + self.encode_time + self.gpu_wait_time
+    }
+
+    /// Returns the average time of a frame.
+    pub fn frame_time(&self) -> Duration {
+        self.total_time() / self.frames.max(1) as u32
+    }
+}
+
+/// Small xorshift generator, so the workload doesn't depend on a random number crate.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    fn index(&mut self, len: usize) -> usize {
+        (self.next() % len.max(1) as u64) as usize
+    }
+}
+
+/// Generates the paint jobs of the workload in points for a screen of `screen_size` points.
+///
+/// The meshes use `texture_ids` in turn, or the egui texture if it is empty.
+pub fn generate_paint_jobs(
+    config: &StressConfig,
+    screen_size: egui::Vec2,
+    texture_ids: &[egui::TextureId],
+) -> Vec<egui::paint::ClippedMesh> {
+    let mut rng = Rng::new(config.seed);
+    let clip_rects: Vec<egui::Rect> = (0..config.clip_rect_count.max(1))
+        .map(|_| {
+            let min = egui::pos2(rng.range(0.0, screen_size.x), rng.range(0.0, screen_size.y));
+            let size = egui::vec2(
+                rng.range(1.0, screen_size.x / 2.0),
+                rng.range(1.0, screen_size.y / 2.0),
+            );
+            egui::Rect::from_min_size(min, size)
+        })
+        .collect();
+
+    (0..config.mesh_count)
+        .map(|i| {
+            let texture_id = if texture_ids.is_empty() {
+                egui::TextureId::Egui
+            } else {
+                texture_ids[i % texture_ids.len()]
+            };
+            let mut mesh = egui::paint::Mesh::with_texture(texture_id);
+            for _ in 0..config.quads_per_mesh {
+                let min = egui::pos2(rng.range(0.0, screen_size.x), rng.range(0.0, screen_size.y));
+                let size = egui::vec2(rng.range(1.0, 64.0), rng.range(1.0, 64.0));
+                let color = egui::Color32::from_rgba_premultiplied(
+                    rng.next() as u8,
+                    rng.next() as u8,
+                    rng.next() as u8,
+                    255,
+                );
+                let uv = if texture_ids.is_empty() {
+                    egui::Rect::from_min_max(egui::paint::WHITE_UV, egui::paint::WHITE_UV)
+                } else {
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0))
+                };
+                mesh.add_rect_with_uv(egui::Rect::from_min_size(min, size), uv, color);
+            }
+            let clip_rect = clip_rects[rng.index(clip_rects.len())];
+            egui::paint::ClippedMesh(clip_rect, mesh)
+        })
+        .collect()
+}
+
+/// Renders the workload described by `config` into an offscreen target and measures the render pass.
+///
+/// A new render pass is created for the run. Every frame waits for the GPU, so the measured times don't
+/// overlap.
+pub fn run(device: &wgpu::Device, queue: &wgpu::Queue, config: &StressConfig) -> StressReport {
+    let mut render_pass =
+        RenderPass::new(device, TARGET_FORMAT, 1).expect("the target format is supported");
+    let (width, height) = config.target_size;
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("egui_stress_target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TARGET_FORMAT,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+    });
+    let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // A white font texture, so meshes of the egui texture are visible.
+    render_pass.update_texture(
+        device,
+        queue,
+        &egui::Texture {
+            version: 0,
+            width: 1,
+            height: 1,
+            pixels: vec![255],
+        },
+    );
+
+    let mut rng = Rng::new(config.seed);
+    let texture_ids: Vec<egui::TextureId> = (0..config.texture_count)
+        .map(|_| {
+            let color = egui::Color32::from_rgba_premultiplied(
+                rng.next() as u8,
+                rng.next() as u8,
+                rng.next() as u8,
+                255,
+            );
+            render_pass.alloc_srgba_premultiplied(
+                (TEXTURE_SIZE, TEXTURE_SIZE),
+                &vec![color; TEXTURE_SIZE * TEXTURE_SIZE],
+            )
+        })
+        .collect();
+
+    let screen_descriptor = ScreenDescriptor {
+        physical_width: width,
+        physical_height: height,
+        scale_factor: 1.0,
+        viewport: None,
+        rotation: SurfaceRotation::Identity,
+    };
+    let paint_jobs = generate_paint_jobs(
+        config,
+        egui::vec2(width as f32, height as f32),
+        &texture_ids,
+    );
+
+    let mut report = StressReport {
+        frames: config.frames,
+        meshes: paint_jobs.len(),
+        vertices: paint_jobs.iter().map(|job| job.1.vertices.len()).sum(),
+        indices: paint_jobs.iter().map(|job| job.1.indices.len()).sum(),
+        ..Default::default()
+    };
+
+    for _ in 0..config.frames {
+        let start = Instant::now();
+        render_pass
+            .update_user_textures(device, queue)
+            .expect("the generated textures are valid");
+        render_pass
+            .update_buffers(device, queue, &paint_jobs, &screen_descriptor)
+            .expect("the generated paint jobs are valid");
+        let uploaded = Instant::now();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("egui_stress_encoder"),
+        });
+        render_pass
+            .execute(
+                &mut encoder,
+                &target_view,
+                None,
+                &paint_jobs,
+                &screen_descriptor,
+                Some(wgpu::Color::BLACK),
+            )
+            .expect("failed to render the generated paint jobs");
+        let command_buffer = encoder.finish();
+        let encoded = Instant::now();
+
+        queue.submit(Some(command_buffer));
+        device.poll(wgpu::Maintain::Wait);
+        let finished = Instant::now();
+
+        report.upload_time += uploaded - start;
+        report.encode_time += encoded - uploaded;
+        report.gpu_wait_time += finished - encoded;
+    }
+
+    let snapshot = render_pass.debug_snapshot();
+    report.vertex_buffer_bytes = snapshot.vertex_buffer_size;
+    report.index_buffer_bytes = snapshot.index_buffer_size;
+    report.texture_bytes = render_pass.texture_memory_usage();
+    report
+}
@@ -0,0 +1,393 @@
+//! Images larger than the maximum texture size of the device, split into several textures.
+
+use epi::TextureAllocator;
+
+use crate::{egui, RenderPass};
+
+/// Tile size supported by all devices that follow the WebGPU default limits.
+pub const DEFAULT_MAX_TILE_SIZE: usize = 8192;
+
+/// An image split into tiles that each fit into one texture. Created by [`RenderPass::alloc_tiled_image`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledImage {
+    size: (usize, usize),
+    tiles: Vec<ImageTile>,
+}
+
+/// A part of a [`TiledImage`] stored in its own texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageTile {
+    /// Texture of the tile.
+    pub texture_id: egui::TextureId,
+    /// Position of the top left pixel of the tile in the image.
+    pub offset: (usize, usize),
+    /// Width and height of the tile in pixels.
+    pub size: (usize, usize),
+}
+
+impl TiledImage {
+    /// Returns the width and height of the whole image in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    /// Returns the tiles row by row.
+    pub fn tiles(&self) -> &[ImageTile] {
+        &self.tiles
+    }
+
+    /// Returns the part of `rect` covered by `tile` if the whole image is drawn into `rect`.
+    pub fn tile_rect(&self, tile: &ImageTile, rect: egui::Rect) -> egui::Rect {
+        let scale_x = rect.width() / self.size.0 as f32;
+        let scale_y = rect.height() / self.size.1 as f32;
+        let min = rect.min
+            + egui::vec2(
+                tile.offset.0 as f32 * scale_x,
+                tile.offset.1 as f32 * scale_y,
+            );
+        let size = egui::vec2(tile.size.0 as f32 * scale_x, tile.size.1 as f32 * scale_y);
+        egui::Rect::from_min_size(min, size)
+    }
+
+    /// Returns the shapes that draw the whole image into `rect`, e.g. for `ui.painter().extend()`.
+    /// Tiles outside of `clip_rect` are skipped.
+    pub fn shapes(
+        &self,
+        rect: egui::Rect,
+        clip_rect: egui::Rect,
+        tint: egui::Color32,
+    ) -> Vec<egui::Shape> {
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        self.tiles
+            .iter()
+            .map(|tile| (tile, self.tile_rect(tile, rect)))
+            .filter(|(_, tile_rect)| tile_rect.intersects(clip_rect))
+            .map(|(tile, tile_rect)| {
+                let mut mesh = egui::paint::Mesh::with_texture(tile.texture_id);
+                mesh.add_rect_with_uv(tile_rect, uv, tint);
+                egui::Shape::mesh(mesh)
+            })
+            .collect()
+    }
+}
+
+impl RenderPass {
+    /// Splits an image into tiles of at most `max_tile_size` pixels per side and allocates a user texture for
+    /// each, like `epi::TextureAllocator::alloc_srgba_premultiplied`. Use this for images larger than the
+    /// maximum texture size of the device, e.g. with [`DEFAULT_MAX_TILE_SIZE`].
+    ///
+    /// The tiles are sampled separately, so linear filtering can show faint seams between them when the
+    /// image is magnified. Free the tiles with [`RenderPass::free_tiled_image`].
+    pub fn alloc_tiled_image(
+        &mut self,
+        size: (usize, usize),
+        srgba_pixels: &[egui::Color32],
+        max_tile_size: usize,
+    ) -> TiledImage {
+        let (width, height) = size;
+        assert_eq!(
+            srgba_pixels.len(),
+            width * height,
+            "image needs width * height pixels"
+        );
+        assert!(
+            max_tile_size > 0,
+            "tile size needs to be at least one pixel"
+        );
+
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(max_tile_size) {
+            for x in (0..width).step_by(max_tile_size) {
+                let tile_size = (max_tile_size.min(width - x), max_tile_size.min(height - y));
+                let pixels: Vec<egui::Color32> = (y..y + tile_size.1)
+                    .flat_map(|row| {
+                        let start = row * width + x;
+                        srgba_pixels[start..start + tile_size.0].iter().copied()
+                    })
+                    .collect();
+                tiles.push(ImageTile {
+                    texture_id: self.alloc_srgba_premultiplied(tile_size, &pixels),
+                    offset: (x, y),
+                    size: tile_size,
+                });
+            }
+        }
+
+        TiledImage { size, tiles }
+    }
+
+    /// Frees the textures of all tiles of `image`.
+    pub fn free_tiled_image(&mut self, image: &TiledImage) {
+        for tile in &image.tiles {
+            self.free(tile.texture_id);
+        }
+    }
+}
+
+/// Identifies a tile of a [`StreamedImage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    /// Mip level of the tile. Level `n` is the image downscaled by `2^n`.
+    pub level: u32,
+    /// Column of the tile in its level.
+    pub x: usize,
+    /// Row of the tile in its level.
+    pub y: usize,
+}
+
+/// Loads the pixels of a tile of a [`StreamedImage`]. Gets the key and the width and height of the tile and
+/// returns its srgba premultiplied pixels row by row, or `None` if they are not available yet, e.g. because
+/// they are still being loaded in the background. Tiles that returned `None` are requested again by the next update.
+pub type TileProvider = dyn FnMut(TileKey, (usize, usize)) -> Option<Vec<egui::Color32>>;
+
+/// A tile of a [`StreamedImage`] that has a texture.
+struct ResidentTile {
+    key: TileKey,
+    texture_id: egui::TextureId,
+    last_used: u64,
+}
+
+/// A very large image, e.g. a map or a medical scan, of which only the tiles in view are kept in textures.
+/// Update it with [`RenderPass::update_streamed_image`] every frame before drawing it.
+pub struct StreamedImage {
+    size: (usize, usize),
+    tile_size: usize,
+    level_count: u32,
+    provider: Box<TileProvider>,
+    max_resident_tiles: usize,
+    resident: Vec<ResidentTile>,
+    level: u32,
+    frame: u64,
+}
+
+impl StreamedImage {
+    /// Creates a streamed image of `size` pixels made of tiles of `tile_size` pixels per side, which are
+    /// loaded by `provider`. Zoomed out views use downscaled mip levels, down to one that fits into a
+    /// single tile.
+    pub fn new(
+        size: (usize, usize),
+        tile_size: usize,
+        provider: impl FnMut(TileKey, (usize, usize)) -> Option<Vec<egui::Color32>> + 'static,
+    ) -> Self {
+        assert!(tile_size > 0, "tile size needs to be at least one pixel");
+        let mut level_count = 1;
+        while size.0.max(size.1) > tile_size << (level_count - 1) {
+            level_count += 1;
+        }
+        Self {
+            size,
+            tile_size,
+            level_count,
+            provider: Box::new(provider),
+            max_resident_tiles: 256,
+            resident: Vec::new(),
+            level: 0,
+            frame: 0,
+        }
+    }
+
+    /// Returns the width and height of the image in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    /// Returns the number of mip levels.
+    pub fn level_count(&self) -> u32 {
+        self.level_count
+    }
+
+    /// Returns the number of tiles that currently have a texture.
+    pub fn resident_tile_count(&self) -> usize {
+        self.resident.len()
+    }
+
+    /// Sets the number of tiles kept in textures. The least recently visible tiles are freed first.
+    /// Tiles in view are never freed, so the limit can be exceeded for a frame. Defaults to 256.
+    pub fn set_max_resident_tiles(&mut self, max_resident_tiles: usize) {
+        self.max_resident_tiles = max_resident_tiles;
+    }
+
+    /// Returns the width and height of the tile `key` in pixels.
+    fn tile_size(&self, key: TileKey) -> (usize, usize) {
+        let scale = 1 << key.level;
+        let level_width = (self.size.0 + scale - 1) / scale;
+        let level_height = (self.size.1 + scale - 1) / scale;
+        (
+            self.tile_size.min(level_width - key.x * self.tile_size),
+            self.tile_size.min(level_height - key.y * self.tile_size),
+        )
+    }
+
+    /// Returns the area of the tile `key` in image pixels.
+    fn tile_area(&self, key: TileKey) -> egui::Rect {
+        let scale = (1usize << key.level) as f32;
+        let (width, height) = self.tile_size(key);
+        let min = egui::pos2(
+            (key.x * self.tile_size) as f32 * scale,
+            (key.y * self.tile_size) as f32 * scale,
+        );
+        let max = egui::pos2(
+            (min.x + width as f32 * scale).min(self.size.0 as f32),
+            (min.y + height as f32 * scale).min(self.size.1 as f32),
+        );
+        egui::Rect::from_min_max(min, max)
+    }
+
+    /// Returns the keys of the tiles of `level` that overlap `area`, given in image pixels.
+    fn tiles_in(&self, level: u32, area: egui::Rect) -> Vec<TileKey> {
+        let level_tile_size = self.tile_size << level;
+        let columns = (self.size.0 + level_tile_size - 1) / level_tile_size;
+        let rows = (self.size.1 + level_tile_size - 1) / level_tile_size;
+        let span = level_tile_size as f32;
+        let first_x = (area.min.x / span).floor().max(0.0) as usize;
+        let first_y = (area.min.y / span).floor().max(0.0) as usize;
+        let end_x = ((area.max.x / span).ceil().max(0.0) as usize).min(columns);
+        let end_y = ((area.max.y / span).ceil().max(0.0) as usize).min(rows);
+        (first_y..end_y)
+            .flat_map(|y| (first_x..end_x).map(move |x| TileKey { level, x, y }))
+            .collect()
+    }
+
+    /// Returns the shapes that draw the image into `rect`, e.g. for `ui.painter().extend()`. Tiles of the
+    /// current level that are not loaded yet are covered by coarser tiles where available.
+    pub fn shapes(
+        &self,
+        rect: egui::Rect,
+        clip_rect: egui::Rect,
+        tint: egui::Color32,
+    ) -> Vec<egui::Shape> {
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let scale_x = rect.width() / self.size.0 as f32;
+        let scale_y = rect.height() / self.size.1 as f32;
+
+        let mut tiles: Vec<&ResidentTile> = self
+            .resident
+            .iter()
+            .filter(|tile| tile.key.level >= self.level)
+            .collect();
+        // Coarse tiles first, so the finer ones are drawn on top of them.
+        tiles.sort_by_key(|tile| std::cmp::Reverse(tile.key.level));
+
+        tiles
+            .into_iter()
+            .filter_map(|tile| {
+                let area = self.tile_area(tile.key);
+                let tile_rect = egui::Rect::from_min_max(
+                    rect.min + egui::vec2(area.min.x * scale_x, area.min.y * scale_y),
+                    rect.min + egui::vec2(area.max.x * scale_x, area.max.y * scale_y),
+                );
+                if !tile_rect.intersects(clip_rect) {
+                    return None;
+                }
+                let mut mesh = egui::paint::Mesh::with_texture(tile.texture_id);
+                mesh.add_rect_with_uv(tile_rect, uv, tint);
+                Some(egui::Shape::mesh(mesh))
+            })
+            .collect()
+    }
+}
+
+impl RenderPass {
+    /// Loads the tiles of `image` needed to show `visible`, the part of the image in view in image pixels,
+    /// at `zoom` screen pixels per image pixel, and frees the least recently visible tiles above the limit.
+    ///
+    /// The mip level is chosen so that a tile pixel covers at least one screen pixel. The loaded tiles are
+    /// uploaded by `update_user_textures()`, so an upload budget spreads them over several frames.
+    pub fn update_streamed_image(
+        &mut self,
+        image: &mut StreamedImage,
+        visible: egui::Rect,
+        zoom: f32,
+    ) {
+        image.frame += 1;
+        let level = if zoom > 0.0 {
+            (1.0 / zoom).log2().floor().max(0.0) as u32
+        } else {
+            image.level_count - 1
+        };
+        image.level = level.min(image.level_count - 1);
+
+        for key in image.tiles_in(image.level, visible) {
+            if let Some(tile) = image.resident.iter_mut().find(|tile| tile.key == key) {
+                tile.last_used = image.frame;
+                continue;
+            }
+            let size = image.tile_size(key);
+            if let Some(pixels) = (image.provider)(key, size) {
+                image.resident.push(ResidentTile {
+                    key,
+                    texture_id: self.alloc_srgba_premultiplied(size, &pixels),
+                    last_used: image.frame,
+                });
+            }
+        }
+
+        if image.resident.len() > image.max_resident_tiles {
+            image.resident.sort_by_key(|tile| tile.last_used);
+            let excess = image.resident.len() - image.max_resident_tiles;
+            let evictable = image
+                .resident
+                .iter()
+                .take(excess)
+                .take_while(|tile| tile.last_used != image.frame)
+                .count();
+            for tile in image.resident.drain(..evictable) {
+                self.free(tile.texture_id);
+            }
+        }
+    }
+
+    /// Frees the textures of all loaded tiles of `image`.
+    pub fn free_streamed_image(&mut self, image: &mut StreamedImage) {
+        for tile in image.resident.drain(..) {
+            self.free(tile.texture_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamedImage, TileKey};
+    use crate::egui;
+
+    fn area(min: (f32, f32), max: (f32, f32)) -> egui::Rect {
+        egui::Rect::from_min_max(egui::pos2(min.0, min.1), egui::pos2(max.0, max.1))
+    }
+
+    fn keys(level: u32, tiles: &[(usize, usize)]) -> Vec<TileKey> {
+        tiles
+            .iter()
+            .map(|&(x, y)| TileKey { level, x, y })
+            .collect()
+    }
+
+    #[test]
+    fn tiles_in_returns_the_overlapping_tiles() {
+        let image = StreamedImage::new((1000, 600), 256, |_, _| None);
+        assert_eq!(image.level_count(), 3);
+        assert_eq!(
+            image.tiles_in(0, area((0.0, 0.0), (1000.0, 600.0))).len(),
+            4 * 3
+        );
+        assert_eq!(
+            image.tiles_in(0, area((300.0, 300.0), (520.0, 520.0))),
+            keys(0, &[(1, 1), (2, 1), (1, 2), (2, 2)])
+        );
+    }
+
+    #[test]
+    fn tiles_in_clamps_to_the_image() {
+        let image = StreamedImage::new((1000, 600), 256, |_, _| None);
+        assert_eq!(
+            image.tiles_in(1, area((-100.0, -100.0), (100.0, 100.0))),
+            keys(1, &[(0, 0)])
+        );
+        assert_eq!(
+            image.tiles_in(1, area((900.0, 0.0), (5000.0, 5000.0))),
+            keys(1, &[(1, 0), (1, 1)])
+        );
+        assert!(image
+            .tiles_in(0, area((2000.0, 0.0), (3000.0, 100.0)))
+            .is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! GPU timestamps around the egui render pass, to tell the time of the UI apart from the rest of the frame.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::readback::noop_waker;
+use crate::{BackendError, RenderPass};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+enum TimerState {
+    /// The timestamps can be written by the next pass.
+    Idle,
+    /// The timestamps were resolved into the buffer, whose encoder is submitted before the next frame.
+    Resolved,
+    /// The buffer is being mapped to read the timestamps.
+    Mapping(MapFuture),
+}
+
+/// The query set and readback buffer of the timestamps of one pass at a time.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    state: Mutex<TimerState>,
+    gpu_time: Option<Duration>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, period: f32) -> Self {
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("egui_timestamp_buffer"),
+                size: 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period,
+            state: Mutex::new(TimerState::Idle),
+            gpu_time: None,
+        }
+    }
+
+    fn state(&mut self) -> &mut TimerState {
+        self.state.get_mut().expect("gpu timer poisoned")
+    }
+
+    /// Writes the start timestamp if the previous measurement was read. Returns `false` if the pass isn't timed.
+    pub(crate) fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) -> bool {
+        if !matches!(self.state(), TimerState::Idle) {
+            return false;
+        }
+        encoder.write_timestamp(&self.query_set, 0);
+        true
+    }
+
+    /// Writes the end timestamp and resolves both into the buffer.
+    pub(crate) fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.buffer, 0);
+        *self.state() = TimerState::Resolved;
+    }
+
+    /// Maps the buffer of the last timed pass, whose encoder has been submitted by now, and reads the timestamps
+    /// once the mapping finished.
+    pub(crate) fn poll(&mut self, device: &wgpu::Device) {
+        if matches!(self.state(), TimerState::Resolved) {
+            let mapping = Box::pin(self.buffer.slice(..).map_async(wgpu::MapMode::Read));
+            *self.state() = TimerState::Mapping(mapping);
+        }
+        let mapping = match self.state.get_mut().expect("gpu timer poisoned") {
+            TimerState::Mapping(mapping) => mapping,
+            _ => return,
+        };
+        device.poll(wgpu::Maintain::Poll);
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let result = match mapping.as_mut().poll(&mut context) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return,
+        };
+        if result.is_ok() {
+            let timestamps: Vec<u64> = {
+                let data = self.buffer.slice(..).get_mapped_range();
+                bytemuck::cast_slice(&data).to_vec()
+            };
+            self.buffer.unmap();
+            let ticks = timestamps[1].saturating_sub(timestamps[0]);
+            self.gpu_time = Some(Duration::from_nanos(
+                (ticks as f64 * self.period as f64) as u64,
+            ));
+        }
+        *self.state() = TimerState::Idle;
+    }
+}
+
+impl RenderPass {
+    /// Measures the GPU time of the egui render pass of `execute()` with timestamp queries, which needs
+    /// `wgpu::Features::TIMESTAMP_QUERY`. `timestamp_period` is the number of nanoseconds per timestamp tick,
+    /// as returned by `wgpu::Adapter::get_timestamp_period()`. `None` disables the measurement.
+    ///
+    /// Returns `BackendError::MissingFeatures` if the device was created without `wgpu::Features::TIMESTAMP_QUERY`.
+    pub fn set_gpu_timing(
+        &mut self,
+        device: &wgpu::Device,
+        timestamp_period: Option<f32>,
+    ) -> Result<(), BackendError> {
+        if timestamp_period.is_some() && !self.capabilities.timestamp_query {
+            return Err(BackendError::MissingFeatures(
+                wgpu::Features::TIMESTAMP_QUERY,
+            ));
+        }
+        self.gpu_timer = timestamp_period.map(|period| GpuTimer::new(device, period));
+        Ok(())
+    }
+
+    /// Returns the GPU time of the last measured egui render pass, see [`RenderPass::set_gpu_timing`].
+    ///
+    /// The timestamps are read back without waiting for the GPU by the `update_buffers()` calls of the next
+    /// frames, so the time lags a few frames behind and passes are skipped while a measurement is in flight.
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref().and_then(|timer| timer.gpu_time)
+    }
+}
@@ -0,0 +1,120 @@
+//! Keeps the screen descriptor and swap chain of a winit window in sync with its size. Needs the `winit` feature.
+
+use winit::event::{Event, WindowEvent};
+use winit::window::Window;
+
+use crate::{ScreenDescriptor, SurfaceRotation};
+
+impl ScreenDescriptor {
+    /// Returns the screen descriptor of the whole inner area of `window`, with its current physical size and
+    /// scale factor.
+    pub fn from_window(window: &Window) -> Self {
+        let size = window.inner_size();
+        Self {
+            physical_width: size.width,
+            physical_height: size.height,
+            scale_factor: window.scale_factor() as f32,
+            viewport: None,
+            rotation: SurfaceRotation::Identity,
+        }
+    }
+}
+
+/// The swap chain of a window, created again with the new size when the window is resized or moved to a
+/// monitor with another scale factor.
+pub struct WindowSurface {
+    surface: wgpu::Surface,
+    descriptor: wgpu::SwapChainDescriptor,
+    swap_chain: Option<wgpu::SwapChain>,
+    scale_factor: f64,
+}
+
+impl WindowSurface {
+    /// Wraps the surface of `window`, whose swap chain uses `format` and `present_mode`.
+    pub fn new(
+        surface: wgpu::Surface,
+        window: &Window,
+        format: wgpu::TextureFormat,
+        present_mode: wgpu::PresentMode,
+    ) -> Self {
+        let size = window.inner_size();
+        Self {
+            surface,
+            descriptor: wgpu::SwapChainDescriptor {
+                usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+                format,
+                width: size.width,
+                height: size.height,
+                present_mode,
+            },
+            swap_chain: None,
+            scale_factor: window.scale_factor(),
+        }
+    }
+
+    /// Returns the format of the swap chain.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.descriptor.format
+    }
+
+    /// Returns the screen descriptor matching the current size of the swap chain.
+    pub fn screen_descriptor(&self) -> ScreenDescriptor {
+        ScreenDescriptor {
+            physical_width: self.descriptor.width,
+            physical_height: self.descriptor.height,
+            scale_factor: self.scale_factor as f32,
+            viewport: None,
+            rotation: SurfaceRotation::Identity,
+        }
+    }
+
+    /// Tracks the size and scale factor of the window. The swap chain is created again by the next
+    /// `get_current_frame()`. Events of other windows should be filtered out before.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        let size = match event {
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => *size,
+            Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    },
+                ..
+            } => {
+                self.scale_factor = *scale_factor;
+                **new_inner_size
+            }
+            _ => return,
+        };
+        if (size.width, size.height) != (self.descriptor.width, self.descriptor.height) {
+            self.descriptor.width = size.width;
+            self.descriptor.height = size.height;
+            self.swap_chain = None;
+        }
+    }
+
+    /// Returns the next frame of the swap chain, creating it first if needed. Returns `None` while the window
+    /// is minimized or if the frame isn't available, in which case it should be skipped. An outdated or lost
+    /// swap chain is created again by the next call.
+    pub fn get_current_frame(&mut self, device: &wgpu::Device) -> Option<wgpu::SwapChainFrame> {
+        if self.descriptor.width == 0 || self.descriptor.height == 0 {
+            return None;
+        }
+        let surface = &self.surface;
+        let descriptor = &self.descriptor;
+        let swap_chain = self
+            .swap_chain
+            .get_or_insert_with(|| device.create_swap_chain(surface, descriptor));
+        match swap_chain.get_current_frame() {
+            Ok(frame) => Some(frame),
+            Err(wgpu::SwapChainError::Outdated) | Err(wgpu::SwapChainError::Lost) => {
+                self.swap_chain = None;
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}